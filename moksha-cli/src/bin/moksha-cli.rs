@@ -1,6 +1,7 @@
-use clap::{Parser, Subcommand};
+use bitcoin_hashes::sha256;
+use clap::{ArgGroup, Parser, Subcommand};
 use console::{style, Term};
-use dialoguer::{theme::ColorfulTheme, Confirm, Select};
+use dialoguer::{theme::ColorfulTheme, Confirm, Password, Select};
 use moksha_core::primitives::{
     CurrencyUnit, PaymentMethod, PostMeltBtcOnchainResponse, PostMintQuoteBolt11Response,
     PostMintQuoteBtcOnchainResponse,
@@ -15,6 +16,7 @@ use mokshacli::cli::{self, choose_mint, get_mints_with_balance};
 use num_format::{Locale, ToFormattedString};
 use qrcode::render::unicode;
 use qrcode::QrCode;
+use secp256k1::PublicKey;
 
 use std::path::PathBuf;
 use std::str::FromStr;
@@ -43,19 +45,60 @@ enum Command {
     PayOnchain { address: String, amount: u64 },
 
     /// Send tokens
-    Send { amount: u64 },
+    #[command(group(ArgGroup::new("conditions").multiple(true).args(["lock", "hash"])))]
+    Send {
+        amount: u64,
+
+        /// Lock the tokens to this pubkey (NUT-11)
+        #[clap(long)]
+        lock: Option<PublicKey>,
+
+        /// Lock the tokens to the preimage of this sha256 hash (NUT-14)
+        #[clap(long)]
+        hash: Option<sha256::Hash>,
+
+        /// Unix timestamp after which the lock expires
+        #[clap(long, requires = "conditions")]
+        locktime: Option<u64>,
+
+        /// Pubkeys that can spend the tokens after the locktime
+        #[clap(long, requires = "locktime")]
+        refund: Vec<PublicKey>,
+    },
 
     /// Receive tokens
-    Receive { token: String },
+    Receive {
+        token: String,
+
+        /// Hex encoded preimage that unlocks HTLC tokens (NUT-14)
+        #[clap(long)]
+        preimage: Option<String>,
+    },
 
     /// Show local balance
     Balance,
 
+    /// Show the pubkey other wallets can lock tokens to
+    Pubkey,
+
     /// Show version and configuration
     Info,
 
     /// Add a new mint to the wallet
     AddMint { mint_url: Url },
+
+    /// Restore tokens from seed words
+    Restore {
+        mint_url: Url,
+
+        /// Number of counters that are requested from the mint at once
+        #[clap(long, default_value_t = 100)]
+        batch_size: u32,
+
+        /// Stop after this many batches in a row without any tokens
+        #[clap(long, default_value_t = 3)]
+        max_empty_batches: u32,
+    },
 }
 
 #[tokio::main]
@@ -76,22 +119,40 @@ async fn main() -> anyhow::Result<()> {
     let term = Term::stdout();
     let localstore = SqliteLocalStore::with_path(db_path.clone()).await?;
     let client = CrossPlatformHttpClient::new();
-    let wallet = moksha_wallet::wallet::WalletBuilder::default()
+    let mut builder = moksha_wallet::wallet::WalletBuilder::default()
         .with_client(client)
-        .with_localstore(localstore)
-        .build()
-        .await
-        .map_err(|e| {
-            if matches!(
-                e,
-                moksha_wallet::error::MokshaWalletError::UnsupportedApiVersion
-            ) {
+        .with_localstore(localstore);
+
+    if let Command::Restore { .. } = cli.command {
+        let seed_words = Password::with_theme(&ColorfulTheme::default())
+            .with_prompt("Enter your seed words")
+            .interact()?;
+        builder = builder.with_seed_words(
+            seed_words
+                .split_whitespace()
+                .collect::<Vec<&str>>()
+                .join(" "),
+        );
+    }
+
+    let wallet = builder.build().await.map_err(|e| {
+        match e {
+            moksha_wallet::error::MokshaWalletError::UnsupportedApiVersion => {
                 term.write_line("Error: Mint does not support /v1 api")
                     .expect("write_line failed");
                 std::process::exit(1);
             }
-            e
-        })?;
+            moksha_wallet::error::MokshaWalletError::SeedMismatch => {
+                term.write_line(
+                    "Error: This wallet uses different seed words. Use --db-dir to restore into a new wallet",
+                )
+                .expect("write_line failed");
+                std::process::exit(1);
+            }
+            _ => {}
+        }
+        e
+    })?;
 
     match cli.command {
         Command::AddMint { mint_url } => {
@@ -100,6 +161,27 @@ async fn main() -> anyhow::Result<()> {
                 .await?;
             term.write_line("Mint added successfully ")?;
         }
+        Command::Restore {
+            mint_url,
+            batch_size,
+            max_empty_batches,
+        } => {
+            if !wallet.get_mint_urls().await?.contains(&mint_url) {
+                wallet
+                    .add_mint_keysets(&mint_url, "sat".to_string())
+                    .await?;
+            }
+
+            let progress_bar = cli::progress_bar()?;
+            progress_bar.set_message("Restoring tokens ...");
+
+            let restored = wallet.restore(batch_size, max_empty_batches).await?;
+            progress_bar.finish_with_message(format!(
+                "Restored {} (sat)\n",
+                restored.total_amount().to_formatted_string(&Locale::en)
+            ));
+            cli::show_total_balance(&wallet).await?;
+        }
         Command::Info => {
             let wallet_version = style(env!("CARGO_PKG_VERSION")).cyan();
             let mint_urls = wallet.get_mint_urls().await?;
@@ -117,7 +199,7 @@ async fn main() -> anyhow::Result<()> {
             }
         }
         // checks if the mints keyset is already in the wallet, if not it adds it and then imports the tokens
-        Command::Receive { token } => {
+        Command::Receive { token, preimage } => {
             let token: TokenV3 = TokenV3::from_str(&token)?;
             let mint_urls = wallet.get_mint_urls().await?;
             let currency = match &token.currency_unit {
@@ -163,10 +245,23 @@ async fn main() -> anyhow::Result<()> {
                 .get_active(&token_mint_url, currency)
                 .expect("no active keyset found");
 
-            wallet.receive_tokens(wallet_keyset, &token).await?;
+            match preimage {
+                Some(preimage) => {
+                    wallet
+                        .receive_htlc_tokens(wallet_keyset, &token, &preimage)
+                        .await?
+                }
+                None => wallet.receive_tokens(wallet_keyset, &token).await?,
+            }
             cli::show_total_balance(&wallet).await?;
         }
-        Command::Send { amount } => {
+        Command::Send {
+            amount,
+            lock,
+            hash,
+            locktime,
+            refund,
+        } => {
             let currency_unit = CurrencyUnit::Sat;
             let mint_url = choose_mint(&wallet, &currency_unit).await?;
 
@@ -183,12 +278,37 @@ async fn main() -> anyhow::Result<()> {
                 .expect("no active keyset found");
 
             term.write_line(&format!("Using tokens from mint: {mint_url}"))?;
-            let result = wallet.send_tokens(wallet_keyset, amount).await?;
+            let result = match (hash, lock) {
+                (Some(hash), lock) => {
+                    term.write_line(&format!("Locking tokens to hash: {hash}"))?;
+                    wallet
+                        .send_htlc_tokens(
+                            wallet_keyset,
+                            amount,
+                            hash,
+                            lock.into_iter().collect(),
+                            locktime,
+                            refund,
+                        )
+                        .await?
+                }
+                (None, Some(pubkey)) => {
+                    term.write_line(&format!("Locking tokens to pubkey: {pubkey}"))?;
+                    wallet
+                        .send_locked_tokens(wallet_keyset, amount, pubkey, locktime, refund)
+                        .await?
+                }
+                (None, None) => wallet.send_tokens(wallet_keyset, amount).await?,
+            };
             let tokens: String = result.try_into()?;
 
             term.write_line(&format!("Result {amount} (sat):\n{tokens}"))?;
             cli::show_total_balance(&wallet).await?;
         }
+        Command::Pubkey => {
+            let pubkey = wallet.get_receive_pubkey().await?;
+            term.write_line(&format!("{pubkey}"))?;
+        }
         Command::Balance => {
             let total_balance = wallet.get_balance().await?;
             if total_balance > 0 {
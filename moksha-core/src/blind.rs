@@ -1,8 +1,8 @@
 //! This module defines the `BlindedMessage` and `BlindedSignature` structs, which are used for representing blinded messages and signatures in Cashu as described in [Nut-00](https://github.com/cashubtc/nuts/blob/main/00.md)
 //!
-//! The `BlindedMessage` struct represents a blinded message, with an `amount` field for the amount in satoshis and a `b_` field for the public key of the blinding factor.
+//! The `BlindedMessage` struct represents a blinded message, with an `amount` field for the amount in satoshis and a `b_` field for the public key of the blinding factor and an optional `witness` field.
 //!
-//! The `BlindedSignature` struct represents a blinded signature, with an `amount` field for the amount in satoshis, a `c_` field for the public key of the blinding factor, and an optional `id` field for the ID of the signature.
+//! The `BlindedSignature` struct represents a blinded signature, with an `amount` field for the amount in satoshis, a `c_` field for the public key of the blinding factor, an `id` field for the ID of the keyset and an optional `dleq` field that proves the signature was created with the keyset's private key ([Nut-12](https://github.com/cashubtc/nuts/blob/main/12.md)).
 //!
 //! Both the `BlindedMessage` and `BlindedSignature` structs are serializable and deserializable using serde.
 //!
@@ -10,10 +10,12 @@
 
 use secp256k1::{PublicKey, SecretKey};
 use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
 use utoipa::ToSchema;
 
-use crate::error::MokshaCoreError;
+use crate::{error::MokshaCoreError, secret::Witness};
 
+#[skip_serializing_none]
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct BlindedSignature {
     pub amount: u64,
@@ -21,8 +23,19 @@ pub struct BlindedSignature {
     #[schema(value_type=String)]
     pub c_: PublicKey,
     pub id: String,
+    pub dleq: Option<BlindSignatureDleq>,
 }
 
+/// DLEQ proof `(e, s)` that `C_ = a*B_` and `A = a*G` share the same private key `a` (Nut-12)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+pub struct BlindSignatureDleq {
+    #[schema(value_type=String)]
+    pub e: SecretKey,
+    #[schema(value_type=String)]
+    pub s: SecretKey,
+}
+
+#[skip_serializing_none]
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct BlindedMessage {
     pub amount: u64,
@@ -31,6 +44,9 @@ pub struct BlindedMessage {
     pub b_: PublicKey,
     // FIXME use KeysetId
     pub id: String,
+    /// only required for outputs of inputs with the `SIG_ALL` flag (Nut-11)
+    #[schema(value_type = Option<String>)]
+    pub witness: Option<Witness>,
 }
 
 #[derive(Debug, Clone)]
@@ -40,8 +40,18 @@
 //!```
 //! If true, C must have originated from Bob
 //!
+//! Bob additionally proves with a DLEQ proof (e, s) that C' was signed with the private key of A ([Nut-12](https://github.com/cashubtc/nuts/blob/main/12.md)):
+//!```python
+//! R1 = p*G, R2 = p*B'
+//! e = hash(R1, R2, A, C')
+//! s = p + e*a
+//!```
 //!
-use crate::{blind::BlindingFactor, error::MokshaCoreError};
+use crate::{
+    blind::{BlindSignatureDleq, BlindingFactor},
+    error::MokshaCoreError,
+    proof::ProofDleq,
+};
 use bitcoin_hashes::{sha256, Hash};
 use secp256k1::{All, PublicKey, Scalar, Secp256k1, SecretKey};
 use std::iter::once;
@@ -74,7 +84,7 @@ impl Dhke {
 
     /// The domain separator is b"Secp256k1_HashToCurve_Cashu_" or
     /// bytes.fromhex("536563703235366b315f48617368546f43757276655f43617368755f").
-    fn hash_to_curve(message: &[u8]) -> Result<PublicKey, MokshaCoreError> {
+    pub fn hash_to_curve(message: &[u8]) -> Result<PublicKey, MokshaCoreError> {
         let msg_to_hash = sha256::Hash::hash(&[b"Secp256k1_HashToCurve_Cashu_", message].concat());
         (0..2u32.pow(16))
             .map(|counter| sha256::Hash::hash(&[&msg_to_hash[..], &counter.to_le_bytes()].concat()))
@@ -101,9 +111,82 @@ impl Dhke {
         Ok(b)
     }
 
-    pub fn step2_bob(&self, b: PublicKey, a: &SecretKey) -> Result<PublicKey, MokshaCoreError> {
-        b.mul_tweak(&self.secp, &Scalar::from(*a))
-            .map_err(MokshaCoreError::Secp256k1Error)
+    /// Signs the blinded message and returns `C'` together with a DLEQ proof that it was signed with `a`
+    pub fn step2_bob(
+        &self,
+        b: PublicKey,
+        a: &SecretKey,
+    ) -> Result<(PublicKey, BlindSignatureDleq), MokshaCoreError> {
+        let nonce = SecretKey::new(&mut rand::thread_rng());
+        self.step2_bob_with_nonce(b, a, &nonce)
+    }
+
+    fn step2_bob_with_nonce(
+        &self,
+        b: PublicKey,
+        a: &SecretKey,
+        p: &SecretKey,
+    ) -> Result<(PublicKey, BlindSignatureDleq), MokshaCoreError> {
+        let c_ = b.mul_tweak(&self.secp, &Scalar::from(*a))?;
+        let r1 = p.public_key(&self.secp);
+        let r2 = b.mul_tweak(&self.secp, &Scalar::from(*p))?;
+        let e = Self::hash_e(&[r1, r2, a.public_key(&self.secp), c_])?;
+        let s = e
+            .mul_tweak(&Scalar::from(*a))?
+            .add_tweak(&Scalar::from(*p))?;
+        Ok((c_, BlindSignatureDleq { e, s }))
+    }
+
+    /// Hashes the hex encoded uncompressed points with sha256 as described in Nut-12
+    pub fn hash_e(points: &[PublicKey]) -> Result<SecretKey, MokshaCoreError> {
+        let e = points
+            .iter()
+            .map(|point| hex::encode(point.serialize_uncompressed()))
+            .collect::<String>();
+        Ok(SecretKey::from_slice(
+            &sha256::Hash::hash(e.as_bytes()).to_byte_array(),
+        )?)
+    }
+
+    /// Verifies the DLEQ proof of a blinded signature by computing `R1 = s*G - e*A` and `R2 = s*B' - e*C'`
+    pub fn verify_dleq(
+        &self,
+        b_: PublicKey,
+        c_: PublicKey,
+        a: PublicKey,
+        dleq: &BlindSignatureDleq,
+    ) -> Result<bool, MokshaCoreError> {
+        let e = Scalar::from(dleq.e);
+        let r1 = dleq
+            .s
+            .public_key(&self.secp)
+            .combine(&a.mul_tweak(&self.secp, &e)?.negate(&self.secp))?;
+        let r2 = b_
+            .mul_tweak(&self.secp, &Scalar::from(dleq.s))?
+            .combine(&c_.mul_tweak(&self.secp, &e)?.negate(&self.secp))?;
+        Ok(Self::hash_e(&[r1, r2, a, c_])? == dleq.e)
+    }
+
+    /// Verifies the DLEQ proof of an unblinded signature by reconstructing `B' = Y + r*G` and `C' = C + r*A`
+    pub fn verify_proof_dleq(
+        &self,
+        secret_msg: impl Into<String>,
+        c: PublicKey,
+        a: PublicKey,
+        dleq: &ProofDleq,
+    ) -> Result<bool, MokshaCoreError> {
+        let y = Self::hash_to_curve(secret_msg.into().as_bytes())?;
+        let b_ = y.combine(&dleq.r.public_key(&self.secp))?;
+        let c_ = c.combine(&a.mul_tweak(&self.secp, &Scalar::from(dleq.r))?)?;
+        self.verify_dleq(
+            b_,
+            c_,
+            a,
+            &BlindSignatureDleq {
+                e: dleq.e,
+                s: dleq.s,
+            },
+        )
     }
 
     pub fn step3_alice(
@@ -146,6 +229,7 @@ mod tests {
     use crate::{
         blind::BlindingFactor,
         dhke::{public_key_from_hex, Dhke},
+        proof::ProofDleq,
     };
     use anyhow::Ok;
     use pretty_assertions::assert_eq;
@@ -219,7 +303,7 @@ mod tests {
 
         let a = pk_from_hex("0000000000000000000000000000000000000000000000000000000000000001");
 
-        let c = dhke.step2_bob(pub_key, &a)?;
+        let (c, _) = dhke.step2_bob(pub_key, &a)?;
         let c_str = c.to_string();
         assert_eq!(
             "025cc16fe33b953e2ace39653efb3e7a7049711ae1d8a2f7a9108753f1cdea742b".to_string(),
@@ -251,6 +335,51 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_hash_e() -> anyhow::Result<()> {
+        let r1 = public_key_from_hex(
+            "020000000000000000000000000000000000000000000000000000000000000001",
+        );
+        let c_ = public_key_from_hex(
+            "02a9acc1e48c25eeeb9289b5031cc57da9fe72f3fe2861d264bdc074209b107ba2",
+        );
+
+        let e = Dhke::hash_e(&[r1, r1, r1, c_])?;
+        assert_eq!(
+            "a4dc034b74338c28c6bc3ea49731f2a24440fc7c4affc08b31a93fc9fbe6401e",
+            e.display_secret().to_string()
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_dleq() -> anyhow::Result<()> {
+        let dhke = Dhke::new();
+        let a = pk_from_hex("0000000000000000000000000000000000000000000000000000000000000001");
+        let A = a.public_key(&dhke.secp);
+        let r: BlindingFactor =
+            "0000000000000000000000000000000000000000000000000000000000000002".try_into()?;
+
+        let B_ = dhke.step1_alice("test_message", &r)?;
+        let (C_, dleq) = dhke.step2_bob(B_, &a)?;
+        assert!(dhke.verify_dleq(B_, C_, A, &dleq)?);
+
+        let C = dhke.step3_alice(C_, r.clone(), A)?;
+        let proof_dleq = ProofDleq {
+            e: dleq.e,
+            s: dleq.s,
+            r: r.to_secret_key(),
+        };
+        assert!(dhke.verify_proof_dleq("test_message", C, A, &proof_dleq)?);
+
+        // a signature created with a different key must not verify against A
+        let other = pk_from_hex("0000000000000000000000000000000000000000000000000000000000000003");
+        let (C_, dleq) = dhke.step2_bob(B_, &other)?;
+        assert!(!dhke.verify_dleq(B_, C_, A, &dleq)?);
+        Ok(())
+    }
+
     #[test]
     #[allow(non_snake_case)]
     fn test_verify() -> anyhow::Result<()> {
@@ -277,7 +406,7 @@ mod tests {
         // Generate a shared secret
         let secret_msg = "test";
         let B_ = dhke.step1_alice(secret_msg, &blinding_factor)?;
-        let C_ = dhke.step2_bob(B_, &a)?;
+        let (C_, _) = dhke.step2_bob(B_, &a)?;
         let C = dhke.step3_alice(C_, blinding_factor, A)?;
 
         // Verify the shared secret
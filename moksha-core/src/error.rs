@@ -37,4 +37,16 @@ pub enum MokshaCoreError {
 
     #[error("Invalid Keyset-ID")]
     Slice(#[from] std::array::TryFromSliceError),
+
+    #[error("Invalid spending condition {0}")]
+    InvalidSpendingCondition(String),
+
+    #[error("Unknown currency unit {0}")]
+    UnknownUnit(String),
+
+    #[error("Unknown bitcredit quote state {0}")]
+    UnknownQuoteState(String),
+
+    #[error("Unknown treasury entry kind {0}")]
+    UnknownTreasuryEntryKind(String),
 }
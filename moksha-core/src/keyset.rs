@@ -31,6 +31,8 @@ pub struct MintKeyset {
     pub public_keys: HashMap<u64, PublicKey>,
     pub keyset_id: String,
     pub mint_pubkey: PublicKey,
+    /// fee per input in parts per thousand (Nut-02)
+    pub input_fee_ppk: u64,
 }
 
 impl MintKeyset {
@@ -42,6 +44,7 @@ impl MintKeyset {
             keyset_id: derive_keyset_id(&pub_keys),
             public_keys: pub_keys,
             mint_pubkey: derive_pubkey(seed).expect("invalid seed"),
+            input_fee_ppk: 0,
         }
     }
 
@@ -57,6 +60,7 @@ impl MintKeyset {
             keyset_id: format!("00{}", id),
             public_keys: pub_keys,
             mint_pubkey: derive_pubkey(master_key).expect("invalid seed"),
+            input_fee_ppk: 0,
         }
     }
 }
@@ -73,6 +77,11 @@ pub struct Keyset {
     pub id: String, // FIXME use KeysetId
     pub unit: CurrencyUnit,
     pub active: bool,
+    #[serde(default)]
+    pub input_fee_ppk: u64,
+    /// unix timestamp in seconds from which on the bill of a `cr-sat` keyset can be redeemed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maturity_date: Option<i64>,
 }
 
 impl Keysets {
@@ -82,6 +91,8 @@ impl Keysets {
                 id,
                 unit,
                 active,
+                input_fee_ppk: 0,
+                maturity_date: None,
             }],
         }
     }
@@ -216,9 +227,22 @@ pub fn derive_pubkey(seed: &str) -> Result<PublicKey, MokshaCoreError> {
     Ok(key.public_key(&secp))
 }
 
+/// Calculates the fee for a set of inputs (Nut-02).
+///
+/// # Arguments
+///
+/// * `sum_fee_ppk` - The sum of the `input_fee_ppk` of the keysets of all inputs.
+///
+/// # Returns
+///
+/// The fee in the unit of the keyset, rounded up to the next full unit.
+pub const fn input_fee(sum_fee_ppk: u64) -> u64 {
+    sum_fee_ppk.div_ceil(1000)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::keyset::{derive_pubkey, KeysetId};
+    use crate::keyset::{derive_pubkey, input_fee, KeysetId};
     use pretty_assertions::assert_eq;
     use secp256k1::PublicKey;
     use std::collections::HashMap;
@@ -230,6 +254,15 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_input_fee() {
+        assert_eq!(0, input_fee(0));
+        assert_eq!(1, input_fee(1));
+        assert_eq!(1, input_fee(1000));
+        assert_eq!(2, input_fee(1001));
+        assert_eq!(3, input_fee(3 * 1000));
+    }
+
     #[test]
     fn test_derive_pubkey() -> anyhow::Result<()> {
         let result = derive_pubkey("supersecretprivatekey")?;
@@ -6,4 +6,5 @@ pub mod fixture;
 pub mod keyset;
 pub mod primitives;
 pub mod proof;
+pub mod secret;
 pub mod token;
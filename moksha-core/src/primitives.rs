@@ -1,9 +1,10 @@
 //! This module contains all the request and response objects that are used for interacting between the Mint and Wallet in Cashu.
 //! All of these structs are serializable and deserializable using serde.
 
-use std::{collections::HashMap, fmt::Display};
+use std::{collections::HashMap, fmt::Display, str::FromStr};
 
-use secp256k1::PublicKey;
+use bitcoin_hashes::{sha256, Hash};
+use secp256k1::{schnorr::Signature, Keypair, Message, PublicKey, Secp256k1, SecretKey};
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use utoipa::ToSchema;
@@ -11,6 +12,7 @@ use uuid::Uuid;
 
 use crate::{
     blind::{BlindedMessage, BlindedSignature},
+    error::MokshaCoreError,
     proof::Proofs,
 };
 
@@ -42,9 +44,130 @@ pub struct PostSwapResponse {
     pub signatures: Vec<BlindedSignature>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct PostRestoreRequest {
+    pub outputs: Vec<BlindedMessage>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default, ToSchema)]
+pub struct PostRestoreResponse {
+    pub outputs: Vec<BlindedMessage>,
+    pub signatures: Vec<BlindedSignature>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct PostCheckStateRequest {
+    #[serde(rename = "Ys")]
+    #[schema(value_type = Vec<String>)]
+    pub ys: Vec<PublicKey>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default, ToSchema)]
+pub struct PostCheckStateResponse {
+    pub states: Vec<ProofState>,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+pub struct ProofState {
+    #[serde(rename = "Y")]
+    #[schema(value_type = String)]
+    pub y: PublicKey,
+    pub state: State,
+    pub witness: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum State {
+    Unspent,
+    Pending,
+    Spent,
+}
+
+/// Error codes of the mint error responses (Nut-00). Codes that are unknown to this version are
+/// kept as `Other`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "u64", into = "u64")]
+pub enum ErrorCode {
+    /// Generic error of a request that the mint rejected
+    BadRequest,
+    /// The blinded message of an output has been signed before
+    OutputAlreadySigned,
+    TokenNotVerified,
+    /// Generic error of an invalid swap, mint or melt
+    TransactionError,
+    TokenAlreadySpent,
+    TransactionUnbalanced,
+    UnitNotSupported,
+    AmountOutOfRange,
+    DuplicateOutputs,
+    KeysetNotFound,
+    KeysetInactive,
+    /// Generic error of the payment backend
+    LightningError,
+    QuoteNotPaid,
+    TokensAlreadyIssued,
+    QuotePending,
+    InvoiceAlreadyPaid,
+    QuoteExpired,
+    Other(u64),
+}
+
+impl From<u64> for ErrorCode {
+    fn from(code: u64) -> Self {
+        match code {
+            10000 => Self::BadRequest,
+            10002 => Self::OutputAlreadySigned,
+            10003 => Self::TokenNotVerified,
+            11000 => Self::TransactionError,
+            11001 => Self::TokenAlreadySpent,
+            11002 => Self::TransactionUnbalanced,
+            11005 => Self::UnitNotSupported,
+            11006 => Self::AmountOutOfRange,
+            11008 => Self::DuplicateOutputs,
+            12001 => Self::KeysetNotFound,
+            12002 => Self::KeysetInactive,
+            20000 => Self::LightningError,
+            20001 => Self::QuoteNotPaid,
+            20002 => Self::TokensAlreadyIssued,
+            20005 => Self::QuotePending,
+            20006 => Self::InvoiceAlreadyPaid,
+            20007 => Self::QuoteExpired,
+            code => Self::Other(code),
+        }
+    }
+}
+
+impl From<ErrorCode> for u64 {
+    fn from(code: ErrorCode) -> Self {
+        match code {
+            ErrorCode::BadRequest => 10000,
+            ErrorCode::OutputAlreadySigned => 10002,
+            ErrorCode::TokenNotVerified => 10003,
+            ErrorCode::TransactionError => 11000,
+            ErrorCode::TokenAlreadySpent => 11001,
+            ErrorCode::TransactionUnbalanced => 11002,
+            ErrorCode::UnitNotSupported => 11005,
+            ErrorCode::AmountOutOfRange => 11006,
+            ErrorCode::DuplicateOutputs => 11008,
+            ErrorCode::KeysetNotFound => 12001,
+            ErrorCode::KeysetInactive => 12002,
+            ErrorCode::LightningError => 20000,
+            ErrorCode::QuoteNotPaid => 20001,
+            ErrorCode::TokensAlreadyIssued => 20002,
+            ErrorCode::QuotePending => 20005,
+            ErrorCode::InvoiceAlreadyPaid => 20006,
+            ErrorCode::QuoteExpired => 20007,
+            ErrorCode::Other(code) => code,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
 pub struct CashuErrorResponse {
-    pub code: u64,
+    #[schema(value_type = u64)]
+    pub code: ErrorCode,
     pub detail: String,
 }
 
@@ -70,23 +193,50 @@ pub struct KeyResponse {
 }
 
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, ToSchema, Hash)]
-#[serde(rename_all = "lowercase")]
+#[serde(try_from = "String", into = "String")]
 pub enum CurrencyUnit {
     Sat,
     MSat,
     Usd,
     CrSat,
+    /// Units that are not built into moksha, e.g. extension units of other mints
+    Custom(String),
+}
+
+impl FromStr for CurrencyUnit {
+    type Err = MokshaCoreError;
+
+    fn from_str(unit: &str) -> Result<Self, Self::Err> {
+        let unit = unit.to_lowercase();
+        match unit.as_str() {
+            "sat" => Ok(Self::Sat),
+            "msat" => Ok(Self::MSat),
+            "usd" => Ok(Self::Usd),
+            "cr-sat" => Ok(Self::CrSat),
+            custom
+                if !custom.is_empty()
+                    && custom
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') =>
+            {
+                Ok(Self::Custom(unit))
+            }
+            _ => Err(MokshaCoreError::UnknownUnit(unit)),
+        }
+    }
 }
 
-impl From<String> for CurrencyUnit {
-    fn from(unit: String) -> Self {
-        match unit.to_lowercase().as_str() {
-            "sat" => Self::Sat,
-            "msat" => Self::MSat,
-            "usd" => Self::Usd,
-            "cr-sat" => Self::CrSat,
-            _ => panic!("Unknown currency unit: {}", unit),
-        }
+impl TryFrom<String> for CurrencyUnit {
+    type Error = MokshaCoreError;
+
+    fn try_from(unit: String) -> Result<Self, Self::Error> {
+        unit.parse()
+    }
+}
+
+impl From<CurrencyUnit> for String {
+    fn from(unit: CurrencyUnit) -> Self {
+        unit.to_string()
     }
 }
 
@@ -97,6 +247,7 @@ impl Display for CurrencyUnit {
             Self::MSat => write!(f, "msat"),
             Self::Usd => write!(f, "usd"),
             Self::CrSat => write!(f, "cr-sat"),
+            Self::Custom(unit) => write!(f, "{unit}"),
         }
     }
 }
@@ -156,22 +307,86 @@ pub struct PostMintQuoteBitcreditRequest {
 #[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
 pub struct PostMintQuoteBitcreditResponse {
     pub quote: String,
+    /// amount the mint offers for the bill, the holder has to accept it before minting
+    pub amount: u64,
+    pub state: BitcreditMintQuoteState,
+    pub expiry: u64,
 }
 
 impl From<BitcreditMintQuote> for PostMintQuoteBitcreditResponse {
     fn from(quote: BitcreditMintQuote) -> Self {
         Self {
             quote: quote.quote_id.to_string(),
+            amount: quote.amount,
+            state: quote.state,
+            expiry: quote.expiry,
         }
     }
 }
 
+/// Hands the keys of a bill to the mint. The request is signed by the node key of the holder.
 #[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
 pub struct PostRequestToMintBitcreditRequest {
     pub bill_id: String,
     pub bill_keys: BillKeys,
     pub maturity_date: i64,
     pub bill_amount: u64,
+    /// hex encoded public key of the holder's node
+    pub node_id: String,
+    /// hex encoded schnorr signature of the request by the holder's node key
+    pub signature: String,
+}
+
+impl PostRequestToMintBitcreditRequest {
+    /// Creates a request that is signed by `node_key`, the node id is derived from the key
+    pub fn new_signed(
+        bill_id: String,
+        bill_keys: BillKeys,
+        maturity_date: i64,
+        bill_amount: u64,
+        node_key: &SecretKey,
+    ) -> Self {
+        let secp = Secp256k1::new();
+        let mut request = Self {
+            bill_id,
+            bill_keys,
+            maturity_date,
+            bill_amount,
+            node_id: node_key.public_key(&secp).to_string(),
+            signature: String::new(),
+        };
+        let keypair = Keypair::from_secret_key(&secp, node_key);
+        request.signature = secp
+            .sign_schnorr_no_aux_rand(&request.message(), &keypair)
+            .to_string();
+        request
+    }
+
+    /// Returns true if the request is signed by the key of `node_id`
+    pub fn verify_signature(&self) -> bool {
+        let (Ok(node_key), Ok(signature)) = (
+            PublicKey::from_str(&self.node_id),
+            Signature::from_str(&self.signature),
+        ) else {
+            return false;
+        };
+        Secp256k1::verification_only()
+            .verify_schnorr(&signature, &self.message(), &node_key.x_only_public_key().0)
+            .is_ok()
+    }
+
+    /// The signed message is the sha256 hash of all fields except the signature
+    fn message(&self) -> Message {
+        let fields = serde_json::json!([
+            self.node_id,
+            self.bill_id,
+            self.bill_keys.private_key_pem,
+            self.bill_keys.public_key_pem,
+            self.maturity_date,
+            self.bill_amount,
+        ]);
+        Message::from_digest(sha256::Hash::hash(fields.to_string().as_bytes()).to_byte_array())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -222,6 +437,20 @@ impl From<BitcreditRequestToMint> for PostRequestToMintBitcreditResponse {
     }
 }
 
+/// Exchanges `cr-sat` proofs of matured bills for `sat` signatures
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct PostRedeemBitcreditRequest {
+    /// proofs of bill keysets
+    pub inputs: Proofs,
+    /// outputs for the active `sat` keyset
+    pub outputs: Vec<BlindedMessage>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default, ToSchema)]
+pub struct PostRedeemBitcreditResponse {
+    pub signatures: Vec<BlindedSignature>,
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
 pub struct PostMintBolt11Request {
     pub quote: String,
@@ -269,6 +498,8 @@ pub struct PostMeltQuoteBolt11Response {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Bolt11MintQuote {
     pub quote_id: Uuid,
+    /// unit of the tokens that are minted for the invoice
+    pub unit: CurrencyUnit,
     pub payment_request: String,
     pub expiry: u64,
     pub paid: bool,
@@ -279,9 +510,108 @@ pub struct BitcreditMintQuote {
     pub quote_id: Uuid,
     pub bill_id: String,
     pub node_id: String,
-    pub sent: bool,
     pub amount: u64,
-    pub endorsed: bool,
+    pub state: BitcreditMintQuoteState,
+    /// unix timestamp in seconds until the bill has to be endorsed to the mint
+    pub expiry: u64,
+}
+
+/// Lifecycle of a bitcredit mint quote. A quote is requested by the holder of a bill, the mint
+/// offers an amount for the bill, the holder accepts the offer and endorses the bill to the mint,
+/// then the tokens are minted. Quotes can be rejected or expire until the bill is endorsed.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BitcreditMintQuoteState {
+    Requested,
+    Offered,
+    Accepted,
+    Endorsed,
+    Minted,
+    Rejected,
+    Expired,
+}
+
+impl BitcreditMintQuoteState {
+    /// Returns true if a quote in this state can be moved to `next`
+    pub const fn can_transition_to(self, next: Self) -> bool {
+        matches!(
+            (self, next),
+            (Self::Requested, Self::Offered)
+                | (Self::Offered, Self::Accepted)
+                | (Self::Accepted, Self::Endorsed)
+                | (Self::Endorsed, Self::Minted)
+                | (
+                    Self::Requested | Self::Offered | Self::Accepted,
+                    Self::Rejected | Self::Expired
+                )
+        )
+    }
+}
+
+impl Display for BitcreditMintQuoteState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Requested => write!(f, "REQUESTED"),
+            Self::Offered => write!(f, "OFFERED"),
+            Self::Accepted => write!(f, "ACCEPTED"),
+            Self::Endorsed => write!(f, "ENDORSED"),
+            Self::Minted => write!(f, "MINTED"),
+            Self::Rejected => write!(f, "REJECTED"),
+            Self::Expired => write!(f, "EXPIRED"),
+        }
+    }
+}
+
+impl FromStr for BitcreditMintQuoteState {
+    type Err = MokshaCoreError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "REQUESTED" => Ok(Self::Requested),
+            "OFFERED" => Ok(Self::Offered),
+            "ACCEPTED" => Ok(Self::Accepted),
+            "ENDORSED" => Ok(Self::Endorsed),
+            "MINTED" => Ok(Self::Minted),
+            "REJECTED" => Ok(Self::Rejected),
+            "EXPIRED" => Ok(Self::Expired),
+            _ => Err(MokshaCoreError::UnknownQuoteState(s.to_owned())),
+        }
+    }
+}
+
+/// A state change of a bitcredit mint quote
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+pub struct BitcreditMintQuoteTransition {
+    pub state: BitcreditMintQuoteState,
+    /// unix timestamp in seconds
+    pub timestamp: u64,
+    /// why the quote was moved to the state, e.g. why it was rejected
+    pub reason: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct GetMintQuoteBitcreditResponse {
+    pub quote: String,
+    pub bill_id: String,
+    pub amount: u64,
+    pub state: BitcreditMintQuoteState,
+    pub expiry: u64,
+    /// all state changes of the quote, the oldest first
+    pub transitions: Vec<BitcreditMintQuoteTransition>,
+}
+
+impl GetMintQuoteBitcreditResponse {
+    pub fn new(quote: BitcreditMintQuote, transitions: Vec<BitcreditMintQuoteTransition>) -> Self {
+        Self {
+            quote: quote.quote_id.to_string(),
+            bill_id: quote.bill_id,
+            amount: quote.amount,
+            state: quote.state,
+            expiry: quote.expiry,
+            transitions,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -292,9 +622,112 @@ pub struct BitcreditRequestToMint {
     pub bill_amount: u64,
 }
 
+/// What a treasury ledger entry records. The mint earns the discount of the bills it mints,
+/// the operator withdraws the earnings as ecash or melts them after the bill has been paid.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TreasuryEntryKind {
+    Earned,
+    Withdrawn,
+    Melted,
+}
+
+impl Display for TreasuryEntryKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Earned => write!(f, "EARNED"),
+            Self::Withdrawn => write!(f, "WITHDRAWN"),
+            Self::Melted => write!(f, "MELTED"),
+        }
+    }
+}
+
+impl FromStr for TreasuryEntryKind {
+    type Err = MokshaCoreError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "EARNED" => Ok(Self::Earned),
+            "WITHDRAWN" => Ok(Self::Withdrawn),
+            "MELTED" => Ok(Self::Melted),
+            _ => Err(MokshaCoreError::UnknownTreasuryEntryKind(s.to_owned())),
+        }
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+pub struct TreasuryEntry {
+    pub bill_id: String,
+    pub kind: TreasuryEntryKind,
+    /// amount in sat
+    pub amount: u64,
+    /// the quote id of earnings or the payment hash of melts
+    pub reference: Option<String>,
+    /// unix timestamp in seconds
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+pub struct TreasuryBalance {
+    pub bill_id: String,
+    pub earned: u64,
+    pub withdrawn: u64,
+    pub melted: u64,
+    /// reserved for payments from the treasury that have not been settled
+    pub pending: u64,
+    /// earnings that have not been withdrawn, melted or reserved yet
+    pub balance: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct GetTreasuryResponse {
+    pub balances: Vec<TreasuryBalance>,
+    /// all ledger entries, the oldest first
+    pub entries: Vec<TreasuryEntry>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct PostTreasuryWithdrawRequest {
+    pub bill_id: String,
+    pub amount: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct PostTreasuryWithdrawResponse {
+    /// `cr-sat` proofs of the bill keyset
+    pub proofs: Proofs,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct PostTreasuryMeltRequest {
+    pub bill_id: String,
+    /// bolt11 invoice that is paid from the earnings of the bill
+    pub request: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct PostTreasuryMeltResponse {
+    pub payment_hash: String,
+    /// amount of the invoice in sat
+    pub amount: u64,
+    /// lightning fees in sat
+    pub fee: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct PostRotateKeysetRequest {
+    pub unit: CurrencyUnit,
+    /// derivation path of the new keyset, the next unused path of the unit if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub derivation_path: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Bolt11MeltQuote {
     pub quote_id: Uuid,
+    /// unit of the tokens that are melted, `amount` and `fee_reserve` are in this unit
+    pub unit: CurrencyUnit,
     pub amount: u64,
     pub fee_reserve: u64,
     pub payment_request: String,
@@ -482,6 +915,10 @@ pub struct Nuts {
     /// DLEQ proofs
     pub nut12: Option<Nut12>,
 
+    /// Hashed Timelock Contracts (HTLCs)
+    #[serde(rename = "14", skip_serializing_if = "Option::is_none")]
+    pub nut14: Option<Nut14>,
+
     #[serde(rename = "17", skip_serializing_if = "Option::is_none")]
     /// minting tokens btc onchain
     pub nut17: Option<Nut17>,
@@ -502,6 +939,7 @@ impl Default for Nuts {
             nut10: Some(Nut10 { supported: false }),
             nut11: Some(Nut11 { supported: false }),
             nut12: Some(Nut12 { supported: false }),
+            nut14: Some(Nut14 { supported: false }),
             nut17: Some(Nut17::default()),
             nut18: Some(Nut18::default()),
         }
@@ -586,6 +1024,11 @@ pub struct Nut12 {
     pub supported: bool,
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Default, ToSchema)]
+pub struct Nut14 {
+    pub supported: bool,
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
 pub struct Nut17 {
     pub supported: bool,
@@ -652,10 +1095,107 @@ mod tests {
 
     use crate::{
         dhke::public_key_from_hex,
+        error::MokshaCoreError,
         fixture::read_fixture,
-        primitives::{KeyResponse, MintInfoResponse, Nuts, PostSwapResponse},
+        primitives::{
+            BillKeys, BitcreditMintQuoteState, CashuErrorResponse, CurrencyUnit, ErrorCode,
+            KeyResponse, MintInfoResponse, Nuts, PostCheckStateResponse,
+            PostRequestToMintBitcreditRequest, PostSwapResponse, ProofState, State,
+        },
     };
 
+    #[test]
+    fn test_bitcredit_quote_state_transitions() -> anyhow::Result<()> {
+        use BitcreditMintQuoteState::*;
+        let lifecycle = [Requested, Offered, Accepted, Endorsed, Minted];
+        for (state, next) in lifecycle.iter().zip(lifecycle.iter().skip(1)) {
+            assert!(state.can_transition_to(*next));
+            assert!(!next.can_transition_to(*state));
+        }
+        assert!(!Offered.can_transition_to(Endorsed));
+        assert!(Accepted.can_transition_to(Rejected));
+        assert!(Accepted.can_transition_to(Expired));
+        // the bill is endorsed to the mint already
+        assert!(!Endorsed.can_transition_to(Rejected));
+        assert!(!Endorsed.can_transition_to(Expired));
+        assert!(!Expired.can_transition_to(Offered));
+
+        assert_eq!("ENDORSED".parse::<BitcreditMintQuoteState>()?, Endorsed);
+        assert_eq!(serde_json::to_string(&Endorsed)?, "\"ENDORSED\"");
+        assert!(matches!(
+            "endorsed".parse::<BitcreditMintQuoteState>(),
+            Err(MokshaCoreError::UnknownQuoteState(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_request_to_mint_signature() -> anyhow::Result<()> {
+        let node_key = secp256k1::SecretKey::from_slice(&[1; 32])?;
+        let request = PostRequestToMintBitcreditRequest::new_signed(
+            "bill".to_owned(),
+            BillKeys {
+                private_key_pem: "private".to_owned(),
+                public_key_pem: "public".to_owned(),
+            },
+            1_700_000_000,
+            1_000,
+            &node_key,
+        );
+        assert!(request.verify_signature());
+
+        // the signature covers the amount
+        let forged = PostRequestToMintBitcreditRequest {
+            bill_amount: 2_000,
+            ..request.clone()
+        };
+        assert!(!forged.verify_signature());
+
+        // someone else can not claim the signature
+        let other_key = secp256k1::SecretKey::from_slice(&[2; 32])?;
+        let other = PostRequestToMintBitcreditRequest {
+            node_id: other_key
+                .public_key(&secp256k1::Secp256k1::new())
+                .to_string(),
+            ..request.clone()
+        };
+        assert!(!other.verify_signature());
+
+        let invalid = PostRequestToMintBitcreditRequest {
+            signature: "invalid".to_owned(),
+            ..request
+        };
+        assert!(!invalid.verify_signature());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_currency_unit() -> anyhow::Result<()> {
+        assert_eq!("sat".parse::<CurrencyUnit>()?, CurrencyUnit::Sat);
+        assert_eq!("cr-sat".parse::<CurrencyUnit>()?, CurrencyUnit::CrSat);
+        assert_eq!(
+            "EUR".parse::<CurrencyUnit>()?,
+            CurrencyUnit::Custom("eur".to_owned())
+        );
+        assert!(matches!(
+            "".parse::<CurrencyUnit>(),
+            Err(MokshaCoreError::UnknownUnit(_))
+        ));
+        assert!(matches!(
+            "sat/../usd".parse::<CurrencyUnit>(),
+            Err(MokshaCoreError::UnknownUnit(_))
+        ));
+
+        let unit = serde_json::from_str::<CurrencyUnit>("\"usd\"")?;
+        assert_eq!(unit, CurrencyUnit::Usd);
+        assert_eq!(
+            serde_json::to_string(&CurrencyUnit::Custom("eur".to_owned()))?,
+            "\"eur\""
+        );
+        assert!(serde_json::from_str::<CurrencyUnit>("\"\"").is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_serialize_empty_swap_response() -> anyhow::Result<()> {
         let response = PostSwapResponse::default();
@@ -664,6 +1204,25 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_serialize_check_state_response() -> anyhow::Result<()> {
+        let response = PostCheckStateResponse {
+            states: vec![ProofState {
+                y: public_key_from_hex(
+                    "02599b9ea0a1ad4143706c2a5a4a568ce442dd4313e1cf1f7f0b58a317c1a355ee",
+                ),
+                state: State::Spent,
+                witness: None,
+            }],
+        };
+        let serialized = serde_json::to_string(&response)?;
+        assert_eq!(
+            serialized,
+            "{\"states\":[{\"Y\":\"02599b9ea0a1ad4143706c2a5a4a568ce442dd4313e1cf1f7f0b58a317c1a355ee\",\"state\":\"SPENT\"}]}"
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_serialize_keyresponse() -> anyhow::Result<()> {
         let response = KeyResponse {
@@ -676,6 +1235,25 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_serialize_error_response() -> anyhow::Result<()> {
+        let response = CashuErrorResponse {
+            code: ErrorCode::TokenAlreadySpent,
+            detail: "Token already spent.".to_string(),
+        };
+        let serialized = serde_json::to_string(&response)?;
+        assert_eq!(
+            serialized,
+            "{\"code\":11001,\"detail\":\"Token already spent.\"}"
+        );
+
+        let unknown = serde_json::from_str::<CashuErrorResponse>(
+            "{\"code\":42,\"detail\":\"unknown error\"}",
+        )?;
+        assert_eq!(ErrorCode::Other(42), unknown.code);
+        Ok(())
+    }
+
     #[test]
     fn test_serialize_mint_info() -> anyhow::Result<()> {
         let mint_info = MintInfoResponse {
@@ -723,6 +1301,7 @@ mod tests {
         assert!(info.nuts.nut10.is_none());
         assert!(info.nuts.nut11.is_none());
         assert!(info.nuts.nut12.is_none());
+        assert!(info.nuts.nut14.is_none());
         Ok(())
     }
 }
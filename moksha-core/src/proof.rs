@@ -1,21 +1,24 @@
-//! This module defines the `Proof`, `P2SHScript`, and `Proofs` structs, which are used for representing proofs in the Moksha Core library as described in [Nut-00](https://github.com/cashubtc/nuts/blob/main/00.md)
+//! This module defines the `Proof` and `Proofs` structs, which are used for representing proofs in the Moksha Core library as described in [Nut-00](https://github.com/cashubtc/nuts/blob/main/00.md)
 //!
-//! The `Proof` struct represents a proof, with an `amount` field for the amount in satoshis, a `secret` field for the secret string, a `c` field for the public key of the blinding factor, an `id` field for the ID of the proof, and an optional `script` field for the P2SH script.
+//! The `Proof` struct represents a proof, with an `amount` field for the amount in satoshis, a `secret` field for the secret string, a `c` field for the public key of the blinding factor, an `id` field for the ID of the proof, an optional `witness` field that unlocks proofs with spending conditions ([Nut-11](https://github.com/cashubtc/nuts/blob/main/11.md)) and an optional `dleq` field that lets the receiver verify the mint's signature offline ([Nut-12](https://github.com/cashubtc/nuts/blob/main/12.md)).
 //!
 //! The `Proof` struct provides a `new` method for creating a new proof from its constituent fields.
 //!
-//! The `P2SHScript` struct represents a P2SH script, and is currently not implemented.
-//!
 //! The `Proofs` struct represents a collection of proofs, with a `Vec<Proof>` field for the proofs.
 //!
 //! Both the `Proof` and `Proofs` structs are serializable and deserializable using serde.
 
-use secp256k1::PublicKey;
+use secp256k1::{PublicKey, SecretKey};
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use utoipa::ToSchema;
 
-use crate::{error::MokshaCoreError, keyset::KeysetId};
+use crate::{
+    dhke::Dhke,
+    error::MokshaCoreError,
+    keyset::{input_fee, KeysetId},
+    secret::Witness,
+};
 
 #[skip_serializing_none]
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
@@ -27,7 +30,20 @@ pub struct Proof {
     #[serde(rename = "C")]
     #[schema(value_type = String)]
     pub c: PublicKey,
-    pub script: Option<P2SHScript>,
+    #[schema(value_type = Option<String>)]
+    pub witness: Option<Witness>,
+    pub dleq: Option<ProofDleq>,
+}
+
+/// DLEQ proof of the blinded signature together with the blinding factor `r` (Nut-12)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+pub struct ProofDleq {
+    #[schema(value_type = String)]
+    pub e: SecretKey,
+    #[schema(value_type = String)]
+    pub s: SecretKey,
+    #[schema(value_type = String)]
+    pub r: SecretKey,
 }
 
 impl Proof {
@@ -37,13 +53,16 @@ impl Proof {
             secret,
             c,
             keyset_id: id,
-            script: None,
+            witness: None,
+            dleq: None,
         }
     }
-}
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
-pub struct P2SHScript;
+    /// Returns `Y = hash_to_curve(secret)`, which identifies the proof without revealing the secret (Nut-07)
+    pub fn y(&self) -> Result<PublicKey, MokshaCoreError> {
+        Dhke::hash_to_curve(self.secret.as_bytes())
+    }
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
 pub struct Proofs(pub(super) Vec<Proof>);
@@ -87,6 +106,15 @@ impl Proofs {
     }
 
     pub fn proofs_for_amount(&self, amount: u64) -> Result<Self, MokshaCoreError> {
+        self.proofs_for_amount_with_fee(amount, 0)
+    }
+
+    /// Selects proofs that cover `amount` plus the input fee (Nut-02) for the selected proofs
+    pub fn proofs_for_amount_with_fee(
+        &self,
+        amount: u64,
+        input_fee_ppk: u64,
+    ) -> Result<Self, MokshaCoreError> {
         let mut all_proofs = self.0.clone();
         if amount > self.total_amount() {
             return Err(MokshaCoreError::NotEnoughTokens);
@@ -97,12 +125,8 @@ impl Proofs {
         let mut selected_proofs = vec![];
         let mut selected_amount = 0;
 
-        while selected_amount < amount {
-            if all_proofs.is_empty() {
-                break;
-            }
-
-            let proof = all_proofs.pop().expect("proofs is empty");
+        while selected_amount < amount + input_fee(selected_proofs.len() as u64 * input_fee_ppk) {
+            let proof = all_proofs.pop().ok_or(MokshaCoreError::NotEnoughTokens)?;
             selected_amount += proof.amount;
             selected_proofs.push(proof);
         }
@@ -160,6 +184,21 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_proofs_for_amount_with_fee() -> anyhow::Result<()> {
+        let fixture = read_fixture("token_60.cashu")?; // 60 tokens (4,8,16,32)
+        let token: TokenV3 = fixture.try_into()?;
+
+        // 32 + 16 would cover 48, but not the fee of 1 for two inputs
+        let result = token.proofs().proofs_for_amount_with_fee(48, 100)?;
+        assert_eq!(56, result.total_amount());
+        assert_eq!(3, result.len());
+
+        let result = token.proofs().proofs_for_amount_with_fee(60, 100);
+        assert!(result.is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_proof() -> anyhow::Result<()> {
         let js = json!(
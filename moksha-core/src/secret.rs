@@ -0,0 +1,474 @@
+//! This module defines the well-known secrets and spending conditions as described in [Nut-10](https://github.com/cashubtc/nuts/blob/main/10.md), [Nut-11](https://github.com/cashubtc/nuts/blob/main/11.md) and [Nut-14](https://github.com/cashubtc/nuts/blob/main/14.md)
+//!
+//! A `WellKnownSecret` is serialized as `[kind, {"nonce": .., "data": .., "tags": [[..], ..]}]` and stored as the `secret` string of a proof.
+//!
+//! The `P2PKConditions` struct contains the parsed P2PK spending conditions of a secret (pubkeys, `n_sigs`, `sigflag`, `locktime` and `refund` keys).
+//!
+//! The `HTLCConditions` struct contains the hash lock of a HTLC secret and the same tag conditions, where the `pubkeys` are optional.
+//!
+//! The `Witness` struct holds the schnorr signatures and the HTLC preimage that unlock a proof or blinded message. It is serialized as a json string.
+
+use std::str::FromStr;
+
+use bitcoin_hashes::{sha256, Hash};
+use secp256k1::{schnorr::Signature, Keypair, Message, PublicKey, Secp256k1, SecretKey};
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::MokshaCoreError;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SecretKind {
+    P2PK,
+    HTLC,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    from = "(SecretKind, WellKnownSecretData)",
+    into = "(SecretKind, WellKnownSecretData)"
+)]
+pub struct WellKnownSecret {
+    pub kind: SecretKind,
+    pub nonce: String,
+    pub data: String,
+    pub tags: Vec<Vec<String>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct WellKnownSecretData {
+    nonce: String,
+    data: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<Vec<String>>,
+}
+
+impl From<(SecretKind, WellKnownSecretData)> for WellKnownSecret {
+    fn from((kind, data): (SecretKind, WellKnownSecretData)) -> Self {
+        Self {
+            kind,
+            nonce: data.nonce,
+            data: data.data,
+            tags: data.tags,
+        }
+    }
+}
+
+impl From<WellKnownSecret> for (SecretKind, WellKnownSecretData) {
+    fn from(secret: WellKnownSecret) -> Self {
+        (
+            secret.kind,
+            WellKnownSecretData {
+                nonce: secret.nonce,
+                data: secret.data,
+                tags: secret.tags,
+            },
+        )
+    }
+}
+
+impl FromStr for WellKnownSecret {
+    type Err = MokshaCoreError;
+
+    fn from_str(secret: &str) -> Result<Self, Self::Err> {
+        Ok(serde_json::from_str(secret)?)
+    }
+}
+
+impl TryFrom<WellKnownSecret> for String {
+    type Error = MokshaCoreError;
+
+    fn try_from(secret: WellKnownSecret) -> Result<Self, Self::Error> {
+        Ok(serde_json::to_string(&secret)?)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub enum SigFlag {
+    #[default]
+    SigInputs,
+    SigAll,
+}
+
+impl FromStr for SigFlag {
+    type Err = MokshaCoreError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "SIG_INPUTS" => Ok(Self::SigInputs),
+            "SIG_ALL" => Ok(Self::SigAll),
+            _ => Err(MokshaCoreError::InvalidSpendingCondition(format!(
+                "unknown sigflag {value}"
+            ))),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct P2PKConditions {
+    /// the pubkey from `data` followed by the keys from the `pubkeys` tag
+    pub pubkeys: Vec<PublicKey>,
+    pub n_sigs: u64,
+    pub sig_flag: SigFlag,
+    /// unix timestamp in seconds after which the refund keys can spend the proof
+    pub locktime: Option<u64>,
+    pub refund_keys: Vec<PublicKey>,
+}
+
+impl TryFrom<&WellKnownSecret> for P2PKConditions {
+    type Error = MokshaCoreError;
+
+    fn try_from(secret: &WellKnownSecret) -> Result<Self, Self::Error> {
+        if secret.kind != SecretKind::P2PK {
+            return Err(MokshaCoreError::InvalidSpendingCondition(
+                "not a P2PK secret".to_string(),
+            ));
+        }
+        parse_conditions(secret, parse_keys(std::slice::from_ref(&secret.data))?)
+    }
+}
+
+impl P2PKConditions {
+    /// Creates a P2PK secret for these conditions with the given nonce
+    pub fn to_secret(&self, nonce: String) -> WellKnownSecret {
+        WellKnownSecret {
+            kind: SecretKind::P2PK,
+            nonce,
+            data: self
+                .pubkeys
+                .first()
+                .map(|key| key.to_string())
+                .unwrap_or_default(),
+            tags: self.to_tags(self.pubkeys.get(1..).unwrap_or_default()),
+        }
+    }
+
+    fn to_tags(&self, pubkeys: &[PublicKey]) -> Vec<Vec<String>> {
+        let mut tags = vec![];
+        if self.sig_flag == SigFlag::SigAll {
+            tags.push(vec!["sigflag".to_owned(), "SIG_ALL".to_owned()]);
+        }
+        if self.n_sigs != 1 {
+            tags.push(vec!["n_sigs".to_owned(), self.n_sigs.to_string()]);
+        }
+        if !pubkeys.is_empty() {
+            tags.push(to_tag("pubkeys", pubkeys));
+        }
+        if let Some(locktime) = self.locktime {
+            tags.push(vec!["locktime".to_owned(), locktime.to_string()]);
+        }
+        if !self.refund_keys.is_empty() {
+            tags.push(to_tag("refund", &self.refund_keys));
+        }
+        tags
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HTLCConditions {
+    /// sha256 hash of the preimage that unlocks the proof
+    pub hash: sha256::Hash,
+    /// conditions from the tags. If `pubkeys` is empty only the preimage is required
+    pub conditions: P2PKConditions,
+}
+
+impl TryFrom<&WellKnownSecret> for HTLCConditions {
+    type Error = MokshaCoreError;
+
+    fn try_from(secret: &WellKnownSecret) -> Result<Self, Self::Error> {
+        if secret.kind != SecretKind::HTLC {
+            return Err(MokshaCoreError::InvalidSpendingCondition(
+                "not a HTLC secret".to_string(),
+            ));
+        }
+        let hash = sha256::Hash::from_str(&secret.data).map_err(|_| {
+            MokshaCoreError::InvalidSpendingCondition(format!("invalid hash {}", secret.data))
+        })?;
+        Ok(Self {
+            hash,
+            conditions: parse_conditions(secret, vec![])?,
+        })
+    }
+}
+
+impl HTLCConditions {
+    /// Creates a HTLC secret for these conditions with the given nonce
+    pub fn to_secret(&self, nonce: String) -> WellKnownSecret {
+        WellKnownSecret {
+            kind: SecretKind::HTLC,
+            nonce,
+            data: self.hash.to_string(),
+            tags: self.conditions.to_tags(&self.conditions.pubkeys),
+        }
+    }
+
+    /// Returns true if `sha256(preimage)` matches the hash lock. The preimage is hex encoded.
+    pub fn verify_preimage(&self, preimage: &str) -> bool {
+        hex::decode(preimage).is_ok_and(|preimage| sha256::Hash::hash(&preimage) == self.hash)
+    }
+}
+
+/// The spending conditions of a well-known secret
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SpendingConditions {
+    P2PK(P2PKConditions),
+    HTLC(HTLCConditions),
+}
+
+impl TryFrom<&WellKnownSecret> for SpendingConditions {
+    type Error = MokshaCoreError;
+
+    fn try_from(secret: &WellKnownSecret) -> Result<Self, Self::Error> {
+        Ok(match secret.kind {
+            SecretKind::P2PK => Self::P2PK(secret.try_into()?),
+            SecretKind::HTLC => Self::HTLC(secret.try_into()?),
+        })
+    }
+}
+
+impl SpendingConditions {
+    pub fn to_secret(&self, nonce: String) -> WellKnownSecret {
+        match self {
+            Self::P2PK(conditions) => conditions.to_secret(nonce),
+            Self::HTLC(conditions) => conditions.to_secret(nonce),
+        }
+    }
+
+    /// Returns the signature related conditions (pubkeys, `n_sigs`, `sigflag`, `locktime` and `refund` keys)
+    pub fn signature_conditions(&self) -> &P2PKConditions {
+        match self {
+            Self::P2PK(conditions) => conditions,
+            Self::HTLC(htlc) => &htlc.conditions,
+        }
+    }
+}
+
+fn parse_keys(values: &[String]) -> Result<Vec<PublicKey>, MokshaCoreError> {
+    values
+        .iter()
+        .map(|key| {
+            PublicKey::from_str(key).map_err(|_| {
+                MokshaCoreError::InvalidSpendingCondition(format!("invalid pubkey {key}"))
+            })
+        })
+        .collect()
+}
+
+/// Parses the tags of the secret. `pubkeys` are the keys that come before the keys of the `pubkeys` tag.
+fn parse_conditions(
+    secret: &WellKnownSecret,
+    pubkeys: Vec<PublicKey>,
+) -> Result<P2PKConditions, MokshaCoreError> {
+    let invalid = |msg: String| MokshaCoreError::InvalidSpendingCondition(msg);
+    let mut conditions = P2PKConditions {
+        pubkeys,
+        n_sigs: 1,
+        sig_flag: SigFlag::SigInputs,
+        locktime: None,
+        refund_keys: vec![],
+    };
+
+    for tag in secret.tags.iter() {
+        let Some((key, values)) = tag.split_first() else {
+            return Err(invalid("empty tag".to_string()));
+        };
+        let first = values
+            .first()
+            .ok_or_else(|| invalid(format!("missing value for tag {key}")));
+
+        // unknown tags are ignored
+        match key.as_str() {
+            "sigflag" => conditions.sig_flag = SigFlag::from_str(first?)?,
+            "n_sigs" => {
+                conditions.n_sigs = first?
+                    .parse()
+                    .map_err(|_| invalid("n_sigs is not a number".to_string()))?
+            }
+            "locktime" => {
+                conditions.locktime = Some(
+                    first?
+                        .parse()
+                        .map_err(|_| invalid("locktime is not a number".to_string()))?,
+                )
+            }
+            "pubkeys" => conditions.pubkeys.extend(parse_keys(values)?),
+            "refund" => conditions.refund_keys.extend(parse_keys(values)?),
+            _ => {}
+        }
+    }
+    Ok(conditions)
+}
+
+fn to_tag(key: &str, pubkeys: &[PublicKey]) -> Vec<String> {
+    std::iter::once(key.to_owned())
+        .chain(pubkeys.iter().map(|key| key.to_string()))
+        .collect()
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct WitnessData {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    preimage: Option<String>,
+    #[serde(default)]
+    signatures: Vec<String>,
+}
+
+/// Signatures and the HTLC preimage that unlock a proof or a blinded message (serialized as a json string)
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Witness {
+    /// hex encoded preimage of a HTLC hash lock (Nut-14)
+    pub preimage: Option<String>,
+    pub signatures: Vec<String>,
+}
+
+impl Witness {
+    /// Adds a schnorr signature of `sha256(msg)` for the given key
+    pub fn sign(&mut self, msg: &[u8], secret_key: &SecretKey) {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_secret_key(&secp, secret_key);
+        let signature = secp.sign_schnorr_no_aux_rand(&message_hash(msg), &keypair);
+        self.signatures.push(signature.to_string());
+    }
+
+    /// Returns the number of distinct `pubkeys` that have a valid signature of `sha256(msg)` in this witness
+    pub fn count_valid_signatures(&self, msg: &[u8], pubkeys: &[PublicKey]) -> usize {
+        let secp = Secp256k1::verification_only();
+        let msg = message_hash(msg);
+        let signatures = self
+            .signatures
+            .iter()
+            .filter_map(|sig| Signature::from_str(sig).ok())
+            .collect::<Vec<Signature>>();
+
+        let mut pubkeys = pubkeys.to_vec();
+        pubkeys.sort();
+        pubkeys.dedup();
+        pubkeys
+            .iter()
+            .filter(|pubkey| {
+                let (xonly, _) = pubkey.x_only_public_key();
+                signatures
+                    .iter()
+                    .any(|sig| secp.verify_schnorr(sig, &msg, &xonly).is_ok())
+            })
+            .count()
+    }
+}
+
+fn message_hash(msg: &[u8]) -> Message {
+    Message::from_digest(sha256::Hash::hash(msg).to_byte_array())
+}
+
+impl Serialize for Witness {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let data = WitnessData {
+            preimage: self.preimage.clone(),
+            signatures: self.signatures.clone(),
+        };
+        serializer.serialize_str(&serde_json::to_string(&data).map_err(ser::Error::custom)?)
+    }
+}
+
+impl<'de> Deserialize<'de> for Witness {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let json = String::deserialize(deserializer)?;
+        let data: WitnessData = serde_json::from_str(&json).map_err(de::Error::custom)?;
+        Ok(Self {
+            preimage: data.preimage,
+            signatures: data.signatures,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use secp256k1::{PublicKey, Secp256k1, SecretKey};
+
+    use crate::secret::{
+        HTLCConditions, P2PKConditions, SecretKind, SigFlag, SpendingConditions, WellKnownSecret,
+        Witness,
+    };
+
+    #[test]
+    fn test_parse_p2pk_secret() -> anyhow::Result<()> {
+        let secret = r#"["P2PK",{"nonce":"da62796403af76c80cd6ce9153ed3746","data":"033281c37677ea273eb7183b783067f5244933ef78d8c3f15b1a77cb246099c26e","tags":[["sigflag","SIG_ALL"],["n_sigs","2"],["locktime","1689418329"],["refund","033281c37677ea273eb7183b783067f5244933ef78d8c3f15b1a77cb246099c26e"],["pubkeys","02698c4e2b5f9534cd0687d87513c759790cf829aa5739184a3e3735471fbda904","023192200a0cfd3867e48eb63b03ff599c7e46c8f4e41146b2d281173ca6c50c54"]]}]"#;
+        let secret = WellKnownSecret::from_str(secret)?;
+        assert_eq!(SecretKind::P2PK, secret.kind);
+        assert_eq!("da62796403af76c80cd6ce9153ed3746", secret.nonce);
+
+        let conditions = P2PKConditions::try_from(&secret)?;
+        assert_eq!(3, conditions.pubkeys.len());
+        assert_eq!(2, conditions.n_sigs);
+        assert_eq!(SigFlag::SigAll, conditions.sig_flag);
+        assert_eq!(Some(1689418329), conditions.locktime);
+        assert_eq!(1, conditions.refund_keys.len());
+
+        let serialized: String = secret.clone().try_into()?;
+        assert_eq!(secret, WellKnownSecret::from_str(&serialized)?);
+
+        let recreated = conditions.to_secret(secret.nonce.clone());
+        assert_eq!(conditions, P2PKConditions::try_from(&recreated)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_htlc_secret() -> anyhow::Result<()> {
+        let preimage = "0000000000000000000000000000000000000000000000000000000000000000";
+        let secret = r#"["HTLC",{"nonce":"da62796403af76c80cd6ce9153ed3746","data":"66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925","tags":[["pubkeys","02698c4e2b5f9534cd0687d87513c759790cf829aa5739184a3e3735471fbda904"],["locktime","1689418329"],["refund","033281c37677ea273eb7183b783067f5244933ef78d8c3f15b1a77cb246099c26e"]]}]"#;
+        let secret = WellKnownSecret::from_str(secret)?;
+        assert_eq!(SecretKind::HTLC, secret.kind);
+        assert!(P2PKConditions::try_from(&secret).is_err());
+
+        let htlc = HTLCConditions::try_from(&secret)?;
+        assert!(htlc.verify_preimage(preimage));
+        assert!(!htlc.verify_preimage(&preimage.replace('0', "1")));
+        assert!(!htlc.verify_preimage("not hex"));
+        assert_eq!(1, htlc.conditions.pubkeys.len());
+        assert_eq!(Some(1689418329), htlc.conditions.locktime);
+        assert_eq!(1, htlc.conditions.refund_keys.len());
+
+        let conditions = SpendingConditions::try_from(&secret)?;
+        assert_eq!(SpendingConditions::HTLC(htlc), conditions);
+        let recreated = conditions.to_secret(secret.nonce.clone());
+        assert_eq!(secret, recreated);
+        Ok(())
+    }
+
+    #[test]
+    fn test_plain_secret_is_not_well_known() {
+        assert!(WellKnownSecret::from_str("EhpennC9qB3iFlW8FZ_pZw").is_err());
+        assert!(WellKnownSecret::from_str(r#"["HTLC2",{"nonce":"a","data":"b"}]"#).is_err());
+    }
+
+    #[test]
+    fn test_witness_signatures() -> anyhow::Result<()> {
+        let secp = Secp256k1::new();
+        let key = SecretKey::from_slice(&[1; 32])?;
+        let other_key = SecretKey::from_slice(&[2; 32])?;
+        let pubkey = PublicKey::from_secret_key(&secp, &key);
+        let other_pubkey = PublicKey::from_secret_key(&secp, &other_key);
+
+        let mut witness = Witness::default();
+        witness.sign(b"secret", &key);
+        witness.sign(b"secret", &key);
+
+        assert_eq!(
+            1,
+            witness.count_valid_signatures(b"secret", &[pubkey, other_pubkey])
+        );
+        assert_eq!(0, witness.count_valid_signatures(b"other", &[pubkey]));
+
+        let json = serde_json::to_value(&witness)?;
+        assert!(json.is_string());
+        assert_eq!(witness, serde_json::from_value(json)?);
+
+        witness.preimage = Some("00".to_owned());
+        let json = serde_json::to_value(&witness)?;
+        assert!(json
+            .as_str()
+            .is_some_and(|json| json.contains(r#""preimage":"00""#)));
+        assert_eq!(witness, serde_json::from_value(json)?);
+        Ok(())
+    }
+}
@@ -313,7 +313,8 @@ mod tests {
                     "02c020067db727d586bc3183aecf97fcb800c3f4cc4759f69c626c9db5d8f5b5d4",
                 ),
                 keyset_id: "someid".to_string(),
-                script: None,
+                witness: None,
+                dleq: None,
             }
             .into(),
         };
@@ -0,0 +1,12 @@
+ALTER TABLE used_proofs
+    ADD COLUMN IF NOT EXISTS y TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS used_proofs_y_idx ON used_proofs (y);
+
+CREATE TABLE IF NOT EXISTS pending_proofs (
+    y TEXT NOT NULL PRIMARY KEY,
+    amount BIGINT NOT NULL,
+    secret TEXT NOT NULL,
+    c TEXT NOT NULL,
+    keyset_id TEXT NOT NULL
+);
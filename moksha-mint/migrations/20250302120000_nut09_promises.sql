@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS promises (
+    b_ TEXT NOT NULL PRIMARY KEY,
+    c_ TEXT NOT NULL,
+    amount BIGINT NOT NULL,
+    keyset_id TEXT NOT NULL
+);
@@ -0,0 +1,2 @@
+ALTER TABLE promises ADD COLUMN dleq_e TEXT;
+ALTER TABLE promises ADD COLUMN dleq_s TEXT;
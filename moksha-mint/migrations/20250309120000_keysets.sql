@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS keysets (
+    id TEXT NOT NULL PRIMARY KEY,
+    unit TEXT NOT NULL,
+    derivation_path TEXT NOT NULL,
+    active BOOLEAN NOT NULL,
+    created_at BIGINT NOT NULL
+);
@@ -0,0 +1,2 @@
+ALTER TABLE keysets
+    ADD COLUMN IF NOT EXISTS input_fee_ppk BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,5 @@
+-- proofs of a melt are pending while the backend pays the quote
+ALTER TABLE pending_proofs
+    ADD COLUMN IF NOT EXISTS quote_id UUID;
+
+CREATE INDEX IF NOT EXISTS pending_proofs_quote_id_idx ON pending_proofs (quote_id);
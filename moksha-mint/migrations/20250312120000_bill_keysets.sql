@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS bill_keysets (
+    id TEXT NOT NULL PRIMARY KEY,
+    bill_id TEXT NOT NULL UNIQUE,
+    public_keys TEXT NOT NULL,
+    maturity_date BIGINT NOT NULL,
+    face_value BIGINT NOT NULL,
+    amount_minted BIGINT NOT NULL DEFAULT 0,
+    status TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
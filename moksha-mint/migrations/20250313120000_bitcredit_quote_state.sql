@@ -0,0 +1,23 @@
+ALTER TABLE bitcredit_mint_quotes
+    ADD COLUMN IF NOT EXISTS state TEXT NOT NULL DEFAULT 'OFFERED',
+    ADD COLUMN IF NOT EXISTS expiry BIGINT NOT NULL DEFAULT 0;
+
+UPDATE bitcredit_mint_quotes SET
+    state = CASE WHEN sent THEN 'MINTED' WHEN endorsed THEN 'ENDORSED' ELSE 'OFFERED' END,
+    expiry = EXTRACT(EPOCH FROM NOW())::BIGINT + 86400;
+
+ALTER TABLE bitcredit_mint_quotes
+    ALTER COLUMN state DROP DEFAULT,
+    ALTER COLUMN expiry DROP DEFAULT,
+    DROP COLUMN IF EXISTS sent,
+    DROP COLUMN IF EXISTS endorsed;
+
+CREATE TABLE IF NOT EXISTS bitcredit_mint_quote_transitions (
+    id BIGSERIAL PRIMARY KEY,
+    quote_id UUID NOT NULL,
+    state TEXT NOT NULL,
+    reason TEXT,
+    created_at BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS bitcredit_mint_quote_transitions_quote_id
+    ON bitcredit_mint_quote_transitions (quote_id);
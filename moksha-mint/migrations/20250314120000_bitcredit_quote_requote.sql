@@ -0,0 +1,5 @@
+-- a bill can be quoted again after its quote has been rejected or has expired
+ALTER TABLE bitcredit_mint_quotes DROP CONSTRAINT IF EXISTS bitcredit_mint_quotes_pkey;
+ALTER TABLE bitcredit_mint_quotes ADD PRIMARY KEY (id);
+CREATE UNIQUE INDEX IF NOT EXISTS bitcredit_mint_quotes_open_bill_id
+    ON bitcredit_mint_quotes (bill_id) WHERE state NOT IN ('REJECTED', 'EXPIRED');
@@ -0,0 +1,19 @@
+CREATE TABLE IF NOT EXISTS treasury_entries (
+    id BIGSERIAL PRIMARY KEY,
+    bill_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    amount BIGINT NOT NULL,
+    reference TEXT,
+    created_at BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS treasury_entries_bill_id ON treasury_entries (bill_id);
+
+-- ecash the mint issued to itself for its earnings, it has never been handed out
+CREATE TABLE IF NOT EXISTS treasury_proofs (
+    secret TEXT NOT NULL PRIMARY KEY,
+    bill_id TEXT NOT NULL,
+    amount BIGINT NOT NULL,
+    c TEXT NOT NULL,
+    keyset_id TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS treasury_proofs_bill_id ON treasury_proofs (bill_id);
@@ -0,0 +1,9 @@
+-- payments from the treasury that have been started but not settled, the amount stays reserved
+CREATE TABLE IF NOT EXISTS treasury_melts (
+    payment_hash TEXT NOT NULL PRIMARY KEY,
+    bill_id TEXT NOT NULL,
+    payment_request TEXT NOT NULL,
+    amount BIGINT NOT NULL,
+    fee_reserve BIGINT NOT NULL,
+    created_at BIGINT NOT NULL
+);
@@ -0,0 +1,3 @@
+-- unit of the ecash that is minted or melted for the invoice, amounts of melt quotes are in this unit
+ALTER TABLE bolt11_mint_quotes ADD COLUMN IF NOT EXISTS unit TEXT NOT NULL DEFAULT 'sat';
+ALTER TABLE bolt11_melt_quotes ADD COLUMN IF NOT EXISTS unit TEXT NOT NULL DEFAULT 'sat';
@@ -0,0 +1,17 @@
+#[derive(Debug, thiserror::Error)]
+pub enum BillRegistryError {
+    #[error("reqwest error: {0}")]
+    ReqwestError(#[from] reqwest::Error),
+
+    #[error("url error: {0}")]
+    UrlError(#[from] url::ParseError),
+
+    #[error("Bill {0} not found")]
+    NotFound(String),
+
+    #[error("Unauthorized")]
+    Unauthorized,
+
+    #[error("Unexpected status {0}")]
+    UnexpectedStatus(u16),
+}
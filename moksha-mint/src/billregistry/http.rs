@@ -0,0 +1,160 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use url::Url;
+
+use crate::{config::BillRegistryConfig, error::MokshaMintError};
+
+use super::{error::BillRegistryError, BillInfo, BillRegistry, Endorsement};
+
+/// Asks the REST api of an E-Bills node about the bills
+#[derive(Clone)]
+pub struct HttpBillRegistry {
+    url: Url,
+    api_key: Option<String>,
+    client: reqwest::Client,
+}
+
+impl HttpBillRegistry {
+    pub fn new(config: &BillRegistryConfig) -> Result<Self, BillRegistryError> {
+        Ok(Self {
+            url: config.url.clone(),
+            api_key: config.api_key.clone(),
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(config.timeout_secs))
+                .build()?,
+        })
+    }
+
+    /// appends the endpoint and the escaped bill id to the path of the base url
+    fn endpoint_url(&self, endpoint: &str, bill_id: &str) -> Result<Url, BillRegistryError> {
+        // dot segments are normalized away by the url, so they can't name a bill
+        if bill_id.is_empty() || bill_id == "." || bill_id == ".." {
+            return Err(BillRegistryError::NotFound(bill_id.to_owned()));
+        }
+        let mut url = self.url.clone();
+        url.path_segments_mut()
+            .map_err(|_| url::ParseError::RelativeUrlWithCannotBeABaseBase)?
+            .pop_if_empty()
+            .extend(endpoint.split('/'))
+            .push(bill_id);
+        Ok(url)
+    }
+
+    async fn make_get<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        bill_id: &str,
+    ) -> Result<T, BillRegistryError> {
+        let url = self.endpoint_url(endpoint, bill_id)?;
+        let mut request = self.client.get(url);
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        let response = request.send().await?;
+
+        match response.status() {
+            reqwest::StatusCode::OK => Ok(response.json().await?),
+            reqwest::StatusCode::NOT_FOUND => Err(BillRegistryError::NotFound(bill_id.to_owned())),
+            reqwest::StatusCode::UNAUTHORIZED => Err(BillRegistryError::Unauthorized),
+            status => Err(BillRegistryError::UnexpectedStatus(status.as_u16())),
+        }
+    }
+}
+
+#[async_trait]
+impl BillRegistry for HttpBillRegistry {
+    async fn is_holder(&self, bill_id: &str) -> Result<bool, MokshaMintError> {
+        Ok(self.make_get("bill/holder", bill_id).await?)
+    }
+
+    async fn get_bill(&self, bill_id: &str) -> Result<BillInfo, MokshaMintError> {
+        Ok(self.make_get("bill/return", bill_id).await?)
+    }
+
+    async fn get_endorsements(&self, bill_id: &str) -> Result<Vec<Endorsement>, MokshaMintError> {
+        Ok(self.make_get("bill/endorsements", bill_id).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HttpBillRegistry;
+    use crate::{
+        billregistry::{error::BillRegistryError, BillRegistry},
+        config::BillRegistryConfig,
+        error::MokshaMintError,
+    };
+
+    fn registry(url: &str) -> anyhow::Result<HttpBillRegistry> {
+        Ok(HttpBillRegistry::new(&BillRegistryConfig {
+            url: url.parse()?,
+            timeout_secs: 1,
+            api_key: None,
+        })?)
+    }
+
+    #[test]
+    fn test_endpoint_url_keeps_base_path() -> anyhow::Result<()> {
+        for base in [
+            "http://localhost:8000/api/v1",
+            "http://localhost:8000/api/v1/",
+        ] {
+            let url = registry(base)?.endpoint_url("bill/holder", "bill")?;
+            assert_eq!(
+                "http://localhost:8000/api/v1/bill/holder/bill",
+                url.as_str()
+            );
+        }
+
+        let url = registry("http://localhost:8000")?.endpoint_url("bill/holder", "bill")?;
+        assert_eq!("http://localhost:8000/bill/holder/bill", url.as_str());
+        Ok(())
+    }
+
+    #[test]
+    fn test_endpoint_url_escapes_bill_id() -> anyhow::Result<()> {
+        let registry = registry("http://localhost:8000/api")?;
+
+        let url = registry.endpoint_url("bill/return", "a/b?c#d")?;
+        assert_eq!(
+            "http://localhost:8000/api/bill/return/a%2Fb%3Fc%23d",
+            url.as_str()
+        );
+        assert_eq!(None, url.query());
+        assert_eq!(None, url.fragment());
+
+        for bill_id in ["", ".", ".."] {
+            assert!(matches!(
+                registry.endpoint_url("bill/return", bill_id),
+                Err(BillRegistryError::NotFound(_))
+            ));
+        }
+
+        let url = registry.endpoint_url("bill/return", "../..")?;
+        assert_eq!(
+            "http://localhost:8000/api/bill/return/..%2F..",
+            url.as_str()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_registry() -> anyhow::Result<()> {
+        let registry = HttpBillRegistry::new(&BillRegistryConfig {
+            url: "http://127.0.0.1:1".parse()?,
+            timeout_secs: 1,
+            api_key: None,
+        })?;
+
+        let result = registry.is_holder("bill").await;
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::BillRegistry(
+                BillRegistryError::ReqwestError(_)
+            ))
+        ));
+        Ok(())
+    }
+}
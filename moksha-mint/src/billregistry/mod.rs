@@ -0,0 +1,46 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::error::MokshaMintError;
+
+pub mod error;
+pub mod http;
+
+#[cfg(test)]
+use mockall::automock;
+
+/// Access to the E-Bills network, which knows the bills, their holders and their endorsements
+#[cfg_attr(test, automock)]
+#[async_trait]
+pub trait BillRegistry: Send + Sync {
+    /// Returns true if the mint is the current holder of the bill
+    async fn is_holder(&self, bill_id: &str) -> Result<bool, MokshaMintError>;
+
+    async fn get_bill(&self, bill_id: &str) -> Result<BillInfo, MokshaMintError>;
+
+    /// Returns the endorsements of the bill, the oldest first
+    async fn get_endorsements(&self, bill_id: &str) -> Result<Vec<Endorsement>, MokshaMintError>;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BillInfo {
+    pub id: String,
+    /// amount in sat
+    pub amount: u64,
+    /// unix timestamp
+    pub maturity_date: i64,
+    pub drawee_node_id: String,
+    pub holder_node_id: String,
+    /// hex encoded public key of the holder's node, requests of the holder are signed by it
+    pub holder_public_key: String,
+    /// true if the drawee paid the bill
+    pub paid: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Endorsement {
+    pub endorser_node_id: String,
+    pub endorsee_node_id: String,
+    /// unix timestamp
+    pub timestamp: i64,
+}
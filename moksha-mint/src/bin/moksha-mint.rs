@@ -31,6 +31,9 @@ pub async fn main() -> anyhow::Result<()> {
     let MintConfig {
         privatekey,
         derivation_path,
+        input_fee_ppk,
+        units,
+        operator_api_key,
         info,
         lightning_fee,
         server,
@@ -38,6 +41,12 @@ pub async fn main() -> anyhow::Result<()> {
         lightning_backend,
         tracing,
         database,
+        bill_registry,
+        clock,
+        pricing,
+        treasury,
+        bill_key_encryption,
+        redemption,
     } = MintConfig::read_config_with_defaults();
 
     init_tracing(tracing.clone())?;
@@ -47,11 +56,20 @@ pub async fn main() -> anyhow::Result<()> {
         .with_server(Some(server))
         .with_private_key(privatekey)
         .with_derivation_path(derivation_path)
+        .with_input_fee_ppk(input_fee_ppk)
+        .with_units(units)
+        .with_operator_api_key(operator_api_key)
         .with_db(Some(database))
         .with_lightning(lightning_backend.expect("lightning not set"))
         .with_btc_onchain(btconchain_backend)
         .with_fee(Some(lightning_fee))
         .with_tracing(tracing)
+        .with_bill_registry(Some(bill_registry))
+        .with_clock(Some(clock))
+        .with_pricing(Some(pricing))
+        .with_treasury(Some(treasury))
+        .with_bill_key_encryption(Some(bill_key_encryption))
+        .with_redemption(Some(redemption))
         .build()
         .await;
 
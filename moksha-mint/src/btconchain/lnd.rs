@@ -3,7 +3,10 @@ use super::{BtcOnchain, EstimateFeeResult, SendCoinsResult};
 use crate::error::MokshaMintError;
 use async_trait::async_trait;
 use fedimint_tonic_lnd::{
-    lnrpc::{AddressType, EstimateFeeRequest, NewAddressRequest, SendCoinsRequest},
+    lnrpc::{
+        AddressType, EstimateFeeRequest, GetTransactionsRequest, NewAddressRequest,
+        SendCoinsRequest,
+    },
     walletrpc::ListUnspentRequest,
     Client,
 };
@@ -109,6 +112,7 @@ impl BtcOnchain for LndBtcOnchain {
         address: &str,
         amount: u64,
         sat_per_vbyte: u32,
+        label: &str,
     ) -> Result<SendCoinsResult, MokshaMintError> {
         let response = self
             .client_lock()
@@ -117,6 +121,7 @@ impl BtcOnchain for LndBtcOnchain {
                 addr: address.to_owned(),
                 amount: amount as i64,
                 sat_per_vbyte: sat_per_vbyte as u64,
+                label: label.to_owned(),
                 ..Default::default()
             })
             .await?
@@ -127,6 +132,26 @@ impl BtcOnchain for LndBtcOnchain {
         })
     }
 
+    #[instrument(level = "debug", skip(self), err)]
+    async fn find_sent_transaction(&self, label: &str) -> Result<Option<String>, MokshaMintError> {
+        let response = self
+            .client_lock()
+            .await?
+            .get_transactions(GetTransactionsRequest {
+                // include unconfirmed transactions
+                end_height: -1,
+                ..Default::default()
+            })
+            .await?
+            .into_inner();
+
+        Ok(response
+            .transactions
+            .into_iter()
+            .find(|transaction| transaction.label == label)
+            .map(|transaction| transaction.tx_hash))
+    }
+
     #[instrument(level = "debug", skip(self), err)]
     async fn estimate_fee(
         &self,
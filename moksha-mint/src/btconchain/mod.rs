@@ -11,13 +11,18 @@ use mockall::automock;
 #[async_trait]
 pub trait BtcOnchain: Send + Sync {
     async fn new_address(&self) -> Result<String, MokshaMintError>;
+    /// Sends the amount to the address, the transaction is labeled in the wallet of the backend
     async fn send_coins(
         &self,
         address: &str,
         amount: u64,
         sat_per_vbyte: u32,
+        label: &str,
     ) -> Result<SendCoinsResult, MokshaMintError>;
 
+    /// Returns the txid of the transaction with the label if the wallet has sent one
+    async fn find_sent_transaction(&self, label: &str) -> Result<Option<String>, MokshaMintError>;
+
     async fn estimate_fee(
         &self,
         address: &str,
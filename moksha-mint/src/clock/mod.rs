@@ -0,0 +1,55 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use async_trait::async_trait;
+use chrono::Utc;
+
+use crate::error::MokshaMintError;
+
+pub mod remote;
+
+/// Source of the current time for expiry, locktime and maturity checks
+#[async_trait]
+pub trait Clock: Send + Sync {
+    /// Returns the current unix timestamp in seconds
+    async fn now(&self) -> Result<u64, MokshaMintError>;
+}
+
+/// Uses the local system time
+#[derive(Debug, Clone, Default)]
+pub struct SystemClock;
+
+#[async_trait]
+impl Clock for SystemClock {
+    async fn now(&self) -> Result<u64, MokshaMintError> {
+        Ok(Utc::now().timestamp() as u64)
+    }
+}
+
+/// Returns a fixed time that only changes when it is set or advanced, used in tests
+#[derive(Debug, Default)]
+pub struct FixedClock {
+    timestamp: AtomicU64,
+}
+
+impl FixedClock {
+    pub const fn new(timestamp: u64) -> Self {
+        Self {
+            timestamp: AtomicU64::new(timestamp),
+        }
+    }
+
+    pub fn set(&self, timestamp: u64) {
+        self.timestamp.store(timestamp, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, seconds: u64) {
+        self.timestamp.fetch_add(seconds, Ordering::SeqCst);
+    }
+}
+
+#[async_trait]
+impl Clock for FixedClock {
+    async fn now(&self) -> Result<u64, MokshaMintError> {
+        Ok(self.timestamp.load(Ordering::SeqCst))
+    }
+}
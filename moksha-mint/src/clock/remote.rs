@@ -0,0 +1,176 @@
+use std::{sync::RwLock, time::Duration};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::Deserialize;
+use tracing::warn;
+use url::Url;
+
+use crate::{config::ClockConfig, error::MokshaMintError};
+
+use super::Clock;
+
+#[derive(Deserialize, Debug)]
+struct TimeResponse {
+    /// unix timestamp in seconds
+    timestamp: u64,
+}
+
+/// Reads the time from a remote time service that returns `{"timestamp": <unix seconds>}`.
+///
+/// The remote time is only trusted if it is within `max_skew_secs` of the local time, otherwise
+/// one of the clocks is wrong and no time is returned.
+///
+/// The offset to the local clock is only fetched again after `refresh_secs`, if the time service
+/// is unavailable by then the last known offset is used.
+pub struct RemoteClock {
+    url: Url,
+    max_skew_secs: u64,
+    refresh_secs: u64,
+    client: reqwest::Client,
+    offset: RwLock<Option<Offset>>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Offset {
+    /// remote time minus local time in seconds
+    secs: i64,
+    /// local time when the offset was fetched
+    fetched_at: u64,
+}
+
+impl Offset {
+    fn apply(&self, local: u64) -> u64 {
+        local.saturating_add_signed(self.secs)
+    }
+}
+
+impl RemoteClock {
+    pub fn new(url: Url, config: &ClockConfig) -> Result<Self, MokshaMintError> {
+        Ok(Self {
+            url,
+            max_skew_secs: config.max_skew_secs,
+            refresh_secs: config.refresh_secs,
+            offset: RwLock::new(None),
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(config.timeout_secs))
+                .build()
+                .map_err(|e| MokshaMintError::ClockUnavailable(e.to_string()))?,
+        })
+    }
+
+    async fn fetch(&self) -> Result<u64, reqwest::Error> {
+        let response = self
+            .client
+            .get(self.url.clone())
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.json::<TimeResponse>().await?.timestamp)
+    }
+
+    fn cached_offset(&self) -> Option<Offset> {
+        *self.offset.read().expect("clock offset lock poisoned")
+    }
+}
+
+#[async_trait]
+impl Clock for RemoteClock {
+    async fn now(&self) -> Result<u64, MokshaMintError> {
+        let local = Utc::now().timestamp() as u64;
+        let cached = self.cached_offset();
+        if let Some(offset) = cached {
+            if local.saturating_sub(offset.fetched_at) < self.refresh_secs {
+                return Ok(offset.apply(local));
+            }
+        }
+
+        let remote = match self.fetch().await {
+            Ok(remote) => remote,
+            Err(e) => {
+                warn!("time service {} unavailable: {e}", self.url);
+                return match cached {
+                    Some(offset) => Ok(offset.apply(local)),
+                    None => Err(MokshaMintError::ClockUnavailable(e.to_string())),
+                };
+            }
+        };
+        let remote = check_skew(remote, local, self.max_skew_secs)?;
+        *self.offset.write().expect("clock offset lock poisoned") = Some(Offset {
+            secs: remote as i64 - local as i64,
+            fetched_at: local,
+        });
+        Ok(remote)
+    }
+}
+
+fn check_skew(remote: u64, local: u64, max_skew_secs: u64) -> Result<u64, MokshaMintError> {
+    let skew = remote.abs_diff(local);
+    if skew > max_skew_secs {
+        return Err(MokshaMintError::ClockSkew(skew));
+    }
+    Ok(remote)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::{check_skew, Offset, RemoteClock};
+    use crate::{clock::Clock, config::ClockConfig, error::MokshaMintError};
+
+    #[test]
+    fn test_check_skew() {
+        assert_eq!(1_000, check_skew(1_000, 1_060, 60).unwrap());
+        assert_eq!(1_060, check_skew(1_060, 1_000, 60).unwrap());
+        assert!(matches!(
+            check_skew(1_000, 1_061, 60),
+            Err(MokshaMintError::ClockSkew(61))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_time_service() -> anyhow::Result<()> {
+        let clock = RemoteClock::new(
+            "http://127.0.0.1:1".parse()?,
+            &ClockConfig {
+                timeout_secs: 1,
+                ..Default::default()
+            },
+        )?;
+        assert!(matches!(
+            clock.now().await,
+            Err(MokshaMintError::ClockUnavailable(_))
+        ));
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_cached_offset_is_used() -> anyhow::Result<()> {
+        let clock = RemoteClock::new(
+            "http://127.0.0.1:1".parse()?,
+            &ClockConfig {
+                timeout_secs: 1,
+                refresh_secs: 60,
+                ..Default::default()
+            },
+        )?;
+
+        // fresh offset, the time service is not asked
+        let local = Utc::now().timestamp() as u64;
+        *clock.offset.write().unwrap() = Some(Offset {
+            secs: 30,
+            fetched_at: local,
+        });
+        let now = clock.now().await?;
+        assert!(now >= local + 30 && now <= local + 31);
+
+        // stale offset, the time service is unavailable so the last known offset is used
+        *clock.offset.write().unwrap() = Some(Offset {
+            secs: -30,
+            fetched_at: local - 120,
+        });
+        let now = clock.now().await?;
+        assert!(now >= local - 30 && now <= local - 28);
+        Ok(())
+    }
+}
@@ -5,6 +5,7 @@ use moksha_core::primitives::{
     CurrencyUnit, Nut17, Nut18, PaymentMethod, PaymentMethodConfigBtcOnchain,
 };
 use serde::{Deserialize, Serialize};
+use url::Url;
 
 use crate::lightning::{
     alby::AlbyLightningSettings, cln::ClnLightningSettings, lnbits::LnbitsLightningSettings,
@@ -18,6 +19,16 @@ pub struct Opts {
     pub privatekey: String,
     #[clap(long, env = "MINT_DERIVATION_PATH")]
     pub derivation_path: Option<String>,
+    /// fee per input in parts per thousand for new keysets (Nut-02)
+    #[clap(long, env = "MINT_INPUT_FEE_PPK", default_value_t = 0)]
+    pub input_fee_ppk: u64,
+    /// currency units the mint creates keysets for, `sat` is always supported. Only `sat` and
+    /// `msat` can be minted and melted over bolt11.
+    #[clap(long, env = "MINT_UNITS", value_delimiter = ',', default_value = "sat")]
+    pub units: Vec<CurrencyUnit>,
+    /// bearer token for the operator api (keyset rotation), the api is disabled if not set
+    #[clap(long, env = "MINT_OPERATOR_API_KEY")]
+    pub operator_api_key: Option<String>,
     #[clap(flatten)]
     pub info: MintInfoConfig,
     #[clap(flatten)]
@@ -26,6 +37,18 @@ pub struct Opts {
     pub server: ServerConfig,
     #[clap(flatten)]
     pub database: DatabaseConfig,
+    #[clap(flatten)]
+    pub bill_registry: BillRegistryConfig,
+    #[clap(flatten)]
+    pub clock: ClockConfig,
+    #[clap(flatten)]
+    pub pricing: PricingConfig,
+    #[clap(flatten)]
+    pub treasury: TreasuryConfig,
+    #[clap(flatten)]
+    pub bill_key_encryption: BillKeyEncryptionConfig,
+    #[clap(flatten)]
+    pub redemption: RedemptionConfig,
 
     #[clap(long, env = "MINT_LIGHTNING_BACKEND")]
     pub lightning_backend: LightningTypeVariant,
@@ -71,6 +94,9 @@ pub struct TracingConfig {
 pub struct MintConfig {
     pub privatekey: String,
     pub derivation_path: Option<String>,
+    pub input_fee_ppk: u64,
+    pub units: Vec<CurrencyUnit>,
+    pub operator_api_key: Option<String>,
     pub info: MintInfoConfig,
     pub lightning_fee: LightningFeeConfig,
     pub server: ServerConfig,
@@ -78,6 +104,12 @@ pub struct MintConfig {
     pub lightning_backend: Option<LightningType>,
     pub tracing: Option<TracingConfig>,
     pub database: DatabaseConfig,
+    pub bill_registry: BillRegistryConfig,
+    pub clock: ClockConfig,
+    pub pricing: PricingConfig,
+    pub treasury: TreasuryConfig,
+    pub bill_key_encryption: BillKeyEncryptionConfig,
+    pub redemption: RedemptionConfig,
 }
 
 impl From<(Opts, LightningType, Option<BtcOnchainConfig>)> for MintConfig {
@@ -85,6 +117,9 @@ impl From<(Opts, LightningType, Option<BtcOnchainConfig>)> for MintConfig {
         Self {
             privatekey: opts.privatekey,
             derivation_path: opts.derivation_path,
+            input_fee_ppk: opts.input_fee_ppk,
+            units: opts.units,
+            operator_api_key: opts.operator_api_key,
             info: opts.info,
             lightning_fee: opts.lightning_fee,
             server: opts.server,
@@ -92,6 +127,12 @@ impl From<(Opts, LightningType, Option<BtcOnchainConfig>)> for MintConfig {
             lightning_backend: Some(ln),
             tracing: opts.tracing,
             database: opts.database,
+            bill_registry: opts.bill_registry,
+            clock: opts.clock,
+            pricing: opts.pricing,
+            treasury: opts.treasury,
+            bill_key_encryption: opts.bill_key_encryption,
+            redemption: opts.redemption,
         }
     }
 }
@@ -128,6 +169,9 @@ impl MintConfig {
     pub const fn new(
         private_key: String,
         derivation_path: Option<String>,
+        input_fee_ppk: u64,
+        units: Vec<CurrencyUnit>,
+        operator_api_key: Option<String>,
         info: MintInfoConfig,
         lightning_fee: LightningFeeConfig,
         server: ServerConfig,
@@ -135,10 +179,19 @@ impl MintConfig {
         btconchain_backend: Option<BtcOnchainConfig>,
         lightning_backend: Option<LightningType>,
         tracing: Option<TracingConfig>,
+        bill_registry: BillRegistryConfig,
+        clock: ClockConfig,
+        pricing: PricingConfig,
+        treasury: TreasuryConfig,
+        bill_key_encryption: BillKeyEncryptionConfig,
+        redemption: RedemptionConfig,
     ) -> Self {
         Self {
             privatekey: private_key,
             derivation_path,
+            input_fee_ppk,
+            units,
+            operator_api_key,
             info,
             lightning_fee,
             server,
@@ -146,6 +199,12 @@ impl MintConfig {
             lightning_backend,
             tracing,
             database,
+            bill_registry,
+            clock,
+            pricing,
+            treasury,
+            bill_key_encryption,
+            redemption,
         }
     }
 }
@@ -274,6 +333,246 @@ impl Default for ServerConfig {
         }
     }
 }
+#[derive(Debug, Clone, Parser)]
+pub struct BillRegistryConfig {
+    /// url of the E-Bills node that knows the bills and their holders
+    #[clap(
+        long,
+        default_value = "http://localhost:8000",
+        env = "MINT_BILL_REGISTRY_URL"
+    )]
+    pub url: Url,
+    #[clap(long, default_value_t = 10, env = "MINT_BILL_REGISTRY_TIMEOUT_SECS")]
+    pub timeout_secs: u64,
+    /// bearer token for the api of the E-Bills node
+    #[clap(long, env = "MINT_BILL_REGISTRY_API_KEY")]
+    pub api_key: Option<String>,
+}
+
+impl Default for BillRegistryConfig {
+    fn default() -> Self {
+        Self {
+            url: "http://localhost:8000".parse().expect("invalid url"),
+            timeout_secs: 10,
+            api_key: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct ClockConfig {
+    /// url of a time service returning `{"timestamp": <unix seconds>}`, the system clock is used if not set
+    #[clap(long, env = "MINT_CLOCK_URL")]
+    pub url: Option<Url>,
+    /// maximum allowed difference in seconds between the time service and the system clock
+    #[clap(long, default_value_t = 60, env = "MINT_CLOCK_MAX_SKEW_SECS")]
+    pub max_skew_secs: u64,
+    #[clap(long, default_value_t = 5, env = "MINT_CLOCK_TIMEOUT_SECS")]
+    pub timeout_secs: u64,
+    /// seconds after which the offset to the time service is fetched again
+    #[clap(long, default_value_t = 300, env = "MINT_CLOCK_REFRESH_SECS")]
+    pub refresh_secs: u64,
+}
+
+impl Default for ClockConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            max_skew_secs: 60,
+            timeout_secs: 5,
+            refresh_secs: 300,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct PricingConfig {
+    /// yearly discount on the face value of a bill, 0.05 is 5%
+    #[clap(
+        long,
+        default_value_t = 0.05,
+        env = "MINT_PRICING_ANNUAL_DISCOUNT_RATE"
+    )]
+    pub annual_discount_rate: f64,
+    /// risk tiers in the format `name:premium`, the premium is added to the annual discount rate
+    #[clap(
+        long,
+        env = "MINT_PRICING_RISK_TIERS",
+        value_delimiter = ',',
+        default_value = "default:0"
+    )]
+    pub risk_tiers: Vec<RiskTier>,
+    /// drawees in the format `node_id:tier`
+    #[clap(long, env = "MINT_PRICING_DRAWEE_TIERS", value_delimiter = ',')]
+    pub drawee_tiers: Vec<DraweeTier>,
+    /// tier of drawees that are not listed in `drawee_tiers`
+    #[clap(
+        long,
+        default_value = "default",
+        env = "MINT_PRICING_DEFAULT_RISK_TIER"
+    )]
+    pub default_risk_tier: String,
+}
+
+impl Default for PricingConfig {
+    fn default() -> Self {
+        Self {
+            annual_discount_rate: 0.05,
+            risk_tiers: vec![RiskTier {
+                name: "default".to_owned(),
+                premium: 0.0,
+            }],
+            drawee_tiers: vec![],
+            default_risk_tier: "default".to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RiskTier {
+    pub name: String,
+    pub premium: f64,
+}
+
+impl FromStr for RiskTier {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, premium) = s
+            .split_once(':')
+            .ok_or_else(|| format!("invalid risk tier {s}, expected name:premium"))?;
+        Ok(Self {
+            name: name.trim().to_owned(),
+            premium: premium
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid premium in risk tier {s}"))?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DraweeTier {
+    pub drawee_node_id: String,
+    pub tier: String,
+}
+
+impl FromStr for DraweeTier {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (drawee_node_id, tier) = s
+            .rsplit_once(':')
+            .ok_or_else(|| format!("invalid drawee tier {s}, expected node_id:tier"))?;
+        Ok(Self {
+            drawee_node_id: drawee_node_id.trim().to_owned(),
+            tier: tier.trim().to_owned(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, Parser)]
+pub struct TreasuryConfig {
+    /// bearer token for the treasury api, the api is disabled if not set
+    #[clap(long, env = "MINT_TREASURY_API_KEY")]
+    pub api_key: Option<String>,
+    /// issue the earnings of a bill as ecash that is kept in the treasury
+    #[clap(long, default_value_t = false, env = "MINT_TREASURY_ISSUE_ECASH")]
+    pub issue_ecash: bool,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct RedemptionConfig {
+    /// seconds after maturity the drawee has to pay a bill before it is considered defaulted
+    #[clap(long, default_value_t = 0, env = "MINT_REDEMPTION_GRACE_PERIOD_SECS")]
+    pub grace_period_secs: u64,
+    /// what happens to `cr-sat` of defaulted bills: `reject` or `redeem`
+    #[clap(long, default_value = "reject", env = "MINT_REDEMPTION_DEFAULT_POLICY")]
+    pub default_policy: DefaultPolicy,
+}
+
+impl Default for RedemptionConfig {
+    fn default() -> Self {
+        Self {
+            grace_period_secs: 0,
+            default_policy: DefaultPolicy::Reject,
+        }
+    }
+}
+
+/// What the mint does with `cr-sat` of a bill that was not paid
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DefaultPolicy {
+    /// the tokens can not be redeemed for `sat`
+    #[default]
+    Reject,
+    /// the mint redeems the tokens and bears the loss
+    Redeem,
+}
+
+impl FromStr for DefaultPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(Self::Reject),
+            "redeem" => Ok(Self::Redeem),
+            _ => Err(format!(
+                "unknown default policy {s}, expected reject or redeem"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Parser)]
+pub struct BillKeyEncryptionConfig {
+    /// key-encryption keys for the private keys of bills in the format `id:hex` with 32 byte keys.
+    /// The first key encrypts the bill keys, the others are kept to decrypt bill keys that were
+    /// encrypted before a rotation. Bill keys are stored unencrypted if no key is set.
+    #[clap(long, env = "MINT_BILL_KEK", value_delimiter = ',')]
+    pub keys: Vec<KeyEncryptionKey>,
+    /// file with one key-encryption key `id:hex` per line, used after the keys of `MINT_BILL_KEK`
+    #[clap(long, env = "MINT_BILL_KEK_FILE")]
+    pub key_file: Option<PathBuf>,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct KeyEncryptionKey {
+    pub id: String,
+    pub key: [u8; 32],
+}
+
+impl FromStr for KeyEncryptionKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (id, key) = s
+            .split_once(':')
+            .ok_or_else(|| "invalid key-encryption key, expected id:hex".to_owned())?;
+        let id = id.trim();
+        if id.is_empty() {
+            return Err("key-encryption key without id".to_owned());
+        }
+        let key = hex::decode(key.trim())
+            .ok()
+            .and_then(|key| key.try_into().ok())
+            .ok_or_else(|| format!("key-encryption key {id} is not 32 bytes of hex"))?;
+        Ok(Self {
+            id: id.to_owned(),
+            key,
+        })
+    }
+}
+
+// the key itself must not end up in logs
+impl std::fmt::Debug for KeyEncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyEncryptionKey")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone, Default, Parser)]
 pub struct MintInfoConfig {
     #[clap(long, default_value = "moksha-mint", env = "MINT_INFO_NAME")]
@@ -1,10 +1,18 @@
-use crate::{error::MokshaMintError, model::Invoice};
+use crate::{
+    error::MokshaMintError,
+    model::{BillKeysetInfo, BillKeysetStatus, Invoice, MintKeysetInfo, TreasuryMelt},
+};
 use async_trait::async_trait;
-use moksha_core::primitives::{BitcreditMintQuote, BitcreditQuoteCheck, BitcreditRequestToMint};
+use moksha_core::primitives::{
+    BitcreditMintQuote, BitcreditMintQuoteTransition, BitcreditQuoteCheck, BitcreditRequestToMint,
+    CurrencyUnit, TreasuryEntry,
+};
 use moksha_core::{
+    blind::{BlindedMessage, BlindedSignature},
     primitives::{Bolt11MeltQuote, Bolt11MintQuote, BtcOnchainMeltQuote, BtcOnchainMintQuote},
     proof::Proofs,
 };
+use secp256k1::PublicKey;
 use uuid::Uuid;
 
 pub mod postgres;
@@ -13,15 +21,69 @@ pub mod postgres;
 pub trait Database {
     type DB: sqlx::Database;
     async fn begin_tx(&self) -> Result<sqlx::Transaction<Self::DB>, sqlx::Error>;
-    async fn get_used_proofs(
+    /// Returns the used proofs with the given secrets (looked up via the primary key)
+    async fn get_used_proofs_by_secrets(
         &self,
         tx: &mut sqlx::Transaction<Self::DB>,
+        secrets: &[String],
     ) -> Result<Proofs, MokshaMintError>;
+    /// Marks the proofs as spent. Fails with `ProofAlreadyUsed` if one of them has been spent
+    /// before, even by a concurrent transaction.
     async fn add_used_proofs(
         &self,
         tx: &mut sqlx::Transaction<Self::DB>,
         proofs: &Proofs,
     ) -> Result<(), MokshaMintError>;
+    async fn get_used_proofs_by_ys(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        ys: &[PublicKey],
+    ) -> Result<Proofs, MokshaMintError>;
+    /// Removes spent proofs again, used to release the inputs of a failed melt
+    async fn delete_used_proofs(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        proofs: &Proofs,
+    ) -> Result<(), MokshaMintError>;
+    async fn get_pending_proofs_by_ys(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        ys: &[PublicKey],
+    ) -> Result<Proofs, MokshaMintError>;
+    /// Marks the proofs as pending while the melt quote `quote_id` is being paid
+    async fn add_pending_proofs(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        proofs: &Proofs,
+        quote_id: &Uuid,
+    ) -> Result<(), MokshaMintError>;
+    async fn get_pending_proofs_by_quote(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        quote_id: &Uuid,
+    ) -> Result<Proofs, MokshaMintError>;
+    /// Returns the ids of all melt quotes that have pending proofs
+    async fn get_pending_quote_ids(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Vec<Uuid>, MokshaMintError>;
+    async fn delete_pending_proofs(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        quote_id: &Uuid,
+    ) -> Result<(), MokshaMintError>;
+
+    async fn add_promises(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        outputs: &[BlindedMessage],
+        promises: &[BlindedSignature],
+    ) -> Result<(), MokshaMintError>;
+    async fn get_promises(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        b_s: &[PublicKey],
+    ) -> Result<Vec<(BlindedMessage, BlindedSignature)>, MokshaMintError>;
 
     async fn get_pending_invoice(
         &self,
@@ -34,6 +96,7 @@ pub trait Database {
         key: String,
         invoice: &Invoice,
     ) -> Result<(), MokshaMintError>;
+    /// Fails with `RowNotFound` if the invoice has been deleted already
     async fn delete_pending_invoice(
         &self,
         tx: &mut sqlx::Transaction<Self::DB>,
@@ -66,6 +129,12 @@ pub trait Database {
         tx: &mut sqlx::Transaction<Self::DB>,
         id: &String,
     ) -> Result<BitcreditRequestToMint, MokshaMintError>;
+    /// Like `get_bitcredit_request_to_mint`, but locks the bill until the transaction ends
+    async fn get_bitcredit_request_to_mint_for_update(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        bill_id: &str,
+    ) -> Result<BitcreditRequestToMint, MokshaMintError>;
 
     async fn get_bitcredit_mint_quote(
         &self,
@@ -82,6 +151,18 @@ pub trait Database {
         tx: &mut sqlx::Transaction<Self::DB>,
         quote: &BitcreditMintQuote,
     ) -> Result<(), MokshaMintError>;
+    async fn add_bitcredit_mint_quote_transition(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        quote_id: &Uuid,
+        transition: &BitcreditMintQuoteTransition,
+    ) -> Result<(), MokshaMintError>;
+    /// Returns the state changes of the quote, the oldest first
+    async fn get_bitcredit_mint_quote_transitions(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        quote_id: &Uuid,
+    ) -> Result<Vec<BitcreditMintQuoteTransition>, MokshaMintError>;
 
     async fn add_bitcredit_request_to_mint(
         &self,
@@ -89,11 +170,109 @@ pub trait Database {
         request_to_mint: &BitcreditRequestToMint,
     ) -> Result<(), MokshaMintError>;
 
-    async fn add_mint_keyset(
+    async fn get_bitcredit_requests_to_mint(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Vec<BitcreditRequestToMint>, MokshaMintError>;
+
+    /// Replaces the stored bill key, e.g. after the key-encryption key was rotated
+    async fn update_bitcredit_request_to_mint_key(
         &self,
         tx: &mut sqlx::Transaction<Self::DB>,
-        keyset_id: &String,
-        keyset_public_key: &String,
+        bill_id: &str,
+        bill_key: &str,
+    ) -> Result<(), MokshaMintError>;
+
+    async fn get_bill_keysets(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Vec<BillKeysetInfo>, MokshaMintError>;
+    async fn add_bill_keyset(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        keyset: &BillKeysetInfo,
+    ) -> Result<(), MokshaMintError>;
+    async fn get_bill_keyset_status(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        id: &str,
+    ) -> Result<BillKeysetStatus, MokshaMintError>;
+    async fn update_bill_keyset_status(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        id: &str,
+        status: BillKeysetStatus,
+    ) -> Result<(), MokshaMintError>;
+    /// Adds `amount` to the amount minted for the bill of the keyset
+    async fn add_bill_amount_minted(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        id: &str,
+        amount: u64,
+    ) -> Result<(), MokshaMintError>;
+
+    async fn add_treasury_entry(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        entry: &TreasuryEntry,
+    ) -> Result<(), MokshaMintError>;
+    /// Returns all entries of the treasury ledger, the oldest first
+    async fn get_treasury_entries(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Vec<TreasuryEntry>, MokshaMintError>;
+    async fn add_treasury_proofs(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        bill_id: &str,
+        proofs: &Proofs,
+    ) -> Result<(), MokshaMintError>;
+    async fn get_treasury_proofs(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        bill_id: &str,
+    ) -> Result<Proofs, MokshaMintError>;
+    async fn delete_treasury_proofs(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        proofs: &Proofs,
+    ) -> Result<(), MokshaMintError>;
+    async fn add_treasury_melt(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        melt: &TreasuryMelt,
+    ) -> Result<(), MokshaMintError>;
+    /// Returns the payments from the treasury that have not been settled, the oldest first
+    async fn get_treasury_melts(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Vec<TreasuryMelt>, MokshaMintError>;
+    async fn delete_treasury_melt(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        payment_hash: &str,
+    ) -> Result<(), MokshaMintError>;
+
+    async fn get_keysets(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Vec<MintKeysetInfo>, MokshaMintError>;
+    async fn get_keyset(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        id: &str,
+    ) -> Result<MintKeysetInfo, MokshaMintError>;
+    async fn add_keyset(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        keyset: &MintKeysetInfo,
+    ) -> Result<(), MokshaMintError>;
+    /// Marks the keyset `id` as active and all other keysets of the same unit as inactive
+    async fn activate_keyset(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        id: &str,
+        unit: &CurrencyUnit,
     ) -> Result<(), MokshaMintError>;
 
     async fn check_bitcredit_quote(
@@ -107,6 +286,12 @@ pub trait Database {
         tx: &mut sqlx::Transaction<Self::DB>,
         key: &Uuid,
     ) -> Result<Bolt11MeltQuote, MokshaMintError>;
+    /// Like `get_bolt11_melt_quote`, but locks the quote until the transaction ends
+    async fn get_bolt11_melt_quote_for_update(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        key: &Uuid,
+    ) -> Result<Bolt11MeltQuote, MokshaMintError>;
     async fn add_bolt11_melt_quote(
         &self,
         tx: &mut sqlx::Transaction<Self::DB>,
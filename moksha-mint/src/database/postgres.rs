@@ -1,7 +1,9 @@
 #![allow(clippy::blocks_in_conditions)]
 use async_trait::async_trait;
 use moksha_core::{
-    dhke,
+    blind::{BlindSignatureDleq, BlindedMessage, BlindedSignature},
+    dhke::{self, Dhke},
+    error::MokshaCoreError,
     primitives::{
         Bolt11MeltQuote, Bolt11MintQuote, BtcOnchainMeltQuote, BtcOnchainMintQuote, CurrencyUnit,
     },
@@ -9,9 +11,18 @@ use moksha_core::{
 };
 
 use super::Database;
-use crate::{config::DatabaseConfig, error::MokshaMintError, model::Invoice};
-use moksha_core::primitives::{BitcreditMintQuote, BitcreditQuoteCheck, BitcreditRequestToMint};
+use crate::{
+    config::DatabaseConfig,
+    error::MokshaMintError,
+    model::{BillKeysetInfo, BillKeysetStatus, Invoice, MintKeysetInfo, TreasuryMelt},
+};
+use moksha_core::primitives::{
+    BitcreditMintQuote, BitcreditMintQuoteTransition, BitcreditQuoteCheck, BitcreditRequestToMint,
+    TreasuryEntry,
+};
+use secp256k1::{PublicKey, SecretKey};
 use sqlx::postgres::PgPoolOptions;
+use std::str::FromStr;
 use tracing::instrument;
 use uuid::Uuid;
 
@@ -35,6 +46,30 @@ impl PostgresDB {
             .run(&self.pool)
             .await
             .expect("Could not run migrations");
+        self.backfill_used_proofs_y()
+            .await
+            .expect("Could not backfill Y of used proofs");
+    }
+
+    /// Proofs that were spent before Nut-07 was implemented are stored without `Y`
+    async fn backfill_used_proofs_y(&self) -> Result<(), MokshaMintError> {
+        let mut tx = self.pool.begin().await?;
+        let secrets = sqlx::query!("SELECT secret FROM used_proofs WHERE y IS NULL")
+            .fetch_all(&mut *tx)
+            .await?;
+
+        for row in secrets {
+            let y = Dhke::hash_to_curve(row.secret.as_bytes())?;
+            sqlx::query!(
+                "UPDATE used_proofs SET y = $1 WHERE secret = $2",
+                y.to_string(),
+                row.secret
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
     }
 }
 
@@ -47,22 +82,27 @@ impl Database for PostgresDB {
     }
 
     #[instrument(level = "debug", skip(self), err)]
-    async fn get_used_proofs(
+    async fn get_used_proofs_by_secrets(
         &self,
         tx: &mut sqlx::Transaction<Self::DB>,
+        secrets: &[String],
     ) -> Result<Proofs, MokshaMintError> {
-        let proofs = sqlx::query!("SELECT * FROM used_proofs")
-            .fetch_all(&mut **tx)
-            .await?
-            .into_iter()
-            .map(|row| Proof {
-                amount: row.amount as u64,
-                secret: row.secret,
-                c: dhke::public_key_from_hex(&row.c).to_owned(),
-                keyset_id: row.keyset_id,
-                script: None,
-            })
-            .collect::<Vec<Proof>>();
+        let proofs = sqlx::query!(
+            "SELECT amount, secret, c, keyset_id FROM used_proofs WHERE secret = ANY($1)",
+            secrets
+        )
+        .fetch_all(&mut **tx)
+        .await?
+        .into_iter()
+        .map(|row| Proof {
+            amount: row.amount as u64,
+            secret: row.secret,
+            c: dhke::public_key_from_hex(&row.c).to_owned(),
+            keyset_id: row.keyset_id,
+            witness: None,
+            dleq: None,
+        })
+        .collect::<Vec<Proof>>();
 
         Ok(proofs.into())
     }
@@ -73,21 +113,279 @@ impl Database for PostgresDB {
         tx: &mut sqlx::Transaction<Self::DB>,
         proofs: &Proofs,
     ) -> Result<(), MokshaMintError> {
-        for proof in proofs.proofs() {
+        let proofs = proofs.proofs();
+        let amounts = proofs.iter().map(|p| p.amount as i64).collect::<Vec<_>>();
+        let secrets = proofs.iter().map(|p| p.secret.clone()).collect::<Vec<_>>();
+        let cs = proofs.iter().map(|p| p.c.to_string()).collect::<Vec<_>>();
+        let keyset_ids = proofs
+            .iter()
+            .map(|p| p.keyset_id.clone())
+            .collect::<Vec<_>>();
+        let ys = proofs
+            .iter()
+            .map(|p| Ok(p.y()?.to_string()))
+            .collect::<Result<Vec<_>, MokshaCoreError>>()?;
+
+        // the primary key on secret makes concurrent transactions that spend the same proof
+        // wait for each other, only the first one can commit
+        sqlx::query!(
+            "INSERT INTO used_proofs (amount, secret, c, keyset_id, y) SELECT * FROM UNNEST($1::BIGINT[], $2::TEXT[], $3::TEXT[], $4::TEXT[], $5::TEXT[])",
+            &amounts,
+            &secrets,
+            &cs,
+            &keyset_ids,
+            &ys
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(|err| match err {
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+                MokshaMintError::ProofAlreadyUsed(db_err.message().to_owned())
+            }
+            err => err.into(),
+        })?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn get_used_proofs_by_ys(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        ys: &[PublicKey],
+    ) -> Result<Proofs, MokshaMintError> {
+        let ys = ys.iter().map(|y| y.to_string()).collect::<Vec<String>>();
+        let proofs = sqlx::query!(
+            "SELECT amount, secret, c, keyset_id FROM used_proofs WHERE y = ANY($1)",
+            &ys
+        )
+        .fetch_all(&mut **tx)
+        .await?
+        .into_iter()
+        .map(|row| Proof {
+            amount: row.amount as u64,
+            secret: row.secret,
+            c: dhke::public_key_from_hex(&row.c).to_owned(),
+            keyset_id: row.keyset_id,
+            witness: None,
+            dleq: None,
+        })
+        .collect::<Vec<Proof>>();
+
+        Ok(proofs.into())
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn get_pending_proofs_by_ys(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        ys: &[PublicKey],
+    ) -> Result<Proofs, MokshaMintError> {
+        let ys = ys.iter().map(|y| y.to_string()).collect::<Vec<String>>();
+        let proofs = sqlx::query!(
+            "SELECT amount, secret, c, keyset_id FROM pending_proofs WHERE y = ANY($1)",
+            &ys
+        )
+        .fetch_all(&mut **tx)
+        .await?
+        .into_iter()
+        .map(|row| Proof {
+            amount: row.amount as u64,
+            secret: row.secret,
+            c: dhke::public_key_from_hex(&row.c).to_owned(),
+            keyset_id: row.keyset_id,
+            witness: None,
+            dleq: None,
+        })
+        .collect::<Vec<Proof>>();
+
+        Ok(proofs.into())
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn delete_used_proofs(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        proofs: &Proofs,
+    ) -> Result<(), MokshaMintError> {
+        let secrets = proofs
+            .proofs()
+            .iter()
+            .map(|p| p.secret.clone())
+            .collect::<Vec<_>>();
+        sqlx::query!("DELETE FROM used_proofs WHERE secret = ANY($1)", &secrets)
+            .execute(&mut **tx)
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn add_pending_proofs(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        proofs: &Proofs,
+        quote_id: &Uuid,
+    ) -> Result<(), MokshaMintError> {
+        let proofs = proofs.proofs();
+        let amounts = proofs.iter().map(|p| p.amount as i64).collect::<Vec<_>>();
+        let secrets = proofs.iter().map(|p| p.secret.clone()).collect::<Vec<_>>();
+        let cs = proofs.iter().map(|p| p.c.to_string()).collect::<Vec<_>>();
+        let keyset_ids = proofs
+            .iter()
+            .map(|p| p.keyset_id.clone())
+            .collect::<Vec<_>>();
+        let ys = proofs
+            .iter()
+            .map(|p| Ok(p.y()?.to_string()))
+            .collect::<Result<Vec<_>, MokshaCoreError>>()?;
+
+        sqlx::query!(
+            "INSERT INTO pending_proofs (amount, secret, c, keyset_id, y, quote_id) SELECT *, $6 FROM UNNEST($1::BIGINT[], $2::TEXT[], $3::TEXT[], $4::TEXT[], $5::TEXT[])",
+            &amounts,
+            &secrets,
+            &cs,
+            &keyset_ids,
+            &ys,
+            quote_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn get_pending_proofs_by_quote(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        quote_id: &Uuid,
+    ) -> Result<Proofs, MokshaMintError> {
+        let proofs = sqlx::query!(
+            "SELECT amount, secret, c, keyset_id FROM pending_proofs WHERE quote_id = $1",
+            quote_id
+        )
+        .fetch_all(&mut **tx)
+        .await?
+        .into_iter()
+        .map(|row| Proof {
+            amount: row.amount as u64,
+            secret: row.secret,
+            c: dhke::public_key_from_hex(&row.c).to_owned(),
+            keyset_id: row.keyset_id,
+            witness: None,
+            dleq: None,
+        })
+        .collect::<Vec<Proof>>();
+
+        Ok(proofs.into())
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn get_pending_quote_ids(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Vec<Uuid>, MokshaMintError> {
+        Ok(
+            sqlx::query!("SELECT DISTINCT quote_id FROM pending_proofs WHERE quote_id IS NOT NULL")
+                .fetch_all(&mut **tx)
+                .await?
+                .into_iter()
+                .filter_map(|row| row.quote_id)
+                .collect(),
+        )
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn delete_pending_proofs(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        quote_id: &Uuid,
+    ) -> Result<(), MokshaMintError> {
+        sqlx::query!("DELETE FROM pending_proofs WHERE quote_id = $1", quote_id)
+            .execute(&mut **tx)
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn add_promises(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        outputs: &[BlindedMessage],
+        promises: &[BlindedSignature],
+    ) -> Result<(), MokshaMintError> {
+        // the primary key on b_ rejects outputs that have been signed before
+        for (output, promise) in outputs.iter().zip(promises) {
             sqlx::query!(
-                "INSERT INTO used_proofs (amount, secret, c, keyset_id) VALUES ($1, $2, $3, $4)",
-                proof.amount as i64,
-                proof.secret,
-                proof.c.to_string(),
-                proof.keyset_id.to_string()
+                "INSERT INTO promises (b_, c_, amount, keyset_id, dleq_e, dleq_s) VALUES ($1, $2, $3, $4, $5, $6)",
+                output.b_.to_string(),
+                promise.c_.to_string(),
+                promise.amount as i64,
+                promise.id,
+                promise
+                    .dleq
+                    .as_ref()
+                    .map(|dleq| dleq.e.display_secret().to_string()),
+                promise
+                    .dleq
+                    .as_ref()
+                    .map(|dleq| dleq.s.display_secret().to_string())
             )
             .execute(&mut **tx)
-            .await?;
+            .await
+            .map_err(|err| match err {
+                sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+                    MokshaMintError::OutputAlreadySigned(output.b_.to_string())
+                }
+                err => err.into(),
+            })?;
         }
 
         Ok(())
     }
 
+    #[instrument(level = "debug", skip(self), err)]
+    async fn get_promises(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        b_s: &[PublicKey],
+    ) -> Result<Vec<(BlindedMessage, BlindedSignature)>, MokshaMintError> {
+        let b_s = b_s.iter().map(|b_| b_.to_string()).collect::<Vec<String>>();
+        let promises = sqlx::query!(
+            "SELECT b_, c_, amount, keyset_id, dleq_e, dleq_s FROM promises WHERE b_ = ANY($1)",
+            &b_s
+        )
+        .fetch_all(&mut **tx)
+        .await?
+        .into_iter()
+        .map(|row| {
+            let dleq = match (row.dleq_e, row.dleq_s) {
+                (Some(e), Some(s)) => Some(BlindSignatureDleq {
+                    e: SecretKey::from_str(&e).map_err(MokshaCoreError::from)?,
+                    s: SecretKey::from_str(&s).map_err(MokshaCoreError::from)?,
+                }),
+                _ => None,
+            };
+            Ok((
+                BlindedMessage {
+                    amount: row.amount as u64,
+                    b_: dhke::public_key_from_hex(&row.b_),
+                    id: row.keyset_id.clone(),
+                    witness: None,
+                },
+                BlindedSignature {
+                    amount: row.amount as u64,
+                    c_: dhke::public_key_from_hex(&row.c_),
+                    id: row.keyset_id,
+                    dleq,
+                },
+            ))
+        })
+        .collect::<Result<Vec<_>, MokshaMintError>>()?;
+
+        Ok(promises)
+    }
+
     #[instrument(level = "debug", skip(self))]
     async fn get_pending_invoice(
         &self,
@@ -133,9 +431,13 @@ impl Database for PostgresDB {
         tx: &mut sqlx::Transaction<Self::DB>,
         key: String,
     ) -> Result<(), MokshaMintError> {
-        sqlx::query!("DELETE FROM pending_invoices WHERE key = $1", key)
+        let result = sqlx::query!("DELETE FROM pending_invoices WHERE key = $1", key)
             .execute(&mut **tx)
             .await?;
+        // a concurrent transaction that deleted the invoice first wins
+        if result.rows_affected() == 0 {
+            return Err(sqlx::Error::RowNotFound.into());
+        }
         Ok(())
     }
 
@@ -146,14 +448,20 @@ impl Database for PostgresDB {
         id: &Uuid,
     ) -> Result<Bolt11MintQuote, MokshaMintError> {
         let quote: Bolt11MintQuote = sqlx::query!(
-            "SELECT id, payment_request, expiry, paid FROM bolt11_mint_quotes WHERE id = $1",
+            "SELECT id, unit, payment_request, expiry, paid FROM bolt11_mint_quotes WHERE id = $1",
             id
         )
-        .map(|row| Bolt11MintQuote {
-            quote_id: row.id,
-            payment_request: row.payment_request,
-            expiry: row.expiry as u64,
-            paid: row.paid,
+        .try_map(|row| {
+            Ok(Bolt11MintQuote {
+                quote_id: row.id,
+                unit: row
+                    .unit
+                    .parse()
+                    .map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+                payment_request: row.payment_request,
+                expiry: row.expiry as u64,
+                paid: row.paid,
+            })
         })
         .fetch_one(&mut **tx)
         .await?;
@@ -167,16 +475,21 @@ impl Database for PostgresDB {
         id: &Uuid,
     ) -> Result<BitcreditMintQuote, MokshaMintError> {
         let quote: BitcreditMintQuote = sqlx::query!(
-            "SELECT id, bill_id, node_id, sent, amount, endorsed FROM bitcredit_mint_quotes WHERE id = $1",
+            "SELECT id, bill_id, node_id, amount, state, expiry FROM bitcredit_mint_quotes WHERE id = $1",
             id
         )
-        .map(|row| BitcreditMintQuote {
-            quote_id: row.id,
-            bill_id: row.bill_id,
-            node_id: row.node_id,
-            sent: row.sent,
-            amount: row.amount as u64,
-            endorsed: row.endorsed,
+        .try_map(|row| {
+            Ok(BitcreditMintQuote {
+                quote_id: row.id,
+                bill_id: row.bill_id,
+                node_id: row.node_id,
+                amount: row.amount as u64,
+                state: row
+                    .state
+                    .parse()
+                    .map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+                expiry: row.expiry as u64,
+            })
         })
         .fetch_one(&mut **tx)
         .await?;
@@ -204,6 +517,27 @@ impl Database for PostgresDB {
         Ok(request_to_mint)
     }
 
+    #[instrument(level = "debug", skip(self), err)]
+    async fn get_bitcredit_request_to_mint_for_update(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        bill_id: &str,
+    ) -> Result<BitcreditRequestToMint, MokshaMintError> {
+        let request_to_mint: BitcreditRequestToMint = sqlx::query!(
+            "SELECT bill_id, bill_key, maturity_date, bill_amount FROM bitcredit_requests_to_mint WHERE bill_id = $1 FOR UPDATE",
+            bill_id
+        )
+        .map(|row| BitcreditRequestToMint {
+            bill_id: row.bill_id,
+            bill_key: row.bill_key,
+            maturity_date: row.maturity_date.unwrap(),
+            bill_amount: row.bill_amount.unwrap() as u64,
+        })
+        .fetch_one(&mut **tx)
+        .await?;
+        Ok(request_to_mint)
+    }
+
     #[instrument(level = "debug", skip(self), err)]
     async fn check_bitcredit_quote(
         &self,
@@ -211,17 +545,22 @@ impl Database for PostgresDB {
         quote_check: &BitcreditQuoteCheck,
     ) -> Result<BitcreditMintQuote, MokshaMintError> {
         let quote: BitcreditMintQuote = sqlx::query!(
-            "SELECT id, bill_id, node_id, sent, amount, endorsed FROM bitcredit_mint_quotes WHERE bill_id = $1 AND node_id = $2",
+            "SELECT id, bill_id, node_id, amount, state, expiry FROM bitcredit_mint_quotes WHERE bill_id = $1 AND node_id = $2 ORDER BY expiry DESC LIMIT 1",
             quote_check.bill_id,
             quote_check.node_id,
         )
-            .map(|row| BitcreditMintQuote {
-                quote_id: row.id,
-                bill_id: row.bill_id,
-                node_id: row.node_id,
-                sent: row.sent,
-                amount: row.amount as u64,
-                endorsed: row.endorsed,
+            .try_map(|row| {
+                Ok(BitcreditMintQuote {
+                    quote_id: row.id,
+                    bill_id: row.bill_id,
+                    node_id: row.node_id,
+                    amount: row.amount as u64,
+                    state: row
+                        .state
+                        .parse()
+                        .map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+                    expiry: row.expiry as u64,
+                })
             })
             .fetch_one(&mut **tx)
             .await?;
@@ -235,8 +574,9 @@ impl Database for PostgresDB {
         quote: &Bolt11MintQuote,
     ) -> Result<(), MokshaMintError> {
         sqlx::query!(
-            "INSERT INTO bolt11_mint_quotes (id, payment_request, expiry, paid) VALUES ($1, $2, $3, $4)",
+            "INSERT INTO bolt11_mint_quotes (id, unit, payment_request, expiry, paid) VALUES ($1, $2, $3, $4, $5)",
             quote.quote_id,
+            quote.unit.to_string(),
             quote.payment_request,
             quote.expiry as i64,
             quote.paid
@@ -269,9 +609,9 @@ impl Database for PostgresDB {
         quote: &BitcreditMintQuote,
     ) -> Result<(), MokshaMintError> {
         sqlx::query!(
-            "UPDATE bitcredit_mint_quotes SET sent = $1, endorsed = $2 WHERE id = $3",
-            quote.sent,
-            quote.endorsed,
+            "UPDATE bitcredit_mint_quotes SET amount = $1, state = $2 WHERE id = $3",
+            quote.amount as i64,
+            quote.state.to_string(),
             quote.quote_id
         )
         .execute(&mut **tx)
@@ -279,6 +619,50 @@ impl Database for PostgresDB {
         Ok(())
     }
 
+    #[instrument(level = "debug", skip(self), err)]
+    async fn add_bitcredit_mint_quote_transition(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        quote_id: &Uuid,
+        transition: &BitcreditMintQuoteTransition,
+    ) -> Result<(), MokshaMintError> {
+        sqlx::query!(
+            "INSERT INTO bitcredit_mint_quote_transitions (quote_id, state, reason, created_at) VALUES ($1, $2, $3, $4)",
+            quote_id,
+            transition.state.to_string(),
+            transition.reason,
+            transition.timestamp as i64,
+        )
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn get_bitcredit_mint_quote_transitions(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        quote_id: &Uuid,
+    ) -> Result<Vec<BitcreditMintQuoteTransition>, MokshaMintError> {
+        let transitions = sqlx::query!(
+            "SELECT state, reason, created_at FROM bitcredit_mint_quote_transitions WHERE quote_id = $1 ORDER BY id",
+            quote_id
+        )
+        .try_map(|row| {
+            Ok(BitcreditMintQuoteTransition {
+                state: row
+                    .state
+                    .parse()
+                    .map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+                timestamp: row.created_at as u64,
+                reason: row.reason,
+            })
+        })
+        .fetch_all(&mut **tx)
+        .await?;
+        Ok(transitions)
+    }
+
     #[instrument(level = "debug", skip(self), err)]
     async fn delete_bolt11_mint_quote(
         &self,
@@ -301,13 +685,13 @@ impl Database for PostgresDB {
         quote: &BitcreditMintQuote,
     ) -> Result<(), MokshaMintError> {
         sqlx::query!(
-            "INSERT INTO bitcredit_mint_quotes (id, bill_id, node_id, sent, amount, endorsed) VALUES ($1, $2, $3, $4, $5, $6)",
+            "INSERT INTO bitcredit_mint_quotes (id, bill_id, node_id, amount, state, expiry) VALUES ($1, $2, $3, $4, $5, $6)",
             quote.quote_id,
             quote.bill_id,
             quote.node_id,
-            quote.sent,
             quote.amount as i64,
-            quote.endorsed,
+            quote.state.to_string(),
+            quote.expiry as i64,
         )
         .execute(&mut **tx)
         .await?;
@@ -333,16 +717,401 @@ impl Database for PostgresDB {
     }
 
     #[instrument(level = "debug", skip(self), err)]
-    async fn add_mint_keyset(
+    async fn get_bitcredit_requests_to_mint(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Vec<BitcreditRequestToMint>, MokshaMintError> {
+        let requests = sqlx::query!(
+            "SELECT bill_id, bill_key, maturity_date, bill_amount FROM bitcredit_requests_to_mint"
+        )
+        .map(|row| BitcreditRequestToMint {
+            bill_id: row.bill_id,
+            bill_key: row.bill_key,
+            maturity_date: row.maturity_date.unwrap_or_default(),
+            bill_amount: row.bill_amount.unwrap_or_default() as u64,
+        })
+        .fetch_all(&mut **tx)
+        .await?;
+        Ok(requests)
+    }
+
+    #[instrument(level = "debug", skip(self, bill_key), err)]
+    async fn update_bitcredit_request_to_mint_key(
         &self,
         tx: &mut sqlx::Transaction<Self::DB>,
-        keyset_id: &String,
-        keyset_public_key: &String,
+        bill_id: &str,
+        bill_key: &str,
     ) -> Result<(), MokshaMintError> {
         sqlx::query!(
-            "INSERT INTO mint_keysets (keyset_id, keyset_public_key) VALUES ($1, $2)",
-            keyset_id,
-            keyset_public_key,
+            "UPDATE bitcredit_requests_to_mint SET bill_key = $1 WHERE bill_id = $2",
+            bill_key,
+            bill_id
+        )
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn get_bill_keysets(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Vec<BillKeysetInfo>, MokshaMintError> {
+        let keysets = sqlx::query!(
+            "SELECT id, bill_id, public_keys, maturity_date, face_value, amount_minted, status, created_at FROM bill_keysets ORDER BY maturity_date, id"
+        )
+        .try_map(|row| {
+            Ok(BillKeysetInfo {
+                id: row.id,
+                bill_id: row.bill_id,
+                public_keys: serde_json::from_str(&row.public_keys)
+                    .map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+                maturity_date: row.maturity_date,
+                face_value: row.face_value as u64,
+                amount_minted: row.amount_minted as u64,
+                status: row
+                    .status
+                    .parse()
+                    .map_err(|e: String| sqlx::Error::Decode(e.into()))?,
+                created_at: row.created_at,
+            })
+        })
+        .fetch_all(&mut **tx)
+        .await?;
+        Ok(keysets)
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn add_bill_keyset(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        keyset: &BillKeysetInfo,
+    ) -> Result<(), MokshaMintError> {
+        sqlx::query!(
+            "INSERT INTO bill_keysets (id, bill_id, public_keys, maturity_date, face_value, amount_minted, status, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            keyset.id,
+            keyset.bill_id,
+            serde_json::to_string(&keyset.public_keys)?,
+            keyset.maturity_date,
+            keyset.face_value as i64,
+            keyset.amount_minted as i64,
+            keyset.status.to_string(),
+            keyset.created_at,
+        )
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn get_bill_keyset_status(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        id: &str,
+    ) -> Result<BillKeysetStatus, MokshaMintError> {
+        let status = sqlx::query!("SELECT status FROM bill_keysets WHERE id = $1", id)
+            .try_map(|row| {
+                row.status
+                    .parse()
+                    .map_err(|e: String| sqlx::Error::Decode(e.into()))
+            })
+            .fetch_one(&mut **tx)
+            .await?;
+        Ok(status)
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn update_bill_keyset_status(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        id: &str,
+        status: BillKeysetStatus,
+    ) -> Result<(), MokshaMintError> {
+        sqlx::query!(
+            "UPDATE bill_keysets SET status = $2 WHERE id = $1",
+            id,
+            status.to_string(),
+        )
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn add_bill_amount_minted(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        id: &str,
+        amount: u64,
+    ) -> Result<(), MokshaMintError> {
+        sqlx::query!(
+            "UPDATE bill_keysets SET amount_minted = amount_minted + $2 WHERE id = $1",
+            id,
+            amount as i64,
+        )
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn add_treasury_entry(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        entry: &TreasuryEntry,
+    ) -> Result<(), MokshaMintError> {
+        sqlx::query!(
+            "INSERT INTO treasury_entries (bill_id, kind, amount, reference, created_at) VALUES ($1, $2, $3, $4, $5)",
+            entry.bill_id,
+            entry.kind.to_string(),
+            entry.amount as i64,
+            entry.reference,
+            entry.timestamp as i64,
+        )
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn get_treasury_entries(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Vec<TreasuryEntry>, MokshaMintError> {
+        let entries = sqlx::query!(
+            "SELECT bill_id, kind, amount, reference, created_at FROM treasury_entries ORDER BY id"
+        )
+        .try_map(|row| {
+            Ok(TreasuryEntry {
+                bill_id: row.bill_id,
+                kind: row
+                    .kind
+                    .parse()
+                    .map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+                amount: row.amount as u64,
+                reference: row.reference,
+                timestamp: row.created_at as u64,
+            })
+        })
+        .fetch_all(&mut **tx)
+        .await?;
+        Ok(entries)
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn add_treasury_proofs(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        bill_id: &str,
+        proofs: &Proofs,
+    ) -> Result<(), MokshaMintError> {
+        let proofs = proofs.proofs();
+        let secrets = proofs.iter().map(|p| p.secret.clone()).collect::<Vec<_>>();
+        let amounts = proofs.iter().map(|p| p.amount as i64).collect::<Vec<_>>();
+        let cs = proofs.iter().map(|p| p.c.to_string()).collect::<Vec<_>>();
+        let keyset_ids = proofs
+            .iter()
+            .map(|p| p.keyset_id.clone())
+            .collect::<Vec<_>>();
+        sqlx::query!(
+            "INSERT INTO treasury_proofs (secret, bill_id, amount, c, keyset_id) SELECT secret, $1, amount, c, keyset_id FROM UNNEST($2::TEXT[], $3::BIGINT[], $4::TEXT[], $5::TEXT[]) AS t(secret, amount, c, keyset_id)",
+            bill_id,
+            &secrets,
+            &amounts,
+            &cs,
+            &keyset_ids
+        )
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn get_treasury_proofs(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        bill_id: &str,
+    ) -> Result<Proofs, MokshaMintError> {
+        let proofs = sqlx::query!(
+            "SELECT amount, secret, c, keyset_id FROM treasury_proofs WHERE bill_id = $1 ORDER BY amount DESC, secret",
+            bill_id
+        )
+        .fetch_all(&mut **tx)
+        .await?
+        .into_iter()
+        .map(|row| Proof {
+            amount: row.amount as u64,
+            secret: row.secret,
+            c: dhke::public_key_from_hex(&row.c).to_owned(),
+            keyset_id: row.keyset_id,
+            witness: None,
+            dleq: None,
+        })
+        .collect::<Vec<Proof>>();
+        Ok(proofs.into())
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn delete_treasury_proofs(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        proofs: &Proofs,
+    ) -> Result<(), MokshaMintError> {
+        let secrets = proofs
+            .proofs()
+            .iter()
+            .map(|p| p.secret.clone())
+            .collect::<Vec<_>>();
+        sqlx::query!(
+            "DELETE FROM treasury_proofs WHERE secret = ANY($1)",
+            &secrets
+        )
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn add_treasury_melt(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        melt: &TreasuryMelt,
+    ) -> Result<(), MokshaMintError> {
+        sqlx::query!(
+            "INSERT INTO treasury_melts (payment_hash, bill_id, payment_request, amount, fee_reserve, created_at) VALUES ($1, $2, $3, $4, $5, $6)",
+            melt.payment_hash,
+            melt.bill_id,
+            melt.payment_request,
+            melt.amount as i64,
+            melt.fee_reserve as i64,
+            melt.created_at as i64,
+        )
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn get_treasury_melts(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Vec<TreasuryMelt>, MokshaMintError> {
+        let melts = sqlx::query!(
+            "SELECT payment_hash, bill_id, payment_request, amount, fee_reserve, created_at FROM treasury_melts ORDER BY created_at, payment_hash"
+        )
+        .map(|row| TreasuryMelt {
+            payment_hash: row.payment_hash,
+            bill_id: row.bill_id,
+            payment_request: row.payment_request,
+            amount: row.amount as u64,
+            fee_reserve: row.fee_reserve as u64,
+            created_at: row.created_at as u64,
+        })
+        .fetch_all(&mut **tx)
+        .await?;
+        Ok(melts)
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn delete_treasury_melt(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        payment_hash: &str,
+    ) -> Result<(), MokshaMintError> {
+        sqlx::query!(
+            "DELETE FROM treasury_melts WHERE payment_hash = $1",
+            payment_hash
+        )
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn get_keysets(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Vec<MintKeysetInfo>, MokshaMintError> {
+        let keysets = sqlx::query!(
+            "SELECT id, unit, derivation_path, active, input_fee_ppk, created_at FROM keysets ORDER BY created_at, id"
+        )
+        .try_map(|row| {
+            Ok(MintKeysetInfo {
+                id: row.id,
+                unit: row
+                    .unit
+                    .parse()
+                    .map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+                derivation_path: row.derivation_path,
+                active: row.active,
+                input_fee_ppk: row.input_fee_ppk as u64,
+                created_at: row.created_at,
+            })
+        })
+        .fetch_all(&mut **tx)
+        .await?;
+        Ok(keysets)
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn get_keyset(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        id: &str,
+    ) -> Result<MintKeysetInfo, MokshaMintError> {
+        let keyset = sqlx::query!(
+            "SELECT id, unit, derivation_path, active, input_fee_ppk, created_at FROM keysets WHERE id = $1",
+            id
+        )
+        .try_map(|row| {
+            Ok(MintKeysetInfo {
+                id: row.id,
+                unit: row
+                    .unit
+                    .parse()
+                    .map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+                derivation_path: row.derivation_path,
+                active: row.active,
+                input_fee_ppk: row.input_fee_ppk as u64,
+                created_at: row.created_at,
+            })
+        })
+        .fetch_one(&mut **tx)
+        .await?;
+        Ok(keyset)
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn add_keyset(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        keyset: &MintKeysetInfo,
+    ) -> Result<(), MokshaMintError> {
+        sqlx::query!(
+            "INSERT INTO keysets (id, unit, derivation_path, active, input_fee_ppk, created_at) VALUES ($1, $2, $3, $4, $5, $6)",
+            keyset.id,
+            keyset.unit.to_string(),
+            keyset.derivation_path,
+            keyset.active,
+            keyset.input_fee_ppk as i64,
+            keyset.created_at,
+        )
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn activate_keyset(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        id: &str,
+        unit: &CurrencyUnit,
+    ) -> Result<(), MokshaMintError> {
+        sqlx::query!(
+            "UPDATE keysets SET active = (id = $1) WHERE unit = $2",
+            id,
+            unit.to_string(),
         )
         .execute(&mut **tx)
         .await?;
@@ -356,16 +1125,52 @@ impl Database for PostgresDB {
         key: &Uuid,
     ) -> Result<Bolt11MeltQuote, MokshaMintError> {
         let quote: Bolt11MeltQuote = sqlx::query!(
-            "SELECT id, payment_request, expiry, paid, amount, fee_reserve FROM bolt11_melt_quotes WHERE id = $1",
+            "SELECT id, unit, payment_request, expiry, paid, amount, fee_reserve FROM bolt11_melt_quotes WHERE id = $1",
             key
         )
-        .map(|row| Bolt11MeltQuote {
-            quote_id: row.id,
-            payment_request: row.payment_request,
-            expiry: row.expiry as u64,
-            paid: row.paid,
-            amount: row.amount as u64,
-            fee_reserve: row.fee_reserve as u64,
+        .try_map(|row| {
+            Ok(Bolt11MeltQuote {
+                quote_id: row.id,
+                unit: row
+                    .unit
+                    .parse()
+                    .map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+                payment_request: row.payment_request,
+                expiry: row.expiry as u64,
+                paid: row.paid,
+                amount: row.amount as u64,
+                fee_reserve: row.fee_reserve as u64,
+            })
+        })
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(quote)
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn get_bolt11_melt_quote_for_update(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        key: &Uuid,
+    ) -> Result<Bolt11MeltQuote, MokshaMintError> {
+        let quote: Bolt11MeltQuote = sqlx::query!(
+            "SELECT id, unit, payment_request, expiry, paid, amount, fee_reserve FROM bolt11_melt_quotes WHERE id = $1 FOR UPDATE",
+            key
+        )
+        .try_map(|row| {
+            Ok(Bolt11MeltQuote {
+                quote_id: row.id,
+                unit: row
+                    .unit
+                    .parse()
+                    .map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+                payment_request: row.payment_request,
+                expiry: row.expiry as u64,
+                paid: row.paid,
+                amount: row.amount as u64,
+                fee_reserve: row.fee_reserve as u64,
+            })
         })
         .fetch_one(&mut **tx)
         .await?;
@@ -380,8 +1185,9 @@ impl Database for PostgresDB {
         quote: &Bolt11MeltQuote,
     ) -> Result<(), MokshaMintError> {
         sqlx::query!(
-            "INSERT INTO bolt11_melt_quotes (id, payment_request, expiry, paid, amount, fee_reserve) VALUES ($1, $2, $3, $4, $5, $6)",
+            "INSERT INTO bolt11_melt_quotes (id, unit, payment_request, expiry, paid, amount, fee_reserve) VALUES ($1, $2, $3, $4, $5, $6, $7)",
             quote.quote_id,
+            quote.unit.to_string(),
             quote.payment_request,
             quote.expiry as i64,
             quote.paid,
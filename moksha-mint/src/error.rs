@@ -8,12 +8,15 @@ use axum::{
 use fedimint_tonic_lnd::{tonic::Status, ConnectError};
 
 use lightning_invoice::ParseOrSemanticError;
-use moksha_core::primitives::CurrencyUnit;
-use serde_json::json;
+use moksha_core::{
+    error::MokshaCoreError,
+    primitives::{BitcreditMintQuoteState, CashuErrorResponse, ErrorCode},
+};
 use thiserror::Error;
 use tracing::{event, Level};
+use uuid::Uuid;
 
-use crate::lightning::error::LightningError;
+use crate::{billregistry::error::BillRegistryError, lightning::error::LightningError};
 
 #[derive(Error, Debug)]
 pub enum MokshaMintError {
@@ -38,15 +41,27 @@ pub enum MokshaMintError {
     #[error("Serde Error {0}")]
     Serialization(#[from] serde_json::Error),
 
-    #[error("Invoice amount is too low {0}")]
-    InvoiceAmountTooLow(String),
-
     #[error("Invoice not found for hash {0}")]
     InvoiceNotFound(String),
 
     #[error("Lightning invoice not paid yet.")]
     InvoiceNotPaidYet,
 
+    #[error("Quote {0} has expired")]
+    QuoteExpired(Uuid),
+
+    #[error("Quote {0} has already been paid")]
+    QuoteAlreadyPaid(Uuid),
+
+    #[error("Quote {0} is being paid")]
+    QuotePending(Uuid),
+
+    #[error("Tokens for quote {0} have already been issued")]
+    QuoteAlreadyIssued(Uuid),
+
+    #[error("Outputs amount {0} exceeds the quote amount {1}")]
+    QuoteAmountExceeded(u64, u64),
+
     #[error("Bitcredit quote already sent.")]
     BitcreditQuoteAlreadySent,
 
@@ -56,24 +71,105 @@ pub enum MokshaMintError {
     #[error("Mint is not a current holder.")]
     BitcreditQuoteMintNotHolder,
 
+    #[error("Bitcredit quote {0} has been rejected")]
+    BitcreditQuoteRejected(Uuid),
+
+    #[error("Bitcredit quote {0} has not been accepted")]
+    BitcreditQuoteNotAccepted(Uuid),
+
+    #[error("Requested amount {0} exceeds the offered amount {1}")]
+    BitcreditAmountAboveOffer(u64, u64),
+
+    #[error("Request to mint bill {0} is not signed by its holder")]
+    BitcreditRequestNotSignedByHolder(String),
+
+    #[error("Request to mint does not match bill {0}")]
+    BitcreditRequestBillMismatch(String),
+
+    #[error("Unknown risk tier {0}")]
+    UnknownRiskTier(String),
+
+    #[error("Invalid key-encryption config: {0}")]
+    InvalidKeyEncryptionConfig(String),
+
+    #[error("Key-encryption key {0} is not configured")]
+    UnknownKeyEncryptionKey(String),
+
+    #[error("Bill key of {0} can not be encrypted or decrypted")]
+    BillKeyEncryption(String),
+
+    #[error("Bill {0} is not mature yet")]
+    BillNotMature(String),
+
+    #[error("Bill {0} has not been paid")]
+    BillNotPaid(String),
+
+    #[error("Bill {0} has defaulted")]
+    BillDefaulted(String),
+
+    #[error("Amount {1} exceeds the treasury balance {2} of bill {0}")]
+    TreasuryBalanceExceeded(String, u64, u64),
+
+    #[error("Unauthorized")]
+    Unauthorized,
+
+    #[error("Bitcredit quote {0} can not move from {1} to {2}")]
+    InvalidQuoteTransition(Uuid, BitcreditMintQuoteState, BitcreditMintQuoteState),
+
     #[error("BTC-Onchain not paid yet.")]
     BtcOnchainNotPaidYet,
 
     #[error("Proof already used {0}")]
     ProofAlreadyUsed(String),
 
+    #[error("Proof is pending {0}")]
+    ProofPending(String),
+
+    #[error("Invalid proof {0}")]
+    InvalidProof(String),
+
+    #[error("Invalid secret {0}")]
+    InvalidSecret(String),
+
+    #[error("Witness missing for {0}")]
+    WitnessMissing(String),
+
+    #[error("Invalid witness signature for {0}")]
+    InvalidWitnessSignature(String),
+
+    #[error("Not enough valid signatures. Required {0}, found {1}")]
+    NotEnoughSignatures(u64, u64),
+
+    #[error("All inputs must have the same spending conditions if SIG_ALL is used")]
+    SigAllMismatch,
+
+    #[error("Invalid or missing HTLC preimage for {0}")]
+    InvalidPreimage(String),
+
     #[error("{0}")]
     SwapAmountMismatch(String),
 
     #[error("duplicate promises.")]
     SwapHasDuplicatePromises,
 
+    #[error("Output already signed {0}")]
+    OutputAlreadySigned(String),
+
     #[error("Invalid amount: {0}")]
     InvalidAmount(String),
 
     #[error("Lightning Error {0}")]
     Lightning(#[from] LightningError),
 
+    #[error("Bill registry error: {0}")]
+    BillRegistry(#[from] BillRegistryError),
+
+    #[error("Time service unavailable: {0}")]
+    ClockUnavailable(String),
+
+    #[error("Clock skew of {0} seconds to the time service")]
+    ClockSkew(u64),
+
     #[error("Invalid quote {0}")]
     InvalidQuote(String),
 
@@ -83,8 +179,14 @@ pub enum MokshaMintError {
     #[error("Keyset not found {0}")]
     KeysetNotFound(String),
 
+    #[error("Keyset {0} is inactive")]
+    InactiveKeyset(String),
+
     #[error("Currency not supported {0}")]
-    CurrencyNotSupported(CurrencyUnit),
+    CurrencyNotSupported(String),
+
+    #[error("Inputs and outputs must have the unit {0}")]
+    UnitMismatch(String),
 
     #[error("Not Enough tokens. Required amount {0}")]
     NotEnoughTokens(u64),
@@ -96,18 +198,157 @@ pub enum MokshaMintError {
     PrivateKeyNotFound,
 
     #[error("MokshaCoreError: {0}")]
-    MokshaCore(#[from] moksha_core::error::MokshaCoreError),
+    MokshaCore(moksha_core::error::MokshaCoreError),
+}
+
+impl From<MokshaCoreError> for MokshaMintError {
+    fn from(err: MokshaCoreError) -> Self {
+        match err {
+            MokshaCoreError::UnknownUnit(unit) => Self::CurrencyNotSupported(unit),
+            err => Self::MokshaCore(err),
+        }
+    }
+}
+
+impl MokshaMintError {
+    /// Returns the Nut error code that is sent to the wallet
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::ConnectError(_)
+            | Self::ClnConnectError(_)
+            | Self::PayInvoice(_, _)
+            | Self::InvoiceNotFound(_)
+            | Self::Lightning(_)
+            | Self::Lnd(_) => ErrorCode::LightningError,
+            Self::DecodeInvoice(_, _)
+            | Self::Unauthorized
+            | Self::InvalidQuote(_)
+            | Self::InvalidUuid(_)
+            | Self::MokshaCore(_) => ErrorCode::BadRequest,
+            Self::Db(sqlx::Error::RowNotFound) => ErrorCode::BadRequest,
+            Self::Db(_) | Self::Utf8(_) | Self::Serialization(_) | Self::PrivateKeyNotFound => {
+                ErrorCode::Other(0)
+            }
+            Self::BillRegistry(_)
+            | Self::ClockUnavailable(_)
+            | Self::ClockSkew(_)
+            | Self::UnknownRiskTier(_)
+            | Self::InvalidKeyEncryptionConfig(_)
+            | Self::UnknownKeyEncryptionKey(_)
+            | Self::BillKeyEncryption(_) => ErrorCode::Other(0),
+            Self::InvoiceNotPaidYet
+            | Self::BitcreditQuoteMintNotHolder
+            | Self::BitcreditQuoteNotAccepted(_)
+            | Self::BtcOnchainNotPaidYet => ErrorCode::QuoteNotPaid,
+            Self::QuoteExpired(_) => ErrorCode::QuoteExpired,
+            Self::QuoteAlreadyPaid(_) => ErrorCode::InvoiceAlreadyPaid,
+            // the proofs are pending because they are used for a quote that is being paid
+            Self::QuotePending(_) | Self::ProofPending(_) => ErrorCode::QuotePending,
+            Self::QuoteAlreadyIssued(_) | Self::BitcreditQuoteAlreadySent => {
+                ErrorCode::TokensAlreadyIssued
+            }
+            Self::QuoteAmountExceeded(_, _)
+            | Self::SwapAmountMismatch(_)
+            | Self::NotEnoughTokens(_)
+            | Self::TreasuryBalanceExceeded(_, _, _) => ErrorCode::TransactionUnbalanced,
+            Self::BitcreditQuoteIncorrectAmount
+            | Self::BitcreditQuoteRejected(_)
+            | Self::BitcreditAmountAboveOffer(_, _)
+            | Self::BitcreditRequestNotSignedByHolder(_)
+            | Self::BitcreditRequestBillMismatch(_)
+            | Self::BillNotMature(_)
+            | Self::BillNotPaid(_)
+            | Self::BillDefaulted(_)
+            | Self::InvalidQuoteTransition(_, _, _) => ErrorCode::TransactionError,
+            Self::ProofAlreadyUsed(_) => ErrorCode::TokenAlreadySpent,
+            Self::InvalidProof(_)
+            | Self::InvalidSecret(_)
+            | Self::WitnessMissing(_)
+            | Self::InvalidWitnessSignature(_)
+            | Self::NotEnoughSignatures(_, _)
+            | Self::SigAllMismatch
+            | Self::InvalidPreimage(_) => ErrorCode::TokenNotVerified,
+            Self::SwapHasDuplicatePromises => ErrorCode::DuplicateOutputs,
+            Self::OutputAlreadySigned(_) => ErrorCode::OutputAlreadySigned,
+            Self::InvalidAmount(_) => ErrorCode::AmountOutOfRange,
+            Self::KeysetNotFound(_) => ErrorCode::KeysetNotFound,
+            Self::InactiveKeyset(_) => ErrorCode::KeysetInactive,
+            Self::CurrencyNotSupported(_) | Self::UnitMismatch(_) => ErrorCode::UnitNotSupported,
+        }
+    }
+
+    /// Returns the HTTP status of the error response. Errors of the request are `400` (Nut-00),
+    /// failures of the mint itself `500` and failures of the payment backends `502`.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::ConnectError(_)
+            | Self::ClnConnectError(_)
+            | Self::PayInvoice(_, _)
+            | Self::Lightning(_)
+            | Self::Lnd(_)
+            | Self::BillRegistry(_)
+            | Self::ClockUnavailable(_) => StatusCode::BAD_GATEWAY,
+            Self::Db(sqlx::Error::RowNotFound) => StatusCode::BAD_REQUEST,
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::Db(_)
+            | Self::Utf8(_)
+            | Self::Serialization(_)
+            | Self::PrivateKeyNotFound
+            | Self::ClockSkew(_)
+            | Self::UnknownRiskTier(_)
+            | Self::InvalidKeyEncryptionConfig(_)
+            | Self::UnknownKeyEncryptionKey(_)
+            | Self::BillKeyEncryption(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
 }
 
 impl IntoResponse for MokshaMintError {
     fn into_response(self) -> Response {
         event!(Level::ERROR, "error in mint: {:?}", self);
 
-        let body = Json(json!({
-            "code": 0,
-            "detail": self.to_string(),
-        }));
+        let body = Json(CashuErrorResponse {
+            code: self.code(),
+            detail: self.to_string(),
+        });
+
+        (self.status(), body).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{http::StatusCode, response::IntoResponse};
+    use http_body_util::BodyExt;
+    use moksha_core::primitives::{CashuErrorResponse, ErrorCode};
+
+    use super::MokshaMintError;
+
+    #[tokio::test]
+    async fn test_error_response() -> anyhow::Result<()> {
+        let response = MokshaMintError::ProofAlreadyUsed("proof".to_owned()).into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = response.into_body().collect().await?.to_bytes();
+        let error = serde_json::from_slice::<CashuErrorResponse>(&body)?;
+        assert_eq!(error.code, ErrorCode::TokenAlreadySpent);
+        assert_eq!(error.detail, "Proof already used proof");
+        Ok(())
+    }
+
+    #[test]
+    fn test_error_status() {
+        let err = MokshaMintError::Lightning(crate::lightning::error::LightningError::NotFound);
+        assert_eq!(err.code(), ErrorCode::LightningError);
+        assert_eq!(err.status(), StatusCode::BAD_GATEWAY);
+
+        let err = MokshaMintError::Db(sqlx::Error::PoolClosed);
+        assert_eq!(err.code(), ErrorCode::Other(0));
+        assert_eq!(err.status(), StatusCode::INTERNAL_SERVER_ERROR);
 
-        (StatusCode::BAD_REQUEST, body).into_response()
+        let err = MokshaMintError::QuoteExpired(uuid::Uuid::new_v4());
+        assert_eq!(err.code(), ErrorCode::QuoteExpired);
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
     }
 }
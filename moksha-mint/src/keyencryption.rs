@@ -0,0 +1,307 @@
+//! Envelope encryption of the private keys of bills.
+//!
+//! The private key of a bill is the seed of its `cr-sat` keyset, so it is never stored in
+//! plaintext when a key-encryption key (KEK) is configured. Every bill key is encrypted with its
+//! own random data key and the data key is encrypted with the KEK. Both are bound to the bill id,
+//! so a stored key can not be moved to another bill. The stored value has the format
+//! `enc:v1:<kek id>:<hex nonce and encrypted data key>:<hex nonce and encrypted bill key>`.
+//!
+//! Rotating the KEK only re-encrypts the data keys: the new KEK is configured first and the old
+//! ones are kept until [`crate::mint::Mint::init_keysets`] has re-encrypted all bill keys.
+
+use std::collections::HashSet;
+
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    XChaCha20Poly1305, XNonce,
+};
+use secp256k1::rand::{thread_rng, Rng};
+
+use crate::{
+    config::{BillKeyEncryptionConfig, KeyEncryptionKey},
+    error::MokshaMintError,
+};
+
+const PREFIX: &str = "enc:v1:";
+const NONCE_LEN: usize = 24;
+
+#[derive(Debug, Clone, Default)]
+pub struct BillKeyEncryption {
+    /// the first key encrypts, all keys decrypt
+    keys: Vec<KeyEncryptionKey>,
+}
+
+impl BillKeyEncryption {
+    /// Reads the keys from the config and from the key file
+    pub fn new(config: &BillKeyEncryptionConfig) -> Result<Self, MokshaMintError> {
+        let mut keys = config.keys.clone();
+        if let Some(path) = &config.key_file {
+            let content = std::fs::read_to_string(path).map_err(|err| {
+                MokshaMintError::InvalidKeyEncryptionConfig(format!(
+                    "can not read {}: {err}",
+                    path.display()
+                ))
+            })?;
+            for line in content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            {
+                keys.push(
+                    line.parse()
+                        .map_err(MokshaMintError::InvalidKeyEncryptionConfig)?,
+                );
+            }
+        }
+
+        let mut ids = HashSet::new();
+        if let Some(key) = keys.iter().find(|key| !ids.insert(key.id.as_str())) {
+            return Err(MokshaMintError::InvalidKeyEncryptionConfig(format!(
+                "duplicate key-encryption key {}",
+                key.id
+            )));
+        }
+        Ok(Self { keys })
+    }
+
+    /// Returns false if no key is configured and bill keys are stored in plaintext
+    pub fn is_enabled(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// Encrypts a bill key with the active KEK, without a KEK the bill key is returned as is
+    pub fn encrypt(&self, bill_id: &str, bill_key: &str) -> Result<String, MokshaMintError> {
+        let Some(kek) = self.keys.first() else {
+            return Ok(bill_key.to_owned());
+        };
+        let data_key = thread_rng().gen::<[u8; 32]>();
+        let data = seal(&data_key, bill_key.as_bytes(), bill_id)?;
+        seal_envelope(kek, bill_id, &data_key, &data)
+    }
+
+    /// Decrypts a stored bill key, bill keys that were stored in plaintext are returned as is
+    pub fn decrypt(&self, bill_id: &str, stored: &str) -> Result<String, MokshaMintError> {
+        let Some(envelope) = Envelope::parse(bill_id, stored)? else {
+            return Ok(stored.to_owned());
+        };
+        let data_key = self.data_key(bill_id, &envelope)?;
+        let bill_key = open(&data_key, &envelope.data, bill_id)?;
+        String::from_utf8(bill_key)
+            .map_err(|_| MokshaMintError::BillKeyEncryption(bill_id.to_owned()))
+    }
+
+    /// Returns the stored bill key encrypted with the active KEK or `None` if it already is
+    pub fn rotate(&self, bill_id: &str, stored: &str) -> Result<Option<String>, MokshaMintError> {
+        let Some(kek) = self.keys.first() else {
+            return Ok(None);
+        };
+        match Envelope::parse(bill_id, stored)? {
+            None => self.encrypt(bill_id, stored).map(Some),
+            Some(envelope) if envelope.kek_id == kek.id => Ok(None),
+            Some(envelope) => {
+                let data_key = self.data_key(bill_id, &envelope)?;
+                seal_envelope(kek, bill_id, &data_key, &envelope.data).map(Some)
+            }
+        }
+    }
+
+    fn data_key(&self, bill_id: &str, envelope: &Envelope) -> Result<Vec<u8>, MokshaMintError> {
+        let kek = self
+            .keys
+            .iter()
+            .find(|key| key.id == envelope.kek_id)
+            .ok_or_else(|| MokshaMintError::UnknownKeyEncryptionKey(envelope.kek_id.clone()))?;
+        open(&kek.key, &envelope.data_key, bill_id)
+    }
+}
+
+struct Envelope {
+    kek_id: String,
+    /// nonce and encrypted data key
+    data_key: Vec<u8>,
+    /// nonce and encrypted bill key
+    data: Vec<u8>,
+}
+
+impl Envelope {
+    /// Returns `None` for bill keys that are stored in plaintext
+    fn parse(bill_id: &str, stored: &str) -> Result<Option<Self>, MokshaMintError> {
+        let Some(envelope) = stored.strip_prefix(PREFIX) else {
+            return Ok(None);
+        };
+        let invalid = || MokshaMintError::BillKeyEncryption(bill_id.to_owned());
+        let mut parts = envelope.split(':');
+        let (Some(kek_id), Some(data_key), Some(data), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        Ok(Some(Self {
+            kek_id: kek_id.to_owned(),
+            data_key: hex::decode(data_key).map_err(|_| invalid())?,
+            data: hex::decode(data).map_err(|_| invalid())?,
+        }))
+    }
+}
+
+/// Encrypts the data key with the KEK and formats the stored value
+fn seal_envelope(
+    kek: &KeyEncryptionKey,
+    bill_id: &str,
+    data_key: &[u8],
+    data: &[u8],
+) -> Result<String, MokshaMintError> {
+    let data_key = seal(&kek.key, data_key, bill_id)?;
+    Ok(format!(
+        "{PREFIX}{}:{}:{}",
+        kek.id,
+        hex::encode(data_key),
+        hex::encode(data)
+    ))
+}
+
+/// Encrypts with a random nonce that is prepended to the ciphertext
+fn seal(key: &[u8], plaintext: &[u8], bill_id: &str) -> Result<Vec<u8>, MokshaMintError> {
+    let cipher = XChaCha20Poly1305::new_from_slice(key)
+        .map_err(|_| MokshaMintError::BillKeyEncryption(bill_id.to_owned()))?;
+    let nonce = thread_rng().gen::<[u8; NONCE_LEN]>();
+    let ciphertext = cipher
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: bill_id.as_bytes(),
+            },
+        )
+        .map_err(|_| MokshaMintError::BillKeyEncryption(bill_id.to_owned()))?;
+    Ok([nonce.as_slice(), &ciphertext].concat())
+}
+
+fn open(key: &[u8], sealed: &[u8], bill_id: &str) -> Result<Vec<u8>, MokshaMintError> {
+    let invalid = || MokshaMintError::BillKeyEncryption(bill_id.to_owned());
+    if sealed.len() < NONCE_LEN {
+        return Err(invalid());
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    XChaCha20Poly1305::new_from_slice(key)
+        .map_err(|_| invalid())?
+        .decrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: bill_id.as_bytes(),
+            },
+        )
+        .map_err(|_| invalid())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use crate::{
+        config::{BillKeyEncryptionConfig, KeyEncryptionKey},
+        error::MokshaMintError,
+        keyencryption::BillKeyEncryption,
+    };
+
+    fn kek(id: &str, byte: u8) -> KeyEncryptionKey {
+        KeyEncryptionKey {
+            id: id.to_owned(),
+            key: [byte; 32],
+        }
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() -> anyhow::Result<()> {
+        let encryption = BillKeyEncryption::new(&BillKeyEncryptionConfig {
+            keys: vec![kek("k1", 1)],
+            key_file: None,
+        })?;
+        let stored = encryption.encrypt("bill", "BILL_KEY")?;
+        assert!(stored.starts_with("enc:v1:k1:"));
+        assert!(!stored.contains("BILL_KEY"));
+        assert_ne!(stored, encryption.encrypt("bill", "BILL_KEY")?);
+        assert_eq!("BILL_KEY", encryption.decrypt("bill", &stored)?);
+
+        // the encrypted key is bound to the bill
+        assert!(matches!(
+            encryption.decrypt("other bill", &stored),
+            Err(MokshaMintError::BillKeyEncryption(bill_id)) if bill_id == "other bill"
+        ));
+        // bill keys that were stored before encryption was enabled are still readable
+        assert_eq!("BILL_KEY", encryption.decrypt("bill", "BILL_KEY")?);
+
+        let disabled = BillKeyEncryption::default();
+        assert!(!disabled.is_enabled());
+        assert_eq!("BILL_KEY", disabled.encrypt("bill", "BILL_KEY")?);
+        assert!(matches!(
+            disabled.decrypt("bill", &stored),
+            Err(MokshaMintError::UnknownKeyEncryptionKey(id)) if id == "k1"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_rotate() -> anyhow::Result<()> {
+        let old = BillKeyEncryption::new(&BillKeyEncryptionConfig {
+            keys: vec![kek("k1", 1)],
+            key_file: None,
+        })?;
+        let stored = old.encrypt("bill", "BILL_KEY")?;
+        assert_eq!(None, old.rotate("bill", &stored)?);
+        assert!(old.rotate("bill", "BILL_KEY")?.is_some());
+
+        let rotated = BillKeyEncryption::new(&BillKeyEncryptionConfig {
+            keys: vec![kek("k2", 2), kek("k1", 1)],
+            key_file: None,
+        })?;
+        let Some(stored) = rotated.rotate("bill", &stored)? else {
+            anyhow::bail!("bill key was not rotated");
+        };
+        assert!(stored.starts_with("enc:v1:k2:"));
+
+        let new = BillKeyEncryption::new(&BillKeyEncryptionConfig {
+            keys: vec![kek("k2", 2)],
+            key_file: None,
+        })?;
+        assert_eq!("BILL_KEY", new.decrypt("bill", &stored)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_file() -> anyhow::Result<()> {
+        let mut file = tempfile::NamedTempFile::new()?;
+        writeln!(file, "# rotated on 2025-03-01")?;
+        writeln!(file, "k2:{}", hex::encode([2; 32]))?;
+        writeln!(file)?;
+        writeln!(file, "k1:{}", hex::encode([1; 32]))?;
+        let encryption = BillKeyEncryption::new(&BillKeyEncryptionConfig {
+            keys: vec![],
+            key_file: Some(file.path().to_owned()),
+        })?;
+        assert!(encryption
+            .encrypt("bill", "BILL_KEY")?
+            .starts_with("enc:v1:k2:"));
+
+        let result = BillKeyEncryption::new(&BillKeyEncryptionConfig {
+            keys: vec![kek("k1", 1)],
+            key_file: Some(file.path().to_owned()),
+        });
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::InvalidKeyEncryptionConfig(_))
+        ));
+
+        writeln!(file, "k3:0102")?;
+        let result = BillKeyEncryption::new(&BillKeyEncryptionConfig {
+            keys: vec![],
+            key_file: Some(file.path().to_owned()),
+        });
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::InvalidKeyEncryptionConfig(_))
+        ));
+        Ok(())
+    }
+}
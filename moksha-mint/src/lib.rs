@@ -1,11 +1,15 @@
+pub mod billregistry;
 pub mod btconchain;
+pub mod clock;
 pub mod config;
 pub mod database;
 pub mod error;
+pub mod keyencryption;
 pub mod lightning;
 pub mod mint;
 pub mod model;
+pub mod pricing;
 mod routes;
 pub mod server;
-mod time;
+mod spending_conditions;
 pub mod url_serialize;
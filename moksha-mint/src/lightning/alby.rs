@@ -11,7 +11,7 @@ use crate::{
     model::{CreateInvoiceParams, CreateInvoiceResult, PayInvoiceResult},
 };
 
-use super::{error::LightningError, Lightning};
+use super::{error::LightningError, Lightning, PaymentStatus};
 
 #[derive(Deserialize, Serialize, Debug, Clone, Default, Parser)]
 pub struct AlbyLightningSettings {
@@ -78,6 +78,17 @@ impl Lightning for AlbyLightning {
             .await
             .map_err(|err| MokshaMintError::PayInvoice(payment_request, err))
     }
+
+    async fn payment_status(
+        &self,
+        payment_request: String,
+    ) -> Result<PaymentStatus, MokshaMintError> {
+        let decoded_invoice = self.decode_invoice(payment_request).await?;
+        Ok(self
+            .client
+            .payment_status(&decoded_invoice.payment_hash().to_string())
+            .await?)
+    }
 }
 
 #[derive(Clone)]
@@ -200,4 +211,73 @@ impl AlbyClient {
             .as_bool()
             .unwrap_or(false))
     }
+
+    /// Looks the payment up in the outgoing invoices of the account. Alby only lists payments
+    /// that were made, so payments that are not listed or not settled are reported as unknown.
+    pub async fn payment_status(
+        &self,
+        payment_hash: &str,
+    ) -> Result<PaymentStatus, LightningError> {
+        for page in 1.. {
+            let body = self
+                .make_get(&format!(
+                    "invoices/outgoing?page={page}&items={OUTGOING_PAGE_SIZE}"
+                ))
+                .await?;
+            let invoices = serde_json::from_str::<Vec<serde_json::Value>>(&body)?;
+            if let Some(status) = outgoing_payment_status(&invoices, payment_hash) {
+                return Ok(status);
+            }
+            if invoices.len() < OUTGOING_PAGE_SIZE {
+                break;
+            }
+        }
+        Ok(PaymentStatus::Unknown)
+    }
+}
+
+const OUTGOING_PAGE_SIZE: usize = 100;
+
+fn outgoing_payment_status(
+    invoices: &[serde_json::Value],
+    payment_hash: &str,
+) -> Option<PaymentStatus> {
+    let invoice = invoices
+        .iter()
+        .find(|invoice| invoice["payment_hash"].as_str() == Some(payment_hash))?;
+
+    Some(if invoice["settled"].as_bool().unwrap_or(false) {
+        PaymentStatus::Paid {
+            total_fees: invoice["fee"].as_u64().unwrap_or(0),
+        }
+    } else {
+        PaymentStatus::Unknown
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::outgoing_payment_status;
+    use crate::lightning::PaymentStatus;
+
+    #[test]
+    fn test_outgoing_payment_status() -> anyhow::Result<()> {
+        let invoices: Vec<serde_json::Value> = serde_json::from_str(
+            r#"[
+                {"payment_hash": "aa", "settled": true, "fee": 2, "type": "outgoing"},
+                {"payment_hash": "bb", "settled": false, "fee": 0, "type": "outgoing"}
+            ]"#,
+        )?;
+
+        assert_eq!(
+            Some(PaymentStatus::Paid { total_fees: 2 }),
+            outgoing_payment_status(&invoices, "aa")
+        );
+        assert_eq!(
+            Some(PaymentStatus::Unknown),
+            outgoing_payment_status(&invoices, "bb")
+        );
+        assert_eq!(None, outgoing_payment_status(&invoices, "cc"));
+        Ok(())
+    }
 }
@@ -1,5 +1,6 @@
 use async_trait::async_trait;
 use clap::Parser;
+use cln_grpc::pb::listpays_pays::ListpaysPaysStatus;
 use cln_grpc::pb::{amount_or_any, Amount, AmountOrAny};
 use cln_grpc::pb::{listinvoices_invoices::ListinvoicesInvoicesStatus, node_client::NodeClient};
 use serde::{Deserialize, Serialize};
@@ -12,7 +13,7 @@ use crate::{
 };
 use tonic::transport::{Certificate, ClientTlsConfig, Identity};
 
-use super::Lightning;
+use super::{Lightning, PaymentStatus};
 
 use secp256k1::rand;
 use std::fs::read;
@@ -169,6 +170,44 @@ impl Lightning for ClnLightning {
             total_fees: payment.amount_sent_msat.unwrap().msat - payment.amount_msat.unwrap().msat, // FIXME check if this is correct
         })
     }
+
+    async fn payment_status(
+        &self,
+        payment_request: String,
+    ) -> Result<PaymentStatus, MokshaMintError> {
+        let pays = self
+            .client_lock()
+            .await
+            .map_err(MokshaMintError::ClnConnectError)?
+            .list_pays(cln_grpc::pb::ListpaysRequest {
+                bolt11: Some(payment_request),
+                payment_hash: None,
+                status: None,
+            })
+            .await
+            .map_err(|status| MokshaMintError::ClnConnectError(status.into()))?
+            .into_inner()
+            .pays;
+
+        // a payment that has been retried shows up once per attempt
+        if let Some(pay) = pays
+            .iter()
+            .find(|pay| pay.status() == ListpaysPaysStatus::Complete)
+        {
+            let msat = |amount: &Option<Amount>| amount.as_ref().map_or(0, |amount| amount.msat);
+            return Ok(PaymentStatus::Paid {
+                total_fees: msat(&pay.amount_sent_msat).saturating_sub(msat(&pay.amount_msat))
+                    / 1_000,
+            });
+        }
+        if pays
+            .iter()
+            .any(|pay| pay.status() == ListpaysPaysStatus::Pending)
+        {
+            return Ok(PaymentStatus::Pending);
+        }
+        Ok(PaymentStatus::Failed)
+    }
 }
 
 // mod tests {
@@ -12,7 +12,7 @@ use crate::{
     model::{CreateInvoiceParams, CreateInvoiceResult, PayInvoiceResult},
 };
 
-use super::{error::LightningError, Lightning};
+use super::{error::LightningError, Lightning, PaymentStatus};
 
 #[derive(Deserialize, Serialize, Debug, Clone, Default, Parser)]
 pub struct LnbitsLightningSettings {
@@ -89,6 +89,17 @@ impl Lightning for LnbitsLightning {
             .await
             .map_err(|err| MokshaMintError::PayInvoice(payment_request, err))
     }
+
+    async fn payment_status(
+        &self,
+        payment_request: String,
+    ) -> Result<PaymentStatus, MokshaMintError> {
+        let decoded_invoice = self.decode_invoice(payment_request).await?;
+        Ok(self
+            .client
+            .payment_status(&decoded_invoice.payment_hash().to_string())
+            .await?)
+    }
 }
 #[derive(Clone)]
 pub struct LNBitsClient {
@@ -227,6 +238,37 @@ impl LNBitsClient {
             .as_bool()
             .unwrap_or(false))
     }
+
+    pub async fn payment_status(
+        &self,
+        payment_hash: &str,
+    ) -> Result<PaymentStatus, LightningError> {
+        let body = match self
+            .make_get(&format!("api/v1/payments/{payment_hash}"))
+            .await
+        {
+            Ok(body) => body,
+            Err(LightningError::NotFound) => return Ok(PaymentStatus::Failed),
+            Err(err) => return Err(err),
+        };
+        let response = serde_json::from_str::<serde_json::Value>(&body)?;
+
+        if response["paid"].as_bool().unwrap_or(false) {
+            // lnbits stores the fee of outgoing payments as negative msat
+            let fee_msat = response["details"]["fee"].as_i64().unwrap_or(0);
+            return Ok(PaymentStatus::Paid {
+                total_fees: fee_msat.unsigned_abs() / 1_000,
+            });
+        }
+
+        let failed = response["details"]["status"].as_str() == Some("failed")
+            || response["details"]["pending"].as_bool() == Some(false);
+        Ok(if failed {
+            PaymentStatus::Failed
+        } else {
+            PaymentStatus::Pending
+        })
+    }
 }
 
 #[cfg(test)]
@@ -18,7 +18,7 @@ use tokio::sync::{MappedMutexGuard, Mutex, MutexGuard};
 use tracing::{debug, instrument};
 use url::Url;
 
-use super::Lightning;
+use super::{Lightning, PaymentStatus};
 
 #[derive(Deserialize, Serialize, Debug, Clone, Default, Parser)]
 pub struct LndLightningSettings {
@@ -85,6 +85,13 @@ impl LndLightning {
         let guard = self.0.lock().await;
         Ok(MutexGuard::map(guard, |client| client.lightning()))
     }
+
+    pub async fn router_lock(
+        &self,
+    ) -> Result<MappedMutexGuard<'_, fedimint_tonic_lnd::RouterClient>, MokshaMintError> {
+        let guard = self.0.lock().await;
+        Ok(MutexGuard::map(guard, |client| client.router()))
+    }
 }
 
 #[async_trait]
@@ -156,4 +163,47 @@ impl Lightning for LndLightning {
             total_fees,
         })
     }
+
+    #[instrument(skip(self), err)]
+    async fn payment_status(
+        &self,
+        payment_request: String,
+    ) -> Result<PaymentStatus, MokshaMintError> {
+        use fedimint_tonic_lnd::lnrpc::payment::PaymentStatus as LndPaymentStatus;
+
+        let invoice = self.decode_invoice(payment_request).await?;
+        let payment_hash: &[u8] = invoice.payment_hash().as_ref();
+
+        let track_request = fedimint_tonic_lnd::routerrpc::TrackPaymentRequest {
+            payment_hash: payment_hash.to_vec(),
+            // the first update contains the current state, even for payments in flight
+            no_inflight_updates: false,
+        };
+        let mut router = self.router_lock().await?;
+        let mut updates = match router
+            .track_payment_v2(fedimint_tonic_lnd::tonic::Request::new(track_request))
+            .await
+        {
+            Ok(response) => response.into_inner(),
+            // lnd does not know payments that were never started
+            Err(status) if status.code() == fedimint_tonic_lnd::tonic::Code::NotFound => {
+                return Ok(PaymentStatus::Failed)
+            }
+            Err(status) => return Err(status.into()),
+        };
+
+        let payment = match updates.message().await? {
+            Some(payment) => payment,
+            None => return Ok(PaymentStatus::Pending),
+        };
+        debug!("lnd payment status: {:?}", payment.status());
+
+        Ok(match payment.status() {
+            LndPaymentStatus::Succeeded => PaymentStatus::Paid {
+                total_fees: payment.fee_sat as u64,
+            },
+            LndPaymentStatus::Failed => PaymentStatus::Failed,
+            _ => PaymentStatus::Pending,
+        })
+    }
 }
@@ -46,6 +46,20 @@ impl fmt::Display for LightningType {
     }
 }
 
+/// State of an outgoing payment as reported by the Lightning backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaymentStatus {
+    /// The payment succeeded, `total_fees` are in sat
+    Paid { total_fees: u64 },
+    /// The payment is still in flight
+    Pending,
+    /// The payment failed or was never started
+    Failed,
+    /// The backend can not tell whether the payment settled, e.g. because it can not look up
+    /// outgoing payments. Inputs stay pending until an operator resolves the melt.
+    Unknown,
+}
+
 #[cfg_attr(test, automock)]
 #[async_trait]
 pub trait Lightning: Send + Sync {
@@ -56,6 +70,15 @@ pub trait Lightning: Send + Sync {
         payment_request: String,
     ) -> Result<PayInvoiceResult, MokshaMintError>;
 
+    /// Looks up the state of a payment started with [`Lightning::pay_invoice`]. Used to resolve
+    /// melts that were interrupted while the invoice was being paid.
+    async fn payment_status(
+        &self,
+        _payment_request: String,
+    ) -> Result<PaymentStatus, MokshaMintError> {
+        Ok(PaymentStatus::Unknown)
+    }
+
     async fn decode_invoice(&self, payment_request: String) -> Result<LNInvoice, MokshaMintError> {
         LNInvoice::from_str(&payment_request)
             .map_err(|err| MokshaMintError::DecodeInvoice(payment_request, err))
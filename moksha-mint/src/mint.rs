@@ -1,29 +1,50 @@
 use crate::{
+    billregistry::{http::HttpBillRegistry, BillRegistry},
     btconchain::{lnd::LndBtcOnchain, BtcOnchain},
+    clock::{remote::RemoteClock, Clock, SystemClock},
     config::{
-        BtcOnchainConfig, BtcOnchainType, BuildParams, DatabaseConfig, LightningFeeConfig,
-        MintConfig, MintInfoConfig, ServerConfig, TracingConfig,
+        BillKeyEncryptionConfig, BillRegistryConfig, BtcOnchainConfig, BtcOnchainType, BuildParams,
+        ClockConfig, DatabaseConfig, DefaultPolicy, LightningFeeConfig, MintConfig, MintInfoConfig,
+        PricingConfig, RedemptionConfig, ServerConfig, TracingConfig, TreasuryConfig,
     },
     database::{postgres::PostgresDB, Database},
     error::MokshaMintError,
+    keyencryption::BillKeyEncryption,
     lightning::{
         alby::AlbyLightning, lnbits::LnbitsLightning, lnd::LndLightning, strike::StrikeLightning,
-        Lightning, LightningType,
+        Lightning, LightningType, PaymentStatus,
     },
-    model::Invoice,
+    model::{
+        BillKeyset, BillKeysetInfo, BillKeysetStatus, Invoice, MintKeysetInfo, PayInvoiceResult,
+        TreasuryMelt,
+    },
+    pricing,
+    spending_conditions::verify_spending_conditions,
 };
 use moksha_core::{
     amount::Amount,
     blind::{BlindedMessage, BlindedSignature, TotalAmount},
     dhke::Dhke,
-    keyset::MintKeyset,
-    primitives::{BtcOnchainMeltQuote, PaymentMethod},
-    proof::Proofs,
+    keyset::{input_fee, MintKeyset},
+    primitives::{
+        BitcreditMintQuote, BitcreditMintQuoteState, BitcreditMintQuoteTransition,
+        BitcreditRequestToMint, Bolt11MeltQuote, Bolt11MintQuote, BtcOnchainMeltQuote,
+        CurrencyUnit, GetTreasuryResponse, PaymentMethod, PostRequestToMintBitcreditRequest,
+        PostTreasuryMeltResponse, ProofState, State, TreasuryBalance, TreasuryEntry,
+        TreasuryEntryKind,
+    },
+    proof::{Proof, Proofs},
 };
+use secp256k1::{PublicKey, SecretKey};
 use sqlx::Transaction;
 use std::str::FromStr;
-use std::{collections::HashSet, sync::Arc, vec};
-use tracing::instrument;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+    vec,
+};
+use tokio::sync::RwLock;
+use tracing::{info, instrument, warn};
 use uuid::Uuid;
 
 use crate::lightning::cln::ClnLightning;
@@ -32,10 +53,19 @@ use crate::lightning::cln::ClnLightning;
 pub struct Mint<DB: Database = PostgresDB> {
     pub lightning: Arc<dyn Lightning + Send + Sync>,
     pub lightning_type: LightningType,
-    pub keyset: MintKeyset,
+    /// active keyset per configured unit, `sat` is always included. The clones of the mint share
+    /// them, so a rotated keyset is used by all requests.
+    pub keysets: Arc<std::sync::RwLock<HashMap<CurrencyUnit, MintKeyset>>>,
+    /// keysets of the bitcredit bills by keyset id, loaded by [`Mint::init_keysets`]
+    pub bill_keysets: Arc<RwLock<HashMap<String, BillKeyset>>>,
     pub db: DB,
     pub dhke: Dhke,
     pub onchain: Option<Arc<dyn BtcOnchain + Send + Sync>>,
+    pub bill_registry: Arc<dyn BillRegistry + Send + Sync>,
+    /// time source for expiry, locktime and maturity checks, the system clock by default
+    pub clock: Arc<dyn Clock + Send + Sync>,
+    /// encrypts the private keys of bills before they are stored, disabled by default
+    pub bill_key_encryption: Arc<BillKeyEncryption>,
     pub config: MintConfig,
     pub build_params: BuildParams,
 }
@@ -51,18 +81,35 @@ where
         config: MintConfig,
         build_params: BuildParams,
         onchain: Option<Arc<dyn BtcOnchain + Send + Sync>>,
+        bill_registry: Arc<dyn BillRegistry + Send + Sync>,
     ) -> Self {
+        let new_keyset = |unit: &CurrencyUnit| MintKeyset {
+            input_fee_ppk: config.input_fee_ppk,
+            ..MintKeyset::new(&config.privatekey, &unit_derivation_path(&config, unit))
+        };
+        // cr-sat keysets are created per bill
+        let keysets = std::iter::once(&CurrencyUnit::Sat)
+            .chain(
+                config
+                    .units
+                    .iter()
+                    .filter(|unit| !matches!(unit, CurrencyUnit::Sat | CurrencyUnit::CrSat)),
+            )
+            .map(|unit| (unit.clone(), new_keyset(unit)))
+            .collect::<HashMap<_, _>>();
+
         Self {
             lightning,
             lightning_type,
-            keyset: MintKeyset::new(
-                &config.privatekey.clone(),
-                &config.derivation_path.clone().unwrap_or_default(),
-            ),
+            keysets: Arc::new(std::sync::RwLock::new(keysets)),
+            bill_keysets: Arc::default(),
             db,
             dhke: Dhke::new(),
             config,
             onchain,
+            bill_registry,
+            clock: Arc::new(SystemClock),
+            bill_key_encryption: Arc::default(),
             build_params,
         }
     }
@@ -79,6 +126,483 @@ where
         std::cmp::max(fee_reserve, self.config.lightning_fee.fee_reserve_min)
     }
 
+    /// Returns true if a bill with the given maturity date (unix seconds) is mature, a bill is
+    /// mature from its maturity date on
+    pub async fn is_mature(&self, maturity_date: i64) -> Result<bool, MokshaMintError> {
+        Ok(maturity_date <= self.clock.now().await? as i64)
+    }
+
+    /// Returns the units the mint has keysets for, `sat` is always the first one
+    pub fn units(&self) -> Vec<CurrencyUnit> {
+        self.active_keysets()
+            .into_iter()
+            .map(|(unit, _)| unit)
+            .collect()
+    }
+
+    /// Returns the active keyset of the `sat` unit
+    pub fn keyset(&self) -> MintKeyset {
+        self.active_keyset(&CurrencyUnit::Sat)
+            .expect("sat keyset is always active")
+    }
+
+    /// Returns the active keyset of the unit
+    pub fn active_keyset(&self, unit: &CurrencyUnit) -> Result<MintKeyset, MokshaMintError> {
+        self.keysets
+            .read()
+            .expect("keysets lock poisoned")
+            .get(unit)
+            .cloned()
+            .ok_or_else(|| MokshaMintError::CurrencyNotSupported(unit.to_string()))
+    }
+
+    /// Returns the active keysets, `sat` first and the other units ordered by name
+    fn active_keysets(&self) -> Vec<(CurrencyUnit, MintKeyset)> {
+        let mut keysets = self
+            .keysets
+            .read()
+            .expect("keysets lock poisoned")
+            .iter()
+            .map(|(unit, keyset)| (unit.clone(), keyset.clone()))
+            .collect::<Vec<_>>();
+        keysets.sort_by_key(|(unit, _)| (*unit != CurrencyUnit::Sat, unit.to_string()));
+        keysets
+    }
+
+    fn set_active_keyset(&self, unit: CurrencyUnit, keyset: MintKeyset) {
+        self.keysets
+            .write()
+            .expect("keysets lock poisoned")
+            .insert(unit, keyset);
+    }
+
+    /// Persists the configured keysets and loads the active keyset of every unit from the
+    /// database.
+    ///
+    /// A keyset that is not known yet (e.g. a new derivation path) becomes the active keyset of
+    /// its unit and all older keysets of the unit are deactivated. If the configured keyset is
+    /// already known the active keyset from the database is used, so keysets rotated with
+    /// [`Mint::rotate_keyset`] stay active after a restart.
+    pub async fn init_keysets(&self) -> Result<(), MokshaMintError> {
+        let mut tx = self.db.begin_tx().await?;
+        for (unit, keyset) in self.active_keysets() {
+            let keyset = self.init_keyset(&mut tx, &unit, keyset).await?;
+            self.set_active_keyset(unit, keyset);
+        }
+        self.init_bill_keysets(&mut tx).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Loads the keysets of all bills into the cache. Bills that were requested to mint before
+    /// their keysets were persisted get a keyset here. Bill keys that are not encrypted with the
+    /// active key-encryption key are re-encrypted.
+    async fn init_bill_keysets(
+        &self,
+        tx: &mut Transaction<'_, <DB as Database>::DB>,
+    ) -> Result<(), MokshaMintError> {
+        let known_bills = self
+            .db
+            .get_bill_keysets(tx)
+            .await?
+            .into_iter()
+            .map(|info| info.bill_id)
+            .collect::<HashSet<_>>();
+        let mut bill_keysets = HashMap::new();
+        for stored in self.db.get_bitcredit_requests_to_mint(tx).await? {
+            if let Some(bill_key) = self
+                .bill_key_encryption
+                .rotate(&stored.bill_id, &stored.bill_key)?
+            {
+                self.db
+                    .update_bitcredit_request_to_mint_key(tx, &stored.bill_id, &bill_key)
+                    .await?;
+            }
+            let request_to_mint = BitcreditRequestToMint {
+                bill_key: self
+                    .bill_key_encryption
+                    .decrypt(&stored.bill_id, &stored.bill_key)?,
+                ..stored
+            };
+            let bill_keyset = BillKeyset::new(&request_to_mint);
+            if !known_bills.contains(&request_to_mint.bill_id) {
+                let info = BillKeysetInfo::new(
+                    &request_to_mint,
+                    &bill_keyset.keyset,
+                    self.clock.now().await? as i64,
+                );
+                self.db.add_bill_keyset(tx, &info).await?;
+            }
+            bill_keysets.insert(bill_keyset.keyset.keyset_id.clone(), bill_keyset);
+        }
+        *self.bill_keysets.write().await = bill_keysets;
+        Ok(())
+    }
+
+    /// Stores the request to mint of a bill together with the keyset of the bill. The bill key
+    /// is encrypted before it is stored.
+    pub async fn add_bill(
+        &self,
+        request_to_mint: &BitcreditRequestToMint,
+    ) -> Result<BillKeysetInfo, MokshaMintError> {
+        let bill_keyset = BillKeyset::new(request_to_mint);
+        let info = BillKeysetInfo::new(
+            request_to_mint,
+            &bill_keyset.keyset,
+            self.clock.now().await? as i64,
+        );
+        let stored = BitcreditRequestToMint {
+            bill_key: self
+                .bill_key_encryption
+                .encrypt(&request_to_mint.bill_id, &request_to_mint.bill_key)?,
+            ..request_to_mint.clone()
+        };
+
+        let mut tx = self.db.begin_tx().await?;
+        self.db
+            .add_bitcredit_request_to_mint(&mut tx, &stored)
+            .await?;
+        self.db.add_bill_keyset(&mut tx, &info).await?;
+        tx.commit().await?;
+
+        self.bill_keysets
+            .write()
+            .await
+            .insert(info.id.clone(), bill_keyset);
+        Ok(info)
+    }
+
+    /// Adds the bill of a request to mint after checking that the request is signed by the
+    /// holder of the bill and that it matches the bill in the registry. The key of the holder is
+    /// taken from the registry, the key in the request has to be the same.
+    pub async fn request_to_mint(
+        &self,
+        request: &PostRequestToMintBitcreditRequest,
+    ) -> Result<BitcreditRequestToMint, MokshaMintError> {
+        let bill = self.bill_registry.get_bill(&request.bill_id).await?;
+        let holder_key = PublicKey::from_str(&bill.holder_public_key).ok();
+        if holder_key.is_none()
+            || holder_key != PublicKey::from_str(&request.node_id).ok()
+            || !request.verify_signature()
+        {
+            return Err(MokshaMintError::BitcreditRequestNotSignedByHolder(
+                request.bill_id.clone(),
+            ));
+        }
+        if bill.amount != request.bill_amount || bill.maturity_date != request.maturity_date {
+            return Err(MokshaMintError::BitcreditRequestBillMismatch(
+                request.bill_id.clone(),
+            ));
+        }
+
+        let request_to_mint = BitcreditRequestToMint {
+            bill_id: request.bill_id.clone(),
+            bill_key: request.bill_keys.private_key_pem.clone(),
+            maturity_date: request.maturity_date,
+            bill_amount: request.bill_amount,
+        };
+        self.add_bill(&request_to_mint).await?;
+        Ok(request_to_mint)
+    }
+
+    /// Returns the keyset of a bill by its keyset id or by the bill id
+    pub async fn bill_keyset(&self, id: &str) -> Result<BillKeyset, MokshaMintError> {
+        let bill_keysets = self.bill_keysets.read().await;
+        bill_keysets
+            .get(id)
+            .or_else(|| bill_keysets.get(&bill_keyset_id(id)))
+            .cloned()
+            .ok_or_else(|| MokshaMintError::KeysetNotFound(id.to_owned()))
+    }
+
+    /// Returns the keysets of all bills ordered by their maturity date
+    pub async fn get_bill_keysets(&self) -> Result<Vec<BillKeysetInfo>, MokshaMintError> {
+        let mut tx = self.db.begin_tx().await?;
+        let keysets = self.db.get_bill_keysets(&mut tx).await?;
+        tx.commit().await?;
+        Ok(keysets)
+    }
+
+    async fn init_keyset(
+        &self,
+        tx: &mut Transaction<'_, <DB as Database>::DB>,
+        unit: &CurrencyUnit,
+        keyset: MintKeyset,
+    ) -> Result<MintKeyset, MokshaMintError> {
+        match self.db.get_keyset(tx, &keyset.keyset_id).await {
+            Ok(_) => {
+                let active = self
+                    .db
+                    .get_keysets(tx)
+                    .await?
+                    .into_iter()
+                    .find(|info| info.active && info.unit == *unit);
+                match active {
+                    Some(active) => self.derive_keyset(&active),
+                    None => {
+                        self.db.activate_keyset(tx, &keyset.keyset_id, unit).await?;
+                        Ok(keyset)
+                    }
+                }
+            }
+            Err(MokshaMintError::Db(sqlx::Error::RowNotFound)) => {
+                let derivation_path = unit_derivation_path(&self.config, unit);
+                self.add_active_keyset(tx, unit, &keyset.keyset_id, &derivation_path)
+                    .await?;
+                Ok(keyset)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Creates a new keyset for the unit from the mint private key and makes it the active
+    /// keyset of the unit. Without a `derivation_path` a counter is appended to the derivation
+    /// path of the unit.
+    ///
+    /// Proofs of the previous keysets stay valid, but only the new keyset is used for signing.
+    pub async fn rotate_keyset(
+        &self,
+        unit: &CurrencyUnit,
+        derivation_path: Option<String>,
+    ) -> Result<MintKeyset, MokshaMintError> {
+        self.active_keyset(unit)?;
+
+        let mut tx = self.db.begin_tx().await?;
+        let known_ids = self
+            .db
+            .get_keysets(&mut tx)
+            .await?
+            .into_iter()
+            .map(|keyset| keyset.id)
+            .collect::<HashSet<_>>();
+
+        let (derivation_path, keyset) = match derivation_path {
+            Some(path) => {
+                let keyset = MintKeyset::new(&self.config.privatekey, &path);
+                (path, keyset)
+            }
+            None => {
+                let base = unit_derivation_path(&self.config, unit);
+                let mut counter = known_ids.len();
+                loop {
+                    let path = format!("{base}/{counter}'");
+                    let keyset = MintKeyset::new(&self.config.privatekey, &path);
+                    if !known_ids.contains(&keyset.keyset_id) {
+                        break (path, keyset);
+                    }
+                    counter += 1;
+                }
+            }
+        };
+
+        let keyset = if known_ids.contains(&keyset.keyset_id) {
+            let info = self.db.get_keyset(&mut tx, &keyset.keyset_id).await?;
+            if info.unit != *unit {
+                return Err(MokshaMintError::UnitMismatch(unit.to_string()));
+            }
+            self.db
+                .activate_keyset(&mut tx, &keyset.keyset_id, unit)
+                .await?;
+            self.derive_keyset(&info)?
+        } else {
+            self.add_active_keyset(&mut tx, unit, &keyset.keyset_id, &derivation_path)
+                .await?;
+            MintKeyset {
+                input_fee_ppk: self.config.input_fee_ppk,
+                ..keyset
+            }
+        };
+        tx.commit().await?;
+
+        self.set_active_keyset(unit.clone(), keyset.clone());
+        Ok(keyset)
+    }
+
+    /// Returns all keysets of the mint, the active keyset of every unit is always included.
+    /// Keysets of units the mint has no active keyset for (e.g. units that were configured
+    /// before) are returned as inactive.
+    pub async fn get_keysets(&self) -> Result<Vec<MintKeysetInfo>, MokshaMintError> {
+        let mut tx = self.db.begin_tx().await?;
+        let mut keysets = self.db.get_keysets(&mut tx).await?;
+        tx.commit().await?;
+
+        let units = self.units();
+        keysets
+            .iter_mut()
+            .filter(|k| !units.contains(&k.unit))
+            .for_each(|k| k.active = false);
+
+        for (unit, active) in self.active_keysets() {
+            if !keysets.iter().any(|k| k.id == active.keyset_id) {
+                keysets
+                    .iter_mut()
+                    .filter(|k| k.unit == unit)
+                    .for_each(|k| k.active = false);
+                keysets.push(MintKeysetInfo {
+                    id: active.keyset_id.clone(),
+                    derivation_path: unit_derivation_path(&self.config, &unit),
+                    unit,
+                    active: true,
+                    input_fee_ppk: active.input_fee_ppk,
+                    created_at: self.clock.now().await? as i64,
+                });
+            }
+        }
+        Ok(keysets)
+    }
+
+    /// Returns the active keyset or one of the inactive keysets of the mint
+    pub async fn get_keyset(&self, keyset_id: &str) -> Result<MintKeyset, MokshaMintError> {
+        let mut tx = self.db.begin_tx().await?;
+        let keyset = self.find_mint_keyset(&mut tx, keyset_id).await?;
+        tx.commit().await?;
+        keyset.ok_or_else(|| MokshaMintError::KeysetNotFound(keyset_id.to_owned()))
+    }
+
+    async fn add_active_keyset(
+        &self,
+        tx: &mut Transaction<'_, <DB as Database>::DB>,
+        unit: &CurrencyUnit,
+        keyset_id: &str,
+        derivation_path: &str,
+    ) -> Result<(), MokshaMintError> {
+        self.db
+            .add_keyset(
+                tx,
+                &MintKeysetInfo {
+                    id: keyset_id.to_owned(),
+                    unit: unit.clone(),
+                    derivation_path: derivation_path.to_owned(),
+                    active: true,
+                    input_fee_ppk: self.config.input_fee_ppk,
+                    created_at: self.clock.now().await? as i64,
+                },
+            )
+            .await?;
+        self.db.activate_keyset(tx, keyset_id, unit).await
+    }
+
+    /// Derives the private keys of a stored keyset. Fails if the keyset was created with a
+    /// different mint private key.
+    fn derive_keyset(&self, info: &MintKeysetInfo) -> Result<MintKeyset, MokshaMintError> {
+        let keyset = MintKeyset::new(&self.config.privatekey, &info.derivation_path);
+        if keyset.keyset_id != info.id {
+            return Err(MokshaMintError::KeysetNotFound(info.id.clone()));
+        }
+        Ok(MintKeyset {
+            input_fee_ppk: info.input_fee_ppk,
+            ..keyset
+        })
+    }
+
+    async fn find_mint_keyset(
+        &self,
+        tx: &mut Transaction<'_, <DB as Database>::DB>,
+        keyset_id: &str,
+    ) -> Result<Option<MintKeyset>, MokshaMintError> {
+        if let Some((_, keyset)) = self
+            .active_keysets()
+            .into_iter()
+            .find(|(_, keyset)| keyset.keyset_id == keyset_id)
+        {
+            return Ok(Some(keyset));
+        }
+        match self.db.get_keyset(tx, keyset_id).await {
+            Ok(info) => Ok(Some(self.derive_keyset(&info)?)),
+            Err(MokshaMintError::Db(sqlx::Error::RowNotFound)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns the unit of a mint keyset or of the keyset of a bitcredit bill
+    async fn keyset_unit(
+        &self,
+        tx: &mut Transaction<'_, <DB as Database>::DB>,
+        keyset_id: &str,
+    ) -> Result<CurrencyUnit, MokshaMintError> {
+        if let Some((unit, _)) = self
+            .active_keysets()
+            .into_iter()
+            .find(|(_, keyset)| keyset.keyset_id == keyset_id)
+        {
+            return Ok(unit);
+        }
+        match self.db.get_keyset(tx, keyset_id).await {
+            Ok(info) => Ok(info.unit),
+            Err(MokshaMintError::Db(sqlx::Error::RowNotFound)) => {
+                self.find_keyset(tx, keyset_id).await?;
+                Ok(CurrencyUnit::CrSat)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Inputs and outputs must have the unit of the keyset that signs the outputs. Units can only
+    /// be converted by minting and melting, never by a swap. Tokens of a bill can not be swapped
+    /// into tokens of another bill.
+    async fn verify_unit(
+        &self,
+        tx: &mut Transaction<'_, <DB as Database>::DB>,
+        proofs: &Proofs,
+        blinded_messages: &[BlindedMessage],
+        keyset: &MintKeyset,
+    ) -> Result<(), MokshaMintError> {
+        let unit = self.keyset_unit(tx, &keyset.keyset_id).await?;
+        let ids = proofs
+            .proofs()
+            .iter()
+            .map(|proof| proof.keyset_id.clone())
+            .chain(blinded_messages.iter().map(|msg| msg.id.clone()))
+            .collect::<HashSet<_>>();
+        for id in ids {
+            let same_bill = unit != CurrencyUnit::CrSat || id == keyset.keyset_id;
+            if !same_bill || self.keyset_unit(tx, &id).await? != unit {
+                return Err(MokshaMintError::UnitMismatch(unit.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the keyset that signs the outputs of a swap: the active keyset of their unit or the
+    /// keyset of their bill.
+    pub async fn swap_keyset(
+        &self,
+        blinded_messages: &[BlindedMessage],
+    ) -> Result<MintKeyset, MokshaMintError> {
+        let Some(output) = blinded_messages.first() else {
+            return Ok(self.keyset());
+        };
+        let mut tx = self.db.begin_tx().await?;
+        let keyset = match self.keyset_unit(&mut tx, &output.id).await? {
+            CurrencyUnit::CrSat => self.find_keyset(&mut tx, &output.id).await?,
+            unit => self.active_keyset(&unit)?,
+        };
+        tx.commit().await?;
+        Ok(keyset)
+    }
+
+    /// Outputs must not be signed with an inactive keyset (Nut-02)
+    async fn verify_outputs_keyset(
+        &self,
+        tx: &mut Transaction<'_, <DB as Database>::DB>,
+        blinded_messages: &[BlindedMessage],
+    ) -> Result<(), MokshaMintError> {
+        let ids = blinded_messages
+            .iter()
+            .map(|msg| msg.id.as_str())
+            .collect::<HashSet<_>>();
+        for id in ids {
+            match self.db.get_keyset(tx, id).await {
+                Ok(info) if !info.active => {
+                    return Err(MokshaMintError::InactiveKeyset(id.to_owned()))
+                }
+                Ok(_) | Err(MokshaMintError::Db(sqlx::Error::RowNotFound)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
     pub fn create_blinded_signatures(
         &self,
         blinded_messages: &[BlindedMessage],
@@ -91,11 +615,12 @@ where
                     .private_keys
                     .get(&blinded_msg.amount)
                     .ok_or(MokshaMintError::PrivateKeyNotFound)?;
-                let blinded_sig = self.dhke.step2_bob(blinded_msg.b_, private_key)?;
+                let (blinded_sig, dleq) = self.dhke.step2_bob(blinded_msg.b_, private_key)?;
                 Ok(BlindedSignature {
                     id: keyset.keyset_id.clone(),
                     amount: blinded_msg.amount,
                     c_: blinded_sig,
+                    dleq: Some(dleq),
                 })
             })
             .collect::<Result<Vec<_>, _>>()
@@ -124,146 +649,821 @@ where
         key: String,
         outputs: &[BlindedMessage],
         keyset: &MintKeyset,
-        return_error: bool,
     ) -> Result<Vec<BlindedSignature>, MokshaMintError> {
-        // FIXME refactor (split up in multiple functions)
+        let mut bitcredit_quote = None;
         if payment_method == PaymentMethod::Bolt11 {
-            let mut tx = self.db.begin_tx().await?;
-            let invoice = self.db.get_pending_invoice(&mut tx, key.clone()).await?;
-
-            let is_paid = self
-                .lightning
-                .is_invoice_paid(invoice.payment_request.clone())
+            let quote = self
+                .verify_bolt11_mint_quote(tx, &Uuid::from_str(&key)?, outputs)
                 .await?;
-
-            // FIXME remove after legacy api is removed
-            if return_error && !is_paid {
-                return Err(MokshaMintError::InvoiceNotPaidYet);
+            if self.keyset_unit(tx, &keyset.keyset_id).await? != quote.unit {
+                return Err(MokshaMintError::UnitMismatch(quote.unit.to_string()));
             }
-
-            self.db.delete_pending_invoice(&mut tx, key).await?;
-            tx.commit().await?;
         } else if payment_method == PaymentMethod::Bitcredit {
-            let mut tx = self.db.begin_tx().await?;
-
             let quote = self
-                .db
-                .get_bitcredit_mint_quote(&mut tx, &Uuid::from_str(key.clone().as_str())?)
+                .verify_bitcredit_mint_quote(tx, &Uuid::from_str(&key)?, outputs)
                 .await?;
-
-            let is_sent = quote.sent;
-            let mut endorsed = quote.endorsed;
-            let quote_amount = quote.amount;
-
-            let mut amount = 0;
-            for blinded_message in outputs {
-                amount += blinded_message.amount;
+            // bills are minted as cr-sat of their own keyset, sat are only paid out on redemption
+            if keyset.keyset_id != bill_keyset_id(&quote.bill_id) {
+                return Err(MokshaMintError::UnitMismatch(
+                    CurrencyUnit::CrSat.to_string(),
+                ));
             }
+            bitcredit_quote = Some(quote);
+        }
+        self.verify_unit(tx, &Proofs::empty(), outputs, keyset)
+            .await?;
+        let promises = self.create_blinded_signatures(outputs, keyset)?;
+        self.db.add_promises(tx, outputs, &promises).await?;
+        if let Some(quote) = bitcredit_quote {
+            self.transition_bitcredit_mint_quote(tx, &quote, BitcreditMintQuoteState::Minted, None)
+                .await?;
+            self.add_bill_earnings(tx, &quote).await?;
+        }
+        Ok(promises)
+    }
 
-            if !endorsed {
-                endorsed = Self::am_i_holder(quote.bill_id).await;
+    /// Checks that the holder accepted the offer, that the outputs match the offered amount and
+    /// that the bill has been endorsed to the mint.
+    async fn verify_bitcredit_mint_quote(
+        &self,
+        tx: &mut Transaction<'_, <DB as Database>::DB>,
+        quote_id: &Uuid,
+        outputs: &[BlindedMessage],
+    ) -> Result<BitcreditMintQuote, MokshaMintError> {
+        let mut quote = self.get_bitcredit_mint_quote(quote_id).await?;
+        match quote.state {
+            BitcreditMintQuoteState::Minted => {
+                return Err(MokshaMintError::BitcreditQuoteAlreadySent)
             }
-
-            if return_error || is_sent {
-                return Err(MokshaMintError::BitcreditQuoteAlreadySent);
+            BitcreditMintQuoteState::Rejected => {
+                return Err(MokshaMintError::BitcreditQuoteRejected(*quote_id))
             }
-            if !quote_amount.eq(&amount) {
-                return Err(MokshaMintError::BitcreditQuoteIncorrectAmount);
+            BitcreditMintQuoteState::Expired => {
+                return Err(MokshaMintError::QuoteExpired(*quote_id))
             }
-            if !endorsed {
-                return Err(MokshaMintError::BitcreditQuoteMintNotHolder);
+            BitcreditMintQuoteState::Requested | BitcreditMintQuoteState::Offered => {
+                return Err(MokshaMintError::BitcreditQuoteNotAccepted(*quote_id))
             }
+            _ => {}
         }
-        self.create_blinded_signatures(outputs, keyset)
-    }
 
-    async fn am_i_holder(id: String) -> bool {
-        let request_url = format!("http://localhost:8000/bill/holder/{id}", id = id);
-        let am_i_holder: bool = reqwest::get(&request_url)
-            .await
-            .expect("Failed to send request")
-            .json()
-            .await
-            .expect("Failed to read response");
+        let amount = outputs.iter().map(|output| output.amount).sum::<u64>();
+        if quote.amount != amount {
+            return Err(MokshaMintError::BitcreditQuoteIncorrectAmount);
+        }
 
-        am_i_holder
-    }
+        if quote.state == BitcreditMintQuoteState::Accepted {
+            if !self.bill_registry.is_holder(&quote.bill_id).await? {
+                return Err(MokshaMintError::BitcreditQuoteMintNotHolder);
+            }
+            quote = self
+                .transition_bitcredit_mint_quote(
+                    tx,
+                    &quote,
+                    BitcreditMintQuoteState::Endorsed,
+                    None,
+                )
+                .await?;
+        }
 
-    fn has_duplicate_pubkeys(outputs: &[BlindedMessage]) -> bool {
-        let mut uniq = HashSet::new();
-        !outputs.iter().all(move |x| uniq.insert(x.b_))
+        self.db
+            .add_bill_amount_minted(tx, &bill_keyset_id(&quote.bill_id), amount)
+            .await?;
+        Ok(quote)
     }
 
-    #[instrument(level = "debug", skip_all, err)]
-    pub async fn swap(
+    /// Creates a quote for the bill and offers the price of the pricing policy. The request is
+    /// rejected if the holder asks for more than the offered amount.
+    pub async fn create_bitcredit_mint_quote(
         &self,
-        proofs: &Proofs,
-        blinded_messages: &[BlindedMessage],
-        keyset: &MintKeyset,
-    ) -> Result<Vec<BlindedSignature>, MokshaMintError> {
+        bill_id: String,
+        node_id: String,
+        amount: u64,
+    ) -> Result<BitcreditMintQuote, MokshaMintError> {
+        let now = self.clock.now().await?;
+        let bill = self.bill_registry.get_bill(&bill_id).await?;
+        let offer = pricing::offer(
+            &self.config.pricing,
+            bill.amount,
+            bill.maturity_date,
+            now,
+            &bill.drawee_node_id,
+        )?;
+        let quote = BitcreditMintQuote {
+            quote_id: Uuid::new_v4(),
+            bill_id,
+            node_id,
+            amount,
+            state: BitcreditMintQuoteState::Requested,
+            expiry: now + BITCREDIT_QUOTE_EXPIRY_SECS,
+        };
+
         let mut tx = self.db.begin_tx().await?;
-        self.check_used_proofs(&mut tx, proofs).await?;
+        self.db.add_bitcredit_mint_quote(&mut tx, &quote).await?;
+        self.db
+            .add_bitcredit_mint_quote_transition(
+                &mut tx,
+                &quote.quote_id,
+                &BitcreditMintQuoteTransition {
+                    state: quote.state,
+                    timestamp: now,
+                    reason: None,
+                },
+            )
+            .await?;
 
-        if Self::has_duplicate_pubkeys(blinded_messages) {
-            return Err(MokshaMintError::SwapHasDuplicatePromises);
+        if amount > offer {
+            self.transition_bitcredit_mint_quote(
+                &mut tx,
+                &quote,
+                BitcreditMintQuoteState::Rejected,
+                Some(format!("requested {amount} but the offer is {offer}")),
+            )
+            .await?;
+            tx.commit().await?;
+            return Err(MokshaMintError::BitcreditAmountAboveOffer(amount, offer));
         }
 
-        let sum_proofs = proofs.total_amount();
+        let quote = self
+            .transition_bitcredit_mint_quote(
+                &mut tx,
+                &BitcreditMintQuote {
+                    amount: offer,
+                    ..quote
+                },
+                BitcreditMintQuoteState::Offered,
+                None,
+            )
+            .await?;
+        tx.commit().await?;
+        Ok(quote)
+    }
 
-        let promises = self.create_blinded_signatures(blinded_messages, keyset)?;
-        let amount_promises = promises.total_amount();
-        if sum_proofs != amount_promises {
-            return Err(MokshaMintError::SwapAmountMismatch(format!(
-                "Swap amount mismatch: {sum_proofs} != {amount_promises}"
-            )));
+    /// Accepts the offer of the mint, the offered amount can be minted after the bill has been
+    /// endorsed to the mint
+    pub async fn accept_bitcredit_mint_quote(
+        &self,
+        quote_id: &Uuid,
+    ) -> Result<BitcreditMintQuote, MokshaMintError> {
+        let quote = self.get_bitcredit_mint_quote(quote_id).await?;
+        match quote.state {
+            BitcreditMintQuoteState::Expired => Err(MokshaMintError::QuoteExpired(*quote_id)),
+            BitcreditMintQuoteState::Rejected => {
+                Err(MokshaMintError::BitcreditQuoteRejected(*quote_id))
+            }
+            _ => {
+                let mut tx = self.db.begin_tx().await?;
+                let quote = self
+                    .transition_bitcredit_mint_quote(
+                        &mut tx,
+                        &quote,
+                        BitcreditMintQuoteState::Accepted,
+                        None,
+                    )
+                    .await?;
+                tx.commit().await?;
+                Ok(quote)
+            }
         }
+    }
 
-        self.db.add_used_proofs(&mut tx, proofs).await?;
+    /// Returns the quote. Quotes that have not been endorsed before their expiry are expired,
+    /// the expiry is committed in its own transaction, so it is kept even if the caller fails
+    /// because the quote expired.
+    pub async fn get_bitcredit_mint_quote(
+        &self,
+        quote_id: &Uuid,
+    ) -> Result<BitcreditMintQuote, MokshaMintError> {
+        let mut tx = self.db.begin_tx().await?;
+        let mut quote = self.db.get_bitcredit_mint_quote(&mut tx, quote_id).await?;
+        if quote
+            .state
+            .can_transition_to(BitcreditMintQuoteState::Expired)
+            && quote.expiry < self.clock.now().await?
+        {
+            quote = self
+                .transition_bitcredit_mint_quote(
+                    &mut tx,
+                    &quote,
+                    BitcreditMintQuoteState::Expired,
+                    None,
+                )
+                .await?;
+        }
         tx.commit().await?;
-        Ok(promises)
+        Ok(quote)
     }
 
-    #[instrument(level = "debug", skip(self, proofs, blinded_messages, keyset), err)]
-    pub async fn melt_bolt11(
+    /// Moves the quote to `state` and records the transition, fails if the quote can not move
+    /// from its current state to `state`
+    pub async fn transition_bitcredit_mint_quote(
         &self,
         tx: &mut Transaction<'_, <DB as Database>::DB>,
-        payment_request: String,
-        fee_reserve: u64,
-        proofs: &Proofs,
-        blinded_messages: Option<Vec<BlindedMessage>>,
-        keyset: &MintKeyset,
-    ) -> Result<(bool, String, Vec<BlindedSignature>), MokshaMintError> {
-        let invoice = self
-            .lightning
-            .decode_invoice(payment_request.clone())
+        quote: &BitcreditMintQuote,
+        state: BitcreditMintQuoteState,
+        reason: Option<String>,
+    ) -> Result<BitcreditMintQuote, MokshaMintError> {
+        if !quote.state.can_transition_to(state) {
+            return Err(MokshaMintError::InvalidQuoteTransition(
+                quote.quote_id,
+                quote.state,
+                state,
+            ));
+        }
+        let quote = BitcreditMintQuote {
+            state,
+            ..quote.clone()
+        };
+        self.db.update_bitcredit_mint_quote(tx, &quote).await?;
+        self.db
+            .add_bitcredit_mint_quote_transition(
+                tx,
+                &quote.quote_id,
+                &BitcreditMintQuoteTransition {
+                    state,
+                    timestamp: self.clock.now().await?,
+                    reason,
+                },
+            )
             .await?;
+        Ok(quote)
+    }
 
-        let proofs_amount = proofs.total_amount();
-
-        // TODO verify proofs
-
-        self.check_used_proofs(tx, proofs).await?;
+    /// Records the discount the mint earned on the bill of the quote in the treasury. If
+    /// configured, the earnings are issued as ecash of the bill keyset and kept in the treasury.
+    async fn add_bill_earnings(
+        &self,
+        tx: &mut Transaction<'_, <DB as Database>::DB>,
+        quote: &BitcreditMintQuote,
+    ) -> Result<(), MokshaMintError> {
+        let request_to_mint = self
+            .db
+            .get_bitcredit_request_to_mint(tx, &quote.bill_id)
+            .await?;
+        let earned = request_to_mint.bill_amount.saturating_sub(quote.amount);
+        if earned == 0 {
+            return Ok(());
+        }
+
+        self.db
+            .add_treasury_entry(
+                tx,
+                &TreasuryEntry {
+                    bill_id: quote.bill_id.clone(),
+                    kind: TreasuryEntryKind::Earned,
+                    amount: earned,
+                    reference: Some(quote.quote_id.to_string()),
+                    timestamp: self.clock.now().await?,
+                },
+            )
+            .await?;
+        if self.config.treasury.issue_ecash {
+            let keyset = self.bill_keyset(&quote.bill_id).await?.keyset;
+            let proofs = self.issue_proofs(earned, &keyset)?;
+            self.db
+                .add_treasury_proofs(tx, &quote.bill_id, &proofs)
+                .await?;
+            self.db
+                .add_bill_amount_minted(tx, &keyset.keyset_id, earned)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Creates proofs for the mint itself. The mint knows the secrets, so nothing is blinded.
+    fn issue_proofs(&self, amount: u64, keyset: &MintKeyset) -> Result<Proofs, MokshaMintError> {
+        Amount(amount)
+            .split()
+            .into_iter()
+            .map(|amount| {
+                let private_key = keyset
+                    .private_keys
+                    .get(&amount)
+                    .ok_or(MokshaMintError::PrivateKeyNotFound)?;
+                let secret =
+                    hex::encode(SecretKey::new(&mut secp256k1::rand::thread_rng()).secret_bytes());
+                let (c, _) = self
+                    .dhke
+                    .step2_bob(Dhke::hash_to_curve(secret.as_bytes())?, private_key)?;
+                Ok(Proof::new(amount, secret, c, keyset.keyset_id.clone()))
+            })
+            .collect::<Result<Vec<_>, MokshaMintError>>()
+            .map(Proofs::new)
+    }
+
+    /// Returns the balances of the treasury per bill together with all ledger entries
+    pub async fn get_treasury(&self) -> Result<GetTreasuryResponse, MokshaMintError> {
+        let mut tx = self.db.begin_tx().await?;
+        let entries = self.db.get_treasury_entries(&mut tx).await?;
+        let melts = self.db.get_treasury_melts(&mut tx).await?;
+        tx.commit().await?;
+        Ok(GetTreasuryResponse {
+            balances: treasury_balances(&entries, &melts),
+            entries,
+        })
+    }
+
+    /// Hands out `amount` of the earnings of the bill as `cr-sat` proofs
+    pub async fn withdraw_treasury(
+        &self,
+        bill_id: &str,
+        amount: u64,
+    ) -> Result<Proofs, MokshaMintError> {
+        let keyset = self.bill_keyset(bill_id).await?.keyset;
+        let mut tx = self.db.begin_tx().await?;
+        let balance = self.lock_treasury_balance(&mut tx, bill_id).await?;
+        if amount > balance {
+            return Err(MokshaMintError::TreasuryBalanceExceeded(
+                bill_id.to_owned(),
+                amount,
+                balance,
+            ));
+        }
+        let covered = self
+            .debit_treasury(&mut tx, bill_id, amount, TreasuryEntryKind::Withdrawn, None)
+            .await?;
+        let proofs = self.issue_proofs(amount, &keyset)?;
+        self.db
+            .add_bill_amount_minted(&mut tx, &keyset.keyset_id, amount - covered)
+            .await?;
+        tx.commit().await?;
+        Ok(proofs)
+    }
+
+    /// Pays the invoice from the earnings of the bill. The earnings are only backed by sats
+    /// after the drawee paid the bill.
+    ///
+    /// The invoice amount and the fee reserve are reserved before the invoice is paid. If the
+    /// outcome of the payment is not known, they stay reserved until
+    /// [`Mint::reconcile_pending_melts`] resolves the payment.
+    pub async fn melt_treasury(
+        &self,
+        bill_id: &str,
+        payment_request: String,
+    ) -> Result<PostTreasuryMeltResponse, MokshaMintError> {
+        let bill_keyset = self.bill_keyset(bill_id).await?;
+        if !self.is_mature(bill_keyset.maturity_date).await? {
+            return Err(MokshaMintError::BillNotMature(bill_id.to_owned()));
+        }
+        if !self.bill_registry.get_bill(bill_id).await?.paid {
+            return Err(MokshaMintError::BillNotPaid(bill_id.to_owned()));
+        }
 
-        // TODO check for fees
+        let invoice = self
+            .lightning
+            .decode_invoice(payment_request.clone())
+            .await?;
         let amount_msat = invoice
             .amount_milli_satoshis()
-            .expect("Invoice amount is missing");
+            .ok_or_else(|| MokshaMintError::InvalidAmount("invalid invoice".to_owned()))?;
+        let melt = TreasuryMelt {
+            payment_hash: invoice.payment_hash().to_string(),
+            bill_id: bill_id.to_owned(),
+            payment_request: payment_request.clone(),
+            amount: amount_msat.div_ceil(1_000),
+            fee_reserve: self.fee_reserve_msat(amount_msat).div_ceil(1_000),
+            created_at: self.clock.now().await?,
+        };
 
-        if amount_msat < (proofs_amount / 1_000) {
-            return Err(MokshaMintError::InvoiceAmountTooLow(format!(
-                "Invoice amount is too low: {amount_msat}",
-            )));
+        let mut tx = self.db.begin_tx().await?;
+        let balance = self.lock_treasury_balance(&mut tx, bill_id).await?;
+        if melt.amount + melt.fee_reserve > balance {
+            return Err(MokshaMintError::TreasuryBalanceExceeded(
+                bill_id.to_owned(),
+                melt.amount + melt.fee_reserve,
+                balance,
+            ));
+        }
+        self.db.add_treasury_melt(&mut tx, &melt).await?;
+        tx.commit().await?;
+
+        let total_fees = match self.lightning.pay_invoice(payment_request.clone()).await {
+            Ok(result) => result.total_fees,
+            Err(err) => match self.lightning.payment_status(payment_request).await {
+                Ok(PaymentStatus::Paid { total_fees }) => total_fees,
+                Ok(PaymentStatus::Failed) => {
+                    self.release_treasury_melt(&melt).await?;
+                    return Err(err);
+                }
+                Ok(status) => {
+                    warn!(
+                        "treasury melt {} is still pending: {status:?}",
+                        melt.payment_hash
+                    );
+                    return Err(err);
+                }
+                Err(status_err) => {
+                    warn!(
+                        "payment status of treasury melt {} unknown: {status_err}",
+                        melt.payment_hash
+                    );
+                    return Err(err);
+                }
+            },
+        };
+        self.settle_treasury_melt(&melt, total_fees).await?;
+        Ok(PostTreasuryMeltResponse {
+            payment_hash: melt.payment_hash,
+            amount: melt.amount,
+            fee: total_fees,
+        })
+    }
+
+    /// Debits the paid invoice and the lightning fees, the rest of the fee reserve is released
+    async fn settle_treasury_melt(
+        &self,
+        melt: &TreasuryMelt,
+        total_fees: u64,
+    ) -> Result<(), MokshaMintError> {
+        let mut tx = self.db.begin_tx().await?;
+        self.db
+            .get_bitcredit_request_to_mint_for_update(&mut tx, &melt.bill_id)
+            .await?;
+        self.db
+            .delete_treasury_melt(&mut tx, &melt.payment_hash)
+            .await?;
+        self.debit_treasury(
+            &mut tx,
+            &melt.bill_id,
+            melt.amount + total_fees,
+            TreasuryEntryKind::Melted,
+            Some(melt.payment_hash.clone()),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn release_treasury_melt(&self, melt: &TreasuryMelt) -> Result<(), MokshaMintError> {
+        let mut tx = self.db.begin_tx().await?;
+        self.db
+            .delete_treasury_melt(&mut tx, &melt.payment_hash)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Locks the treasury of the bill until the transaction ends and returns its balance.
+    /// Concurrent withdrawals and melts of the bill wait here, so they can not overdraw it.
+    async fn lock_treasury_balance(
+        &self,
+        tx: &mut Transaction<'_, <DB as Database>::DB>,
+        bill_id: &str,
+    ) -> Result<u64, MokshaMintError> {
+        self.db
+            .get_bitcredit_request_to_mint_for_update(tx, bill_id)
+            .await?;
+        let entries = self.db.get_treasury_entries(tx).await?;
+        let melts = self.db.get_treasury_melts(tx).await?;
+        Ok(treasury_balances(&entries, &melts)
+            .into_iter()
+            .find(|balance| balance.bill_id == bill_id)
+            .map_or(0, |balance| balance.balance))
+    }
+
+    /// Records the debit and retires treasury proofs of the bill worth `amount`. Returns the
+    /// amount that was covered by treasury proofs.
+    async fn debit_treasury(
+        &self,
+        tx: &mut Transaction<'_, <DB as Database>::DB>,
+        bill_id: &str,
+        amount: u64,
+        kind: TreasuryEntryKind,
+        reference: Option<String>,
+    ) -> Result<u64, MokshaMintError> {
+        self.db
+            .add_treasury_entry(
+                tx,
+                &TreasuryEntry {
+                    bill_id: bill_id.to_owned(),
+                    kind,
+                    amount,
+                    reference,
+                    timestamp: self.clock.now().await?,
+                },
+            )
+            .await?;
+
+        let mut retired = vec![];
+        for proof in self.db.get_treasury_proofs(tx, bill_id).await?.proofs() {
+            if retired.iter().map(|p: &Proof| p.amount).sum::<u64>() >= amount {
+                break;
+            }
+            retired.push(proof);
+        }
+        let retired = Proofs::new(retired);
+        let retired_amount = retired.total_amount();
+        if retired_amount == 0 {
+            return Ok(0);
+        }
+        self.db.delete_treasury_proofs(tx, &retired).await?;
+        if retired_amount > amount {
+            let keyset = self.bill_keyset(bill_id).await?.keyset;
+            let change = self.issue_proofs(retired_amount - amount, &keyset)?;
+            self.db.add_treasury_proofs(tx, bill_id, &change).await?;
+        }
+        Ok(retired_amount.min(amount))
+    }
+
+    /// Checks that the invoice of the quote is paid, that the outputs do not exceed the invoice
+    /// amount in the unit of the quote and that no tokens have been issued for the quote yet. Paid
+    /// quotes can be minted after they expired.
+    async fn verify_bolt11_mint_quote(
+        &self,
+        tx: &mut Transaction<'_, <DB as Database>::DB>,
+        quote_id: &Uuid,
+        outputs: &[BlindedMessage],
+    ) -> Result<Bolt11MintQuote, MokshaMintError> {
+        let quote = self.db.get_bolt11_mint_quote(tx, quote_id).await?;
+        // the pending invoice is deleted once the tokens are issued
+        let invoice = match self.db.get_pending_invoice(tx, quote_id.to_string()).await {
+            Err(MokshaMintError::Db(sqlx::Error::RowNotFound)) => {
+                return Err(MokshaMintError::QuoteAlreadyIssued(*quote_id))
+            }
+            invoice => invoice?,
+        };
+
+        let amount = outputs.iter().map(|output| output.amount).sum::<u64>();
+        let invoice_amount = msat_to_unit(&quote.unit, invoice.amount * 1_000)?;
+        if amount > invoice_amount {
+            return Err(MokshaMintError::QuoteAmountExceeded(amount, invoice_amount));
+        }
+
+        if !self
+            .lightning
+            .is_invoice_paid(invoice.payment_request.clone())
+            .await?
+        {
+            if quote.expiry < self.clock.now().await? {
+                return Err(MokshaMintError::QuoteExpired(*quote_id));
+            }
+            return Err(MokshaMintError::InvoiceNotPaidYet);
+        }
+
+        // the delete locks the invoice, a concurrent mint of the same quote fails here
+        match self
+            .db
+            .delete_pending_invoice(tx, quote_id.to_string())
+            .await
+        {
+            Err(MokshaMintError::Db(sqlx::Error::RowNotFound)) => {
+                Err(MokshaMintError::QuoteAlreadyIssued(*quote_id))
+            }
+            result => result.map(|_| quote),
+        }
+    }
+
+    fn has_duplicate_pubkeys(outputs: &[BlindedMessage]) -> bool {
+        let mut uniq = HashSet::new();
+        !outputs.iter().all(move |x| uniq.insert(x.b_))
+    }
+
+    #[instrument(level = "debug", skip_all, err)]
+    pub async fn swap(
+        &self,
+        proofs: &Proofs,
+        blinded_messages: &[BlindedMessage],
+        keyset: &MintKeyset,
+    ) -> Result<Vec<BlindedSignature>, MokshaMintError> {
+        let mut tx = self.db.begin_tx().await?;
+        self.verify_proofs(&mut tx, proofs).await?;
+        self.verify_unit(&mut tx, proofs, blinded_messages, keyset)
+            .await?;
+        let promises = self
+            .exchange(&mut tx, proofs, blinded_messages, keyset)
+            .await?;
+        tx.commit().await?;
+        Ok(promises)
+    }
+
+    /// Redeems `cr-sat` proofs of matured bills for signatures of the active `sat` keyset. The
+    /// drawee must have paid the bills, unpaid bills are handled by the configured default policy.
+    pub async fn redeem_bitcredit(
+        &self,
+        proofs: &Proofs,
+        blinded_messages: &[BlindedMessage],
+    ) -> Result<Vec<BlindedSignature>, MokshaMintError> {
+        let keyset = self.keyset();
+        let mut bill_keysets = HashMap::new();
+        for proof in proofs.proofs() {
+            if !bill_keysets.contains_key(&proof.keyset_id) {
+                let bill_keyset = self
+                    .bill_keysets
+                    .read()
+                    .await
+                    .get(&proof.keyset_id)
+                    .cloned()
+                    .ok_or_else(|| {
+                        MokshaMintError::UnitMismatch(CurrencyUnit::CrSat.to_string())
+                    })?;
+                bill_keysets.insert(proof.keyset_id.clone(), bill_keyset);
+            }
+        }
+        if blinded_messages
+            .iter()
+            .any(|output| output.id != keyset.keyset_id)
+        {
+            return Err(MokshaMintError::UnitMismatch(CurrencyUnit::Sat.to_string()));
+        }
+        for bill_keyset in bill_keysets.values() {
+            self.verify_redeemable(bill_keyset).await?;
+        }
+
+        let mut tx = self.db.begin_tx().await?;
+        self.verify_proofs(&mut tx, proofs).await?;
+        let promises = self
+            .exchange(&mut tx, proofs, blinded_messages, &keyset)
+            .await?;
+        tx.commit().await?;
+        Ok(promises)
+    }
+
+    /// Checks that the bill is mature and paid. A bill that is still unpaid after the grace period
+    /// is marked as defaulted, its tokens are only redeemed if the default policy allows it.
+    async fn verify_redeemable(&self, bill_keyset: &BillKeyset) -> Result<(), MokshaMintError> {
+        let bill_id = &bill_keyset.bill_id;
+        if !self.is_mature(bill_keyset.maturity_date).await? {
+            return Err(MokshaMintError::BillNotMature(bill_id.clone()));
+        }
+
+        let keyset_id = &bill_keyset.keyset.keyset_id;
+        let mut tx = self.db.begin_tx().await?;
+        let mut status = self.db.get_bill_keyset_status(&mut tx, keyset_id).await?;
+        if status == BillKeysetStatus::Active {
+            let grace_period_end =
+                bill_keyset.maturity_date + self.config.redemption.grace_period_secs as i64;
+            status = if self.bill_registry.get_bill(bill_id).await?.paid {
+                BillKeysetStatus::Settled
+            } else if !self.is_mature(grace_period_end).await? {
+                return Err(MokshaMintError::BillNotPaid(bill_id.clone()));
+            } else {
+                BillKeysetStatus::Defaulted
+            };
+            self.db
+                .update_bill_keyset_status(&mut tx, keyset_id, status)
+                .await?;
+        }
+        tx.commit().await?;
+
+        match (status, self.config.redemption.default_policy) {
+            (BillKeysetStatus::Defaulted, DefaultPolicy::Reject) => {
+                Err(MokshaMintError::BillDefaulted(bill_id.clone()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Spends the verified proofs and signs the outputs with `keyset`
+    async fn exchange(
+        &self,
+        tx: &mut Transaction<'_, <DB as Database>::DB>,
+        proofs: &Proofs,
+        blinded_messages: &[BlindedMessage],
+        keyset: &MintKeyset,
+    ) -> Result<Vec<BlindedSignature>, MokshaMintError> {
+        verify_spending_conditions(proofs, blinded_messages, self.clock.now().await?)?;
+        self.check_used_proofs(tx, proofs).await?;
+        self.verify_outputs_keyset(tx, blinded_messages).await?;
+
+        if Self::has_duplicate_pubkeys(blinded_messages) {
+            return Err(MokshaMintError::SwapHasDuplicatePromises);
         }
 
-        // TODO check invoice
+        let sum_proofs = proofs.total_amount();
+        let fee = self.input_fee(tx, proofs).await?;
+
+        let promises = self.create_blinded_signatures(blinded_messages, keyset)?;
+        let amount_promises = promises.total_amount();
+        if sum_proofs.checked_sub(fee) != Some(amount_promises) {
+            return Err(MokshaMintError::SwapAmountMismatch(format!(
+                "Swap amount mismatch: {sum_proofs} - {fee} (fee) != {amount_promises}"
+            )));
+        }
 
-        let result = self.lightning.pay_invoice(payment_request).await?;
         self.db.add_used_proofs(tx, proofs).await?;
+        self.db
+            .add_promises(tx, blinded_messages, &promises)
+            .await?;
+        Ok(promises)
+    }
+
+    /// Pays the invoice of the quote with the proofs and returns the change for the unused fee
+    /// reserve.
+    ///
+    /// The quote must not be expired or paid and the proofs have to cover the quote amount, the
+    /// fee reserve and the input fee. The proofs are pending while the invoice is being paid.
+    /// They are spent if the payment succeeds and released if it fails. If the backend can not
+    /// tell whether the payment went through, the proofs stay pending until
+    /// [`Mint::reconcile_pending_melts`] resolves them.
+    #[instrument(level = "debug", skip(self, proofs, blinded_messages, keyset), err)]
+    pub async fn melt_bolt11(
+        &self,
+        quote_id: &Uuid,
+        proofs: &Proofs,
+        blinded_messages: Option<Vec<BlindedMessage>>,
+        keyset: &MintKeyset,
+    ) -> Result<(bool, String, Vec<BlindedSignature>), MokshaMintError> {
+        let mut tx = self.db.begin_tx().await?;
+        // concurrent melts of the same quote wait here until the inputs are reserved
+        let quote = self
+            .db
+            .get_bolt11_melt_quote_for_update(&mut tx, quote_id)
+            .await?;
+        if quote.paid {
+            return Err(MokshaMintError::QuoteAlreadyPaid(quote.quote_id));
+        }
+        if !self
+            .db
+            .get_pending_proofs_by_quote(&mut tx, &quote.quote_id)
+            .await?
+            .is_empty()
+        {
+            return Err(MokshaMintError::QuotePending(quote.quote_id));
+        }
+        if quote.expiry < self.clock.now().await? {
+            return Err(MokshaMintError::QuoteExpired(quote.quote_id));
+        }
+
+        let payment_request = quote.payment_request.clone();
+        let fee_reserve = quote.fee_reserve;
+        let invoice = self
+            .lightning
+            .decode_invoice(payment_request.clone())
+            .await?;
+        let amount_msat = invoice
+            .amount_milli_satoshis()
+            .ok_or_else(|| MokshaMintError::InvalidAmount("invoice has no amount".to_owned()))?;
+        if msat_to_unit(&quote.unit, amount_msat)? != quote.amount {
+            return Err(MokshaMintError::InvalidQuote(format!(
+                "invoice amount {amount_msat} msat does not match quote amount {} {}",
+                quote.amount, quote.unit
+            )));
+        }
+
+        self.verify_proofs(&mut tx, proofs).await?;
+        let outputs = blinded_messages.as_deref().unwrap_or_default();
+        if self.keyset_unit(&mut tx, &keyset.keyset_id).await? != quote.unit {
+            return Err(MokshaMintError::UnitMismatch(quote.unit.to_string()));
+        }
+        self.verify_unit(&mut tx, proofs, outputs, keyset).await?;
+        verify_spending_conditions(proofs, outputs, self.clock.now().await?)?;
+        self.check_used_proofs(&mut tx, proofs).await?;
+
+        let fee = self.input_fee(&mut tx, proofs).await?;
+        let required_amount = quote.amount + fee_reserve + fee;
+        if proofs.total_amount() < required_amount {
+            return Err(MokshaMintError::NotEnoughTokens(required_amount));
+        }
+
+        self.reserve_melt_inputs(tx, &quote.quote_id, proofs)
+            .await?;
+
+        let result = match self.lightning.pay_invoice(payment_request.clone()).await {
+            Ok(result) => result,
+            Err(err) => match self.lightning.payment_status(payment_request).await {
+                Ok(PaymentStatus::Paid { total_fees }) => PayInvoiceResult {
+                    payment_hash: invoice.payment_hash().to_string(),
+                    total_fees,
+                },
+                Ok(PaymentStatus::Failed) => {
+                    self.release_melt_inputs(&quote.quote_id).await?;
+                    return Err(err);
+                }
+                // the payment may still settle, the inputs stay pending until it is reconciled
+                Ok(PaymentStatus::Pending) => return Err(err),
+                Ok(PaymentStatus::Unknown) => {
+                    warn!(
+                        "payment status of melt {} unknown, inputs stay pending",
+                        quote.quote_id
+                    );
+                    return Err(err);
+                }
+                Err(status_err) => {
+                    warn!(
+                        "payment status of melt {} unknown: {status_err}",
+                        quote.quote_id
+                    );
+                    return Err(err);
+                }
+            },
+        };
+
+        let mut tx = self.db.begin_tx().await?;
+        self.db
+            .delete_pending_proofs(&mut tx, &quote.quote_id)
+            .await?;
 
         let change = match blinded_messages {
             Some(blinded_messages) => {
                 if fee_reserve > 0 {
-                    let return_fees = Amount(fee_reserve - result.total_fees).split();
+                    let total_fees = msat_to_unit(&quote.unit, result.total_fees * 1_000)?;
+                    if total_fees > fee_reserve {
+                        warn!(
+                            "fees of melt {} exceed the fee reserve: {total_fees} > {fee_reserve}",
+                            quote.quote_id
+                        );
+                    }
+                    let return_fees = Amount(fee_reserve.saturating_sub(total_fees)).split();
 
                     if (return_fees.len()) > blinded_messages.len() {
                         // FIXME better handle case when there are more fees than blinded messages
@@ -278,7 +1478,9 @@ where
                             })
                             .collect();
 
-                        self.create_blinded_signatures(&out, keyset)?
+                        let promises = self.create_blinded_signatures(&out, keyset)?;
+                        self.db.add_promises(&mut tx, &out, &promises).await?;
+                        promises
                     }
                 } else {
                     vec![]
@@ -288,56 +1490,526 @@ where
                 vec![]
             }
         };
+        self.db
+            .update_bolt11_melt_quote(
+                &mut tx,
+                &Bolt11MeltQuote {
+                    paid: true,
+                    ..quote.clone()
+                },
+            )
+            .await?;
+        tx.commit().await?;
+
         Ok((true, result.payment_hash, change))
     }
 
-    pub async fn check_used_proofs(
+    /// Reserves the inputs of a melt before the backend pays the quote.
+    ///
+    /// The proofs are added to the used proofs, so a concurrent swap or melt can not spend them,
+    /// and to the pending proofs of the quote, so their Nut-07 state is `PENDING`. The
+    /// transaction is committed before the payment starts, so the reservation survives a restart.
+    async fn reserve_melt_inputs(
         &self,
-        tx: &mut Transaction<'_, <DB as Database>::DB>,
+        mut tx: Transaction<'_, <DB as Database>::DB>,
+        quote_id: &Uuid,
         proofs: &Proofs,
     ) -> Result<(), MokshaMintError> {
-        let used_proofs = self.db.get_used_proofs(tx).await?.proofs();
-        for used_proof in used_proofs {
-            if proofs.proofs().contains(&used_proof) {
-                return Err(MokshaMintError::ProofAlreadyUsed(format!("{used_proof:?}")));
-            }
-        }
+        self.db.add_used_proofs(&mut tx, proofs).await?;
+        self.db
+            .add_pending_proofs(&mut tx, proofs, quote_id)
+            .await?;
+        tx.commit().await?;
         Ok(())
     }
 
-    #[instrument(level = "debug", skip(self, proofs), err)]
-    pub async fn melt_onchain(
-        &self,
-        quote: &BtcOnchainMeltQuote,
-        proofs: &Proofs,
-    ) -> Result<String, MokshaMintError> {
-        let proofs_amount = proofs.total_amount();
-
-        if proofs_amount < quote.amount {
-            return Err(MokshaMintError::NotEnoughTokens(quote.amount));
-        }
-
+    /// Makes the pending inputs of a failed melt spendable again
+    async fn release_melt_inputs(&self, quote_id: &Uuid) -> Result<(), MokshaMintError> {
         let mut tx = self.db.begin_tx().await?;
-        self.check_used_proofs(&mut tx, proofs).await?;
-
-        let send_response = self
-            .onchain
-            .as_ref()
-            .expect("onchain backend not set")
-            .send_coins(&quote.address, quote.amount, quote.fee_sat_per_vbyte)
+        let proofs = self
+            .db
+            .get_pending_proofs_by_quote(&mut tx, quote_id)
             .await?;
-
-        self.db.add_used_proofs(&mut tx, proofs).await?;
+        self.db.delete_used_proofs(&mut tx, &proofs).await?;
+        self.db.delete_pending_proofs(&mut tx, quote_id).await?;
         tx.commit().await?;
-
-        Ok(send_response.txid)
+        Ok(())
     }
-}
 
-#[derive(Debug, Default)]
-pub struct MintBuilder {
+    /// Resolves melts that were interrupted while the backend paid the quote, e.g. because the
+    /// mint crashed. The inputs of paid quotes are spent and the inputs of failed payments are
+    /// released. The change for the fee reserve of these melts is not returned.
+    ///
+    /// On-chain melts are looked up by their label in the wallet of the backend. Payments in an
+    /// unknown state stay pending and have to be checked by the operator. Payments from the
+    /// treasury are settled or released the same way.
+    pub async fn reconcile_pending_melts(&self) -> Result<(), MokshaMintError> {
+        let mut tx = self.db.begin_tx().await?;
+        let quote_ids = self.db.get_pending_quote_ids(&mut tx).await?;
+        tx.commit().await?;
+
+        for quote_id in quote_ids {
+            let mut tx = self.db.begin_tx().await?;
+            let quote = self.db.get_bolt11_melt_quote(&mut tx, &quote_id).await;
+            tx.commit().await?;
+
+            let result = match quote {
+                Ok(quote) => self.reconcile_bolt11_melt(&quote).await,
+                Err(MokshaMintError::Db(sqlx::Error::RowNotFound)) => {
+                    let mut tx = self.db.begin_tx().await?;
+                    let quote = self.db.get_onchain_melt_quote(&mut tx, &quote_id).await;
+                    tx.commit().await?;
+                    match quote {
+                        Ok(quote) => self.reconcile_onchain_melt(&quote).await,
+                        Err(err) => Err(err),
+                    }
+                }
+                Err(err) => return Err(err),
+            };
+            if let Err(err) = result {
+                warn!("failed to reconcile melt {quote_id}: {err}");
+            }
+        }
+
+        let mut tx = self.db.begin_tx().await?;
+        let treasury_melts = self.db.get_treasury_melts(&mut tx).await?;
+        tx.commit().await?;
+        for melt in treasury_melts {
+            if let Err(err) = self.reconcile_treasury_melt(&melt).await {
+                warn!(
+                    "failed to reconcile treasury melt {}: {err}",
+                    melt.payment_hash
+                );
+            }
+        }
+        Ok(())
+    }
+
+    async fn reconcile_treasury_melt(&self, melt: &TreasuryMelt) -> Result<(), MokshaMintError> {
+        match self
+            .lightning
+            .payment_status(melt.payment_request.clone())
+            .await?
+        {
+            PaymentStatus::Paid { total_fees } => {
+                self.settle_treasury_melt(melt, total_fees).await?;
+                info!("treasury melt {} was paid", melt.payment_hash);
+            }
+            PaymentStatus::Failed => {
+                self.release_treasury_melt(melt).await?;
+                info!(
+                    "treasury melt {} failed, reserve is released",
+                    melt.payment_hash
+                );
+            }
+            status => warn!(
+                "treasury melt {} is still pending: {status:?}",
+                melt.payment_hash
+            ),
+        }
+        Ok(())
+    }
+
+    async fn reconcile_onchain_melt(
+        &self,
+        quote: &BtcOnchainMeltQuote,
+    ) -> Result<(), MokshaMintError> {
+        let Some(onchain) = self.onchain.as_ref() else {
+            warn!(
+                "onchain melt {} is pending, but no onchain backend is configured",
+                quote.quote_id
+            );
+            return Ok(());
+        };
+
+        match onchain
+            .find_sent_transaction(&quote.quote_id.to_string())
+            .await?
+        {
+            Some(txid) => {
+                let mut tx = self.db.begin_tx().await?;
+                self.db
+                    .delete_pending_proofs(&mut tx, &quote.quote_id)
+                    .await?;
+                tx.commit().await?;
+                info!(
+                    "onchain melt {} was sent in {txid}, inputs are spent",
+                    quote.quote_id
+                );
+            }
+            None => {
+                self.release_melt_inputs(&quote.quote_id).await?;
+                info!(
+                    "onchain melt {} was not sent, inputs are released",
+                    quote.quote_id
+                );
+            }
+        }
+        Ok(())
+    }
+
+    async fn reconcile_bolt11_melt(&self, quote: &Bolt11MeltQuote) -> Result<(), MokshaMintError> {
+        match self
+            .lightning
+            .payment_status(quote.payment_request.clone())
+            .await?
+        {
+            PaymentStatus::Paid { .. } => {
+                let mut tx = self.db.begin_tx().await?;
+                self.db
+                    .delete_pending_proofs(&mut tx, &quote.quote_id)
+                    .await?;
+                self.db
+                    .update_bolt11_melt_quote(
+                        &mut tx,
+                        &Bolt11MeltQuote {
+                            paid: true,
+                            ..quote.clone()
+                        },
+                    )
+                    .await?;
+                tx.commit().await?;
+                info!("melt {} was paid, inputs are spent", quote.quote_id);
+            }
+            PaymentStatus::Failed => {
+                self.release_melt_inputs(&quote.quote_id).await?;
+                info!("melt {} failed, inputs are released", quote.quote_id);
+            }
+            status => warn!("melt {} is still pending: {status:?}", quote.quote_id),
+        }
+        Ok(())
+    }
+
+    /// Checks that every proof carries a valid signature of the keyset it claims to be from.
+    #[instrument(level = "debug", skip_all, err)]
+    pub async fn verify_proofs(
+        &self,
+        tx: &mut Transaction<'_, <DB as Database>::DB>,
+        proofs: &Proofs,
+    ) -> Result<(), MokshaMintError> {
+        let keysets = self.find_keysets(tx, proofs).await?;
+        for proof in proofs.proofs() {
+            let private_key = keysets[&proof.keyset_id]
+                .private_keys
+                .get(&proof.amount)
+                .ok_or_else(|| {
+                    MokshaMintError::InvalidProof(format!(
+                        "amount {} not found in keyset {}",
+                        proof.amount, proof.keyset_id
+                    ))
+                })?;
+
+            if !self
+                .dhke
+                .verify(*private_key, proof.c, proof.secret.clone())?
+            {
+                return Err(MokshaMintError::InvalidProof(format!(
+                    "invalid signature for secret {}",
+                    proof.secret
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the fee for spending the proofs, based on the `input_fee_ppk` of their keysets (Nut-02)
+    pub async fn input_fee(
+        &self,
+        tx: &mut Transaction<'_, <DB as Database>::DB>,
+        proofs: &Proofs,
+    ) -> Result<u64, MokshaMintError> {
+        let keysets = self.find_keysets(tx, proofs).await?;
+        let sum_fee_ppk = proofs
+            .proofs()
+            .iter()
+            .map(|proof| keysets[&proof.keyset_id].input_fee_ppk)
+            .sum();
+        Ok(input_fee(sum_fee_ppk))
+    }
+
+    async fn find_keysets(
+        &self,
+        tx: &mut Transaction<'_, <DB as Database>::DB>,
+        proofs: &Proofs,
+    ) -> Result<HashMap<String, MintKeyset>, MokshaMintError> {
+        let mut keysets: HashMap<String, MintKeyset> = HashMap::new();
+        for proof in proofs.proofs() {
+            if !keysets.contains_key(&proof.keyset_id) {
+                let keyset = self.find_keyset(tx, &proof.keyset_id).await?;
+                keysets.insert(proof.keyset_id.clone(), keyset);
+            }
+        }
+        Ok(keysets)
+    }
+
+    /// Resolves a keyset id to one of the mint keysets (active or inactive) or to the keyset of a
+    /// bitcredit bill.
+    async fn find_keyset(
+        &self,
+        tx: &mut Transaction<'_, <DB as Database>::DB>,
+        keyset_id: &str,
+    ) -> Result<MintKeyset, MokshaMintError> {
+        if let Some(keyset) = self.find_mint_keyset(tx, keyset_id).await? {
+            return Ok(keyset);
+        }
+        self.bill_keysets
+            .read()
+            .await
+            .get(keyset_id)
+            .map(|bill_keyset| bill_keyset.keyset.clone())
+            .ok_or_else(|| MokshaMintError::KeysetNotFound(keyset_id.to_owned()))
+    }
+
+    /// Rejects proofs that are spent already or that are used twice in the same request.
+    /// Proofs spent by a concurrent transaction are rejected when they are added to the used proofs.
+    pub async fn check_used_proofs(
+        &self,
+        tx: &mut Transaction<'_, <DB as Database>::DB>,
+        proofs: &Proofs,
+    ) -> Result<(), MokshaMintError> {
+        let mut secrets = HashSet::new();
+        for proof in proofs.proofs() {
+            if !secrets.insert(proof.secret.clone()) {
+                return Err(MokshaMintError::ProofAlreadyUsed(format!("{proof:?}")));
+            }
+        }
+
+        let secrets = secrets.into_iter().collect::<Vec<_>>();
+        if let Some(used_proof) = self
+            .db
+            .get_used_proofs_by_secrets(tx, &secrets)
+            .await?
+            .proofs()
+            .first()
+        {
+            // the inputs of a melt in flight are used and pending at the same time
+            if !self
+                .db
+                .get_pending_proofs_by_ys(tx, &[used_proof.y()?])
+                .await?
+                .is_empty()
+            {
+                return Err(MokshaMintError::ProofPending(format!("{used_proof:?}")));
+            }
+            return Err(MokshaMintError::ProofAlreadyUsed(format!("{used_proof:?}")));
+        }
+        Ok(())
+    }
+
+    /// Returns the stored signatures for all outputs the mint has already signed (Nut-09)
+    #[instrument(level = "debug", skip_all, err)]
+    pub async fn restore(
+        &self,
+        outputs: &[BlindedMessage],
+    ) -> Result<(Vec<BlindedMessage>, Vec<BlindedSignature>), MokshaMintError> {
+        let b_s = outputs.iter().map(|output| output.b_).collect::<Vec<_>>();
+        let mut tx = self.db.begin_tx().await?;
+        let promises = self
+            .db
+            .get_promises(&mut tx, &b_s)
+            .await?
+            .into_iter()
+            .map(|(output, promise)| (output.b_, (output, promise)))
+            .collect::<HashMap<_, _>>();
+        tx.commit().await?;
+
+        Ok(b_s
+            .iter()
+            .filter_map(|b_| promises.get(b_).cloned())
+            .unzip())
+    }
+
+    /// Returns the Nut-07 state of the proofs identified by their `Y` points
+    #[instrument(level = "debug", skip_all, err)]
+    pub async fn check_state(&self, ys: &[PublicKey]) -> Result<Vec<ProofState>, MokshaMintError> {
+        let mut tx = self.db.begin_tx().await?;
+        let spent = self
+            .db
+            .get_used_proofs_by_ys(&mut tx, ys)
+            .await?
+            .proofs()
+            .iter()
+            .map(|proof| proof.y())
+            .collect::<Result<HashSet<PublicKey>, _>>()?;
+        let pending = self
+            .db
+            .get_pending_proofs_by_ys(&mut tx, ys)
+            .await?
+            .proofs()
+            .iter()
+            .map(|proof| proof.y())
+            .collect::<Result<HashSet<PublicKey>, _>>()?;
+        tx.commit().await?;
+
+        Ok(ys
+            .iter()
+            .map(|y| ProofState {
+                y: *y,
+                state: if pending.contains(y) {
+                    State::Pending
+                } else if spent.contains(y) {
+                    State::Spent
+                } else {
+                    State::Unspent
+                },
+                witness: None,
+            })
+            .collect())
+    }
+
+    /// Sends the amount of the quote on-chain. The proofs are pending while the coins are sent
+    /// and released if the backend fails to send them.
+    #[instrument(level = "debug", skip(self, proofs), err)]
+    pub async fn melt_onchain(
+        &self,
+        quote: &BtcOnchainMeltQuote,
+        proofs: &Proofs,
+    ) -> Result<String, MokshaMintError> {
+        let proofs_amount = proofs.total_amount();
+
+        let mut tx = self.db.begin_tx().await?;
+        self.verify_proofs(&mut tx, proofs).await?;
+        self.verify_unit(&mut tx, proofs, &[], &self.keyset())
+            .await?;
+
+        let required_amount = quote.amount + self.input_fee(&mut tx, proofs).await?;
+        if proofs_amount < required_amount {
+            return Err(MokshaMintError::NotEnoughTokens(required_amount));
+        }
+        verify_spending_conditions(proofs, &[], self.clock.now().await?)?;
+        self.check_used_proofs(&mut tx, proofs).await?;
+
+        self.reserve_melt_inputs(tx, &quote.quote_id, proofs)
+            .await?;
+
+        let onchain = self.onchain.as_ref().expect("onchain backend not set");
+        let label = quote.quote_id.to_string();
+        let txid = match onchain
+            .send_coins(
+                &quote.address,
+                quote.amount,
+                quote.fee_sat_per_vbyte,
+                &label,
+            )
+            .await
+        {
+            Ok(send_response) => send_response.txid,
+            // the transaction may have been published although the backend returned an error, the
+            // inputs are only released if the wallet has not sent a transaction for the quote
+            Err(err) => match onchain.find_sent_transaction(&label).await {
+                Ok(Some(txid)) => {
+                    warn!(
+                        "onchain melt {} was sent in {txid} despite error: {err}",
+                        quote.quote_id
+                    );
+                    txid
+                }
+                Ok(None) => {
+                    self.release_melt_inputs(&quote.quote_id).await?;
+                    return Err(err);
+                }
+                Err(lookup_err) => {
+                    warn!(
+                        "onchain melt {} stays pending, the wallet can not be checked: {lookup_err}",
+                        quote.quote_id
+                    );
+                    return Err(err);
+                }
+            },
+        };
+
+        let mut tx = self.db.begin_tx().await?;
+        self.db
+            .delete_pending_proofs(&mut tx, &quote.quote_id)
+            .await?;
+        tx.commit().await?;
+
+        Ok(txid)
+    }
+}
+
+/// Converts an amount of the unit to msat. Only `sat` and `msat` have a fixed conversion, tokens of
+/// other units can not be minted or melted over lightning.
+pub fn unit_to_msat(unit: &CurrencyUnit, amount: u64) -> Result<u64, MokshaMintError> {
+    match unit {
+        CurrencyUnit::Sat => Ok(amount * 1_000),
+        CurrencyUnit::MSat => Ok(amount),
+        unit => Err(MokshaMintError::CurrencyNotSupported(unit.to_string())),
+    }
+}
+
+/// Converts msat to an amount of the unit, rounded up
+pub fn msat_to_unit(unit: &CurrencyUnit, amount_msat: u64) -> Result<u64, MokshaMintError> {
+    match unit {
+        CurrencyUnit::Sat => Ok(amount_msat.div_ceil(1_000)),
+        CurrencyUnit::MSat => Ok(amount_msat),
+        unit => Err(MokshaMintError::CurrencyNotSupported(unit.to_string())),
+    }
+}
+
+/// Keysets of the `sat` unit use the configured derivation path, keysets of the other units are
+/// derived from a separate path per unit.
+fn unit_derivation_path(config: &MintConfig, unit: &CurrencyUnit) -> String {
+    let base = config.derivation_path.clone().unwrap_or_default();
+    match unit {
+        CurrencyUnit::Sat => base,
+        unit => format!("{base}/{unit}"),
+    }
+}
+
+/// Time the holder of a bill has to endorse the bill to the mint after requesting a quote
+pub const BITCREDIT_QUOTE_EXPIRY_SECS: u64 = 24 * 60 * 60;
+
+/// Keysets of bills are identified by `00` + bill id
+pub fn bill_keyset_id(bill_id: &str) -> String {
+    format!("00{bill_id}")
+}
+
+/// Sums up the ledger entries and the reservations of unsettled melts per bill, ordered by bill
+/// id
+fn treasury_balances(entries: &[TreasuryEntry], melts: &[TreasuryMelt]) -> Vec<TreasuryBalance> {
+    fn balance<'a>(
+        balances: &'a mut BTreeMap<String, TreasuryBalance>,
+        bill_id: &str,
+    ) -> &'a mut TreasuryBalance {
+        balances
+            .entry(bill_id.to_owned())
+            .or_insert_with(|| TreasuryBalance {
+                bill_id: bill_id.to_owned(),
+                ..Default::default()
+            })
+    }
+
+    let mut balances = BTreeMap::new();
+    for entry in entries {
+        let balance = balance(&mut balances, &entry.bill_id);
+        match entry.kind {
+            TreasuryEntryKind::Earned => balance.earned += entry.amount,
+            TreasuryEntryKind::Withdrawn => balance.withdrawn += entry.amount,
+            TreasuryEntryKind::Melted => balance.melted += entry.amount,
+        }
+    }
+    for melt in melts {
+        balance(&mut balances, &melt.bill_id).pending += melt.amount + melt.fee_reserve;
+    }
+    balances
+        .into_values()
+        .map(|balance| TreasuryBalance {
+            balance: balance
+                .earned
+                .saturating_sub(balance.withdrawn + balance.melted + balance.pending),
+            ..balance
+        })
+        .collect()
+}
+
+#[derive(Debug, Default)]
+pub struct MintBuilder {
     private_key: Option<String>,
     derivation_path: Option<String>,
+    input_fee_ppk: u64,
+    units: Vec<CurrencyUnit>,
+    operator_api_key: Option<String>,
     lightning_type: Option<LightningType>,
 
     db_config: Option<DatabaseConfig>,
@@ -346,6 +2018,12 @@ pub struct MintBuilder {
     server_config: Option<ServerConfig>,
     btc_onchain_config: Option<BtcOnchainConfig>,
     tracing_config: Option<TracingConfig>,
+    bill_registry_config: Option<BillRegistryConfig>,
+    clock_config: Option<ClockConfig>,
+    pricing_config: Option<PricingConfig>,
+    treasury_config: Option<TreasuryConfig>,
+    bill_key_encryption_config: Option<BillKeyEncryptionConfig>,
+    redemption_config: Option<RedemptionConfig>,
 }
 
 impl MintBuilder {
@@ -353,6 +2031,9 @@ impl MintBuilder {
         MintBuilder {
             private_key: None,
             derivation_path: None,
+            input_fee_ppk: 0,
+            units: vec![CurrencyUnit::Sat],
+            operator_api_key: None,
             lightning_type: None,
             db_config: None,
             fee_config: None,
@@ -360,6 +2041,12 @@ impl MintBuilder {
             server_config: None,
             btc_onchain_config: None,
             tracing_config: None,
+            bill_registry_config: None,
+            clock_config: None,
+            pricing_config: None,
+            treasury_config: None,
+            bill_key_encryption_config: None,
+            redemption_config: None,
         }
     }
 
@@ -388,6 +2075,21 @@ impl MintBuilder {
         self
     }
 
+    pub const fn with_input_fee_ppk(mut self, input_fee_ppk: u64) -> Self {
+        self.input_fee_ppk = input_fee_ppk;
+        self
+    }
+
+    pub fn with_units(mut self, units: Vec<CurrencyUnit>) -> Self {
+        self.units = units;
+        self
+    }
+
+    pub fn with_operator_api_key(mut self, operator_api_key: Option<String>) -> Self {
+        self.operator_api_key = operator_api_key;
+        self
+    }
+
     pub fn with_lightning(mut self, lightning: LightningType) -> Self {
         self.lightning_type = Some(lightning);
         self
@@ -408,6 +2110,39 @@ impl MintBuilder {
         self
     }
 
+    pub fn with_bill_registry(mut self, bill_registry_config: Option<BillRegistryConfig>) -> Self {
+        self.bill_registry_config = bill_registry_config;
+        self
+    }
+
+    pub fn with_clock(mut self, clock_config: Option<ClockConfig>) -> Self {
+        self.clock_config = clock_config;
+        self
+    }
+
+    pub fn with_pricing(mut self, pricing_config: Option<PricingConfig>) -> Self {
+        self.pricing_config = pricing_config;
+        self
+    }
+
+    pub fn with_treasury(mut self, treasury_config: Option<TreasuryConfig>) -> Self {
+        self.treasury_config = treasury_config;
+        self
+    }
+
+    pub fn with_redemption(mut self, redemption_config: Option<RedemptionConfig>) -> Self {
+        self.redemption_config = redemption_config;
+        self
+    }
+
+    pub fn with_bill_key_encryption(
+        mut self,
+        bill_key_encryption_config: Option<BillKeyEncryptionConfig>,
+    ) -> Self {
+        self.bill_key_encryption_config = bill_key_encryption_config;
+        self
+    }
+
     pub async fn build(self) -> Result<Mint<PostgresDB>, MokshaMintError> {
         let ln: Arc<dyn Lightning + Send + Sync> = match self.lightning_type.clone() {
             Some(LightningType::Lnbits(lnbits_settings)) => Arc::new(LnbitsLightning::new(
@@ -459,11 +2194,21 @@ impl MintBuilder {
                 )),
                 _ => None,
             };
+        let bill_registry_config = self.bill_registry_config.unwrap_or_default();
+        let bill_registry = Arc::new(HttpBillRegistry::new(&bill_registry_config)?);
+        let clock_config = self.clock_config.unwrap_or_default();
+        let clock: Arc<dyn Clock + Send + Sync> = match clock_config.url.clone() {
+            Some(url) => Arc::new(RemoteClock::new(url, &clock_config)?),
+            None => Arc::new(SystemClock),
+        };
+
+        let bill_key_encryption_config = self.bill_key_encryption_config.unwrap_or_default();
+
         let db_config = self.db_config.expect("db-config not set");
         let db = PostgresDB::new(&db_config).await?;
         db.migrate().await;
 
-        Ok(Mint::new(
+        let mut mint = Mint::new(
             ln,
             self.lightning_type
                 .clone()
@@ -473,6 +2218,9 @@ impl MintBuilder {
             MintConfig::new(
                 self.private_key.expect("private-key not set"),
                 self.derivation_path,
+                self.input_fee_ppk,
+                self.units,
+                self.operator_api_key,
                 self.mint_info_settings.unwrap_or_default(),
                 self.fee_config.expect("fee-config not set"),
                 self.server_config.unwrap_or_default(),
@@ -480,275 +2228,2379 @@ impl MintBuilder {
                 self.btc_onchain_config,
                 self.lightning_type,
                 self.tracing_config,
+                bill_registry_config,
+                clock_config,
+                self.pricing_config.unwrap_or_default(),
+                self.treasury_config.unwrap_or_default(),
+                bill_key_encryption_config.clone(),
+                self.redemption_config.unwrap_or_default(),
             ),
             BuildParams::from_env(),
             lnd_onchain,
-        ))
+            bill_registry,
+        );
+        mint.clock = clock;
+        mint.bill_key_encryption = Arc::new(BillKeyEncryption::new(&bill_key_encryption_config)?);
+        if !mint.bill_key_encryption.is_enabled() {
+            warn!("bill keys are stored unencrypted, set MINT_BILL_KEK or MINT_BILL_KEK_FILE");
+        }
+        mint.init_keysets().await?;
+        Ok(mint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::billregistry::error::BillRegistryError;
+    use crate::billregistry::{BillInfo, MockBillRegistry};
+    use crate::btconchain::MockBtcOnchain;
+    use crate::clock::FixedClock;
+    use crate::config::{BillKeyEncryptionConfig, DatabaseConfig, DefaultPolicy, MintConfig};
+    use crate::database::postgres::PostgresDB;
+    use crate::database::Database;
+    use crate::error::MokshaMintError;
+    use crate::keyencryption::BillKeyEncryption;
+    use crate::lightning::error::LightningError;
+    use crate::lightning::{LightningType, MockLightning, PaymentStatus};
+    use crate::mint::{Mint, BITCREDIT_QUOTE_EXPIRY_SECS};
+    use crate::model::{BillKeysetInfo, BillKeysetStatus, Invoice, PayInvoiceResult};
+    use chrono::{Duration, Utc};
+    use moksha_core::amount::Amount;
+    use moksha_core::blind::{BlindedMessage, TotalAmount};
+    use moksha_core::dhke;
+    use moksha_core::fixture::read_fixture_as;
+    use moksha_core::primitives::{
+        BillKeys, BitcreditMintQuote, BitcreditMintQuoteState, BitcreditQuoteCheck,
+        BitcreditRequestToMint, Bolt11MeltQuote, Bolt11MintQuote, BtcOnchainMeltQuote,
+        CurrencyUnit, ErrorCode, PostRequestToMintBitcreditRequest, PostSwapRequest, State,
+        TreasuryBalance, TreasuryEntryKind,
+    };
+    use moksha_core::proof::{Proof, Proofs};
+    use moksha_core::secret::{SecretKind, WellKnownSecret, Witness};
+    use moksha_core::token::TokenV3;
+    use pretty_assertions::assert_eq;
+    use secp256k1::{PublicKey, Secp256k1, SecretKey};
+    use std::collections::HashSet;
+    use std::str::FromStr;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use testcontainers::clients::Cli;
+    use testcontainers::RunnableImage;
+    use testcontainers_modules::postgres::Postgres;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_fee_reserve() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let mint = create_mint_from_mocks(
+            create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+            None,
+        )
+        .await?;
+        let fee = mint.fee_reserve_msat(10000);
+        assert_eq!(4000, fee);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_create_blindsignatures() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let mint = create_mint_from_mocks(
+            create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+            None,
+        )
+        .await?;
+
+        let blinded_messages = vec![BlindedMessage {
+            amount: 8,
+            b_: dhke::public_key_from_hex(
+                "02634a2c2b34bec9e8a4aba4361f6bf202d7fa2365379b0840afe249a7a9d71239",
+            ),
+            id: "00ffd48b8f5ecf80".to_owned(),
+            witness: None,
+        }];
+
+        let result = mint.create_blinded_signatures(&blinded_messages, &mint.keyset())?;
+
+        assert_eq!(1, result.len());
+        assert_eq!(8, result[0].amount);
+        assert_eq!(
+            dhke::public_key_from_hex(
+                "037074c4f53e326ee14ed67125f387d160e0e729351471b69ad41f7d5d21071e15"
+            ),
+            result[0].c_
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mint_empty() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let mut lightning = MockLightning::new();
+        lightning.expect_is_invoice_paid().returning(|_| Ok(true));
+        let mint = create_mint_from_mocks(
+            create_mock_db_pending_invoice(node.get_host_port_ipv4(5432), 3600).await?,
+            Some(lightning),
+        )
+        .await?;
+
+        let mut tx = mint.db.begin_tx().await?;
+        let outputs = vec![];
+        let result = mint
+            .mint_tokens(
+                &mut tx,
+                moksha_core::primitives::PaymentMethod::Bolt11,
+                MINT_QUOTE_ID.to_string(),
+                &outputs,
+                &mint.keyset(),
+            )
+            .await?;
+        assert!(result.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bolt11_quotes_use_keyset_of_their_unit() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let mut lightning = create_mock_lightning();
+        lightning.expect_is_invoice_paid().returning(|_| Ok(true));
+        let db = create_mock_db_empty(node.get_host_port_ipv4(5432)).await?;
+        let sat_mint = create_mint_from_mocks(db.clone(), Some(lightning)).await?;
+        let mint = Mint::new(
+            sat_mint.lightning,
+            sat_mint.lightning_type,
+            sat_mint.db,
+            MintConfig {
+                units: vec![CurrencyUnit::Sat, CurrencyUnit::MSat],
+                ..sat_mint.config
+            },
+            sat_mint.build_params,
+            sat_mint.onchain,
+            sat_mint.bill_registry,
+        );
+        mint.init_keysets().await?;
+        let msat_keyset = mint.active_keyset(&CurrencyUnit::MSat)?;
+
+        // 100 sat invoice, minted as msat
+        let quote_id = Uuid::new_v4();
+        let mut tx = db.begin_tx().await?;
+        db.add_pending_invoice(
+            &mut tx,
+            quote_id.to_string(),
+            &Invoice::new(100, INVOICE_20_SAT.to_owned()),
+        )
+        .await?;
+        db.add_bolt11_mint_quote(
+            &mut tx,
+            &Bolt11MintQuote {
+                quote_id,
+                unit: CurrencyUnit::MSat,
+                payment_request: INVOICE_20_SAT.to_owned(),
+                expiry: (Utc::now().timestamp() + 3600) as u64,
+                paid: false,
+            },
+        )
+        .await?;
+        tx.commit().await?;
+
+        let outputs = read_fixture_as::<Vec<BlindedMessage>>("blinded_messages_40.json")?
+            .into_iter()
+            .map(|output| BlindedMessage {
+                id: msat_keyset.keyset_id.clone(),
+                ..output
+            })
+            .collect::<Vec<_>>();
+        let mut tx = db.begin_tx().await?;
+        let result = mint
+            .mint_tokens(
+                &mut tx,
+                moksha_core::primitives::PaymentMethod::Bolt11,
+                quote_id.to_string(),
+                &outputs,
+                &mint.keyset(),
+            )
+            .await;
+        assert!(matches!(result, Err(MokshaMintError::UnitMismatch(unit)) if unit == "msat"));
+        drop(tx);
+
+        let mut tx = db.begin_tx().await?;
+        let signatures = mint
+            .mint_tokens(
+                &mut tx,
+                moksha_core::primitives::PaymentMethod::Bolt11,
+                quote_id.to_string(),
+                &outputs,
+                &msat_keyset,
+            )
+            .await?;
+        tx.commit().await?;
+        assert_eq!(40, signatures.total_amount());
+        assert!(signatures
+            .iter()
+            .all(|signature| signature.id == msat_keyset.keyset_id));
+
+        // sat inputs can not be melted for a msat quote
+        let quote = Bolt11MeltQuote {
+            quote_id: Uuid::new_v4(),
+            unit: CurrencyUnit::MSat,
+            amount: 20_000,
+            fee_reserve: 4_000,
+            payment_request: INVOICE_20_SAT.to_owned(),
+            expiry: (Utc::now() + Duration::hours(1)).timestamp() as u64,
+            paid: false,
+        };
+        let mut tx = db.begin_tx().await?;
+        db.add_bolt11_melt_quote(&mut tx, &quote).await?;
+        tx.commit().await?;
+        let tokens = create_token_from_fixture("token_60.cashu").expect("can not read fixture");
+        let result = mint
+            .melt_bolt11(&quote.quote_id, &tokens.proofs(), None, &mint.keyset())
+            .await;
+        assert!(matches!(result, Err(MokshaMintError::UnitMismatch(unit)) if unit == "msat"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mint_valid() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let mut lightning = MockLightning::new();
+        lightning.expect_is_invoice_paid().returning(|_| Ok(true));
+        let mint = create_mint_from_mocks(
+            create_mock_db_pending_invoice(node.get_host_port_ipv4(5432), 3600).await?,
+            Some(lightning),
+        )
+        .await?;
+
+        let outputs = read_fixture_as::<Vec<BlindedMessage>>("blinded_messages_40.json")?;
+        let mut tx = mint.db.begin_tx().await?;
+        let result = mint
+            .mint_tokens(
+                &mut tx,
+                moksha_core::primitives::PaymentMethod::Bolt11,
+                MINT_QUOTE_ID.to_string(),
+                &outputs,
+                &mint.keyset(),
+            )
+            .await?;
+        assert_eq!(40, result.total_amount());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mint_quote_single_use() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let mut lightning = MockLightning::new();
+        lightning.expect_is_invoice_paid().returning(|_| Ok(true));
+        let mint = create_mint_from_mocks(
+            create_mock_db_pending_invoice(node.get_host_port_ipv4(5432), 3600).await?,
+            Some(lightning),
+        )
+        .await?;
+
+        let outputs = read_fixture_as::<Vec<BlindedMessage>>("blinded_messages_40.json")?;
+        let mut tx = mint.db.begin_tx().await?;
+        mint.mint_tokens(
+            &mut tx,
+            moksha_core::primitives::PaymentMethod::Bolt11,
+            MINT_QUOTE_ID.to_string(),
+            &outputs,
+            &mint.keyset(),
+        )
+        .await?;
+        tx.commit().await?;
+
+        let mut tx = mint.db.begin_tx().await?;
+        let result = mint
+            .mint_tokens(
+                &mut tx,
+                moksha_core::primitives::PaymentMethod::Bolt11,
+                MINT_QUOTE_ID.to_string(),
+                &outputs,
+                &mint.keyset(),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::QuoteAlreadyIssued(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mint_unpaid_invoice() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let mut lightning = MockLightning::new();
+        lightning.expect_is_invoice_paid().returning(|_| Ok(false));
+        let mint = create_mint_from_mocks(
+            create_mock_db_pending_invoice(node.get_host_port_ipv4(5432), 3600).await?,
+            Some(lightning),
+        )
+        .await?;
+
+        let outputs = read_fixture_as::<Vec<BlindedMessage>>("blinded_messages_40.json")?;
+        let mut tx = mint.db.begin_tx().await?;
+        let result = mint
+            .mint_tokens(
+                &mut tx,
+                moksha_core::primitives::PaymentMethod::Bolt11,
+                MINT_QUOTE_ID.to_string(),
+                &outputs,
+                &mint.keyset(),
+            )
+            .await;
+        assert!(matches!(result, Err(MokshaMintError::InvoiceNotPaidYet)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mint_bitcredit_not_holder() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let db = create_mock_db_empty(node.get_host_port_ipv4(5432)).await?;
+        let quote = BitcreditMintQuote {
+            quote_id: Uuid::new_v4(),
+            bill_id: "bill".to_owned(),
+            node_id: "node".to_owned(),
+            amount: 40,
+            state: BitcreditMintQuoteState::Accepted,
+            expiry: (Utc::now() + Duration::hours(1)).timestamp() as u64,
+        };
+        let mut tx = db.begin_tx().await?;
+        db.add_bitcredit_mint_quote(&mut tx, &quote).await?;
+        tx.commit().await?;
+
+        let mut bill_registry = MockBillRegistry::new();
+        bill_registry
+            .expect_is_holder()
+            .times(1)
+            .returning(|_| Ok(false));
+        bill_registry
+            .expect_is_holder()
+            .times(1)
+            .returning(|_| Err(BillRegistryError::Unauthorized.into()));
+        let mint = Mint {
+            bill_registry: Arc::new(bill_registry),
+            ..create_mint_from_mocks(db, None).await?
+        };
+
+        let outputs = read_fixture_as::<Vec<BlindedMessage>>("blinded_messages_40.json")?;
+        let mut tx = mint.db.begin_tx().await?;
+        let result = mint
+            .mint_tokens(
+                &mut tx,
+                moksha_core::primitives::PaymentMethod::Bitcredit,
+                quote.quote_id.to_string(),
+                &outputs,
+                &mint.keyset(),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::BitcreditQuoteMintNotHolder)
+        ));
+
+        // an unavailable registry fails the request instead of the mint
+        let result = mint
+            .mint_tokens(
+                &mut tx,
+                moksha_core::primitives::PaymentMethod::Bitcredit,
+                quote.quote_id.to_string(),
+                &outputs,
+                &mint.keyset(),
+            )
+            .await;
+        assert!(matches!(result, Err(MokshaMintError::BillRegistry(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mint_expired_quote() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let mut lightning = MockLightning::new();
+        lightning.expect_is_invoice_paid().returning(|_| Ok(false));
+        let mint = create_mint_from_mocks(
+            create_mock_db_pending_invoice(node.get_host_port_ipv4(5432), -60).await?,
+            Some(lightning),
+        )
+        .await?;
+
+        let outputs = read_fixture_as::<Vec<BlindedMessage>>("blinded_messages_40.json")?;
+        let mut tx = mint.db.begin_tx().await?;
+        let result = mint
+            .mint_tokens(
+                &mut tx,
+                moksha_core::primitives::PaymentMethod::Bolt11,
+                MINT_QUOTE_ID.to_string(),
+                &outputs,
+                &mint.keyset(),
+            )
+            .await;
+        assert!(matches!(result, Err(MokshaMintError::QuoteExpired(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mint_amount_exceeds_quote() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let mut lightning = MockLightning::new();
+        lightning.expect_is_invoice_paid().returning(|_| Ok(true));
+        let mint = create_mint_from_mocks(
+            create_mock_db_pending_invoice(node.get_host_port_ipv4(5432), 3600).await?,
+            Some(lightning),
+        )
+        .await?;
+
+        let mut outputs = read_fixture_as::<Vec<BlindedMessage>>("blinded_messages_40.json")?;
+        outputs[0].amount = 128;
+        let mut tx = mint.db.begin_tx().await?;
+        let result = mint
+            .mint_tokens(
+                &mut tx,
+                moksha_core::primitives::PaymentMethod::Bolt11,
+                MINT_QUOTE_ID.to_string(),
+                &outputs,
+                &mint.keyset(),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::QuoteAmountExceeded(_, 100))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swap_zero() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let blinded_messages = vec![];
+        let mint = create_mint_from_mocks(
+            create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+            None,
+        )
+        .await?;
+
+        let proofs = Proofs::empty();
+        let result = mint
+            .swap(&proofs, &blinded_messages, &mint.keyset())
+            .await?;
+
+        assert!(result.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swap_64_in_20() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let mint = create_mint_from_mocks(
+            create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+            None,
+        )
+        .await?;
+        let request = read_fixture_as::<PostSwapRequest>("post_swap_request_64_20.json")?;
+
+        let result = mint
+            .swap(&request.inputs, &request.outputs, &mint.keyset())
+            .await?;
+        assert_eq!(result.total_amount(), 64);
+
+        let prv_last = result.get(result.len() - 2).expect("element not found");
+        let last = result.last().expect("element not found");
+
+        assert_eq!(prv_last.amount, 4);
+        assert_eq!(last.amount, 16);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swap_between_units() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let sat_mint = create_mint_from_mocks(
+            create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+            None,
+        )
+        .await?;
+        let mint = Mint::new(
+            sat_mint.lightning,
+            sat_mint.lightning_type,
+            sat_mint.db,
+            MintConfig {
+                units: vec![CurrencyUnit::Sat, CurrencyUnit::Usd],
+                ..sat_mint.config
+            },
+            sat_mint.build_params,
+            sat_mint.onchain,
+            sat_mint.bill_registry,
+        );
+        mint.init_keysets().await?;
+
+        let usd_keyset = mint.active_keyset(&CurrencyUnit::Usd)?.clone();
+        assert_ne!(usd_keyset.keyset_id, mint.keyset().keyset_id);
+        assert_eq!(vec![CurrencyUnit::Sat, CurrencyUnit::Usd], mint.units());
+        let active = mint
+            .get_keysets()
+            .await?
+            .into_iter()
+            .filter(|k| k.active)
+            .map(|k| k.unit)
+            .collect::<HashSet<_>>();
+        assert_eq!(
+            HashSet::from([CurrencyUnit::Sat, CurrencyUnit::Usd]),
+            active
+        );
+
+        // sat inputs can not be swapped into usd outputs
+        let request = read_fixture_as::<PostSwapRequest>("post_swap_request_64_20.json")?;
+        let usd_outputs = request
+            .outputs
+            .iter()
+            .map(|output| BlindedMessage {
+                id: usd_keyset.keyset_id.clone(),
+                ..output.clone()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            usd_keyset.keyset_id,
+            mint.swap_keyset(&usd_outputs).await?.keyset_id
+        );
+        let result = mint.swap(&request.inputs, &usd_outputs, &usd_keyset).await;
+        assert!(matches!(result, Err(MokshaMintError::UnitMismatch(unit)) if unit == "usd"));
+        let result = mint
+            .swap(&request.inputs, &usd_outputs, &mint.keyset())
+            .await;
+        assert!(matches!(result, Err(MokshaMintError::UnitMismatch(unit)) if unit == "sat"));
+
+        assert!(matches!(
+            mint.active_keyset(&CurrencyUnit::MSat),
+            Err(MokshaMintError::CurrencyNotSupported(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swap_input_fee() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let mint = create_mint_from_mocks(
+            create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+            None,
+        )
+        .await?;
+        let mut keyset = mint.keyset();
+        keyset.input_fee_ppk = 4_000;
+        mint.set_active_keyset(CurrencyUnit::Sat, keyset.clone());
+        let request = read_fixture_as::<PostSwapRequest>("post_swap_request_64_20.json")?;
+
+        // one input of 64 with a fee of 4
+        let result = mint.swap(&request.inputs, &request.outputs, &keyset).await;
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::SwapAmountMismatch(_))
+        ));
+
+        let result = mint
+            .swap(&request.inputs, &request.outputs[1..], &keyset)
+            .await?;
+        assert_eq!(result.total_amount(), 60);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swap_after_keyset_rotation() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+        let db = create_mock_db_empty(node.get_host_port_ipv4(5432)).await?;
+
+        let mint = create_mint_from_mocks(db.clone(), None).await?;
+        mint.init_keysets().await?;
+        let old_keyset_id = mint.keyset().keyset_id.clone();
+        let new_keyset = mint.rotate_keyset(&CurrencyUnit::Sat, None).await?;
+        assert_ne!(old_keyset_id, new_keyset.keyset_id);
+
+        let keysets = mint.get_keysets().await?;
+        assert_eq!(2, keysets.len());
+        let active = keysets
+            .iter()
+            .filter(|k| k.active)
+            .map(|k| k.id.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![new_keyset.keyset_id.clone()], active);
+
+        // the active keyset survives a restart with the same configuration
+        let restarted = create_mint_from_mocks(db, None).await?;
+        restarted.init_keysets().await?;
+        assert_eq!(new_keyset.keyset_id, restarted.keyset().keyset_id);
+
+        // inputs are from the inactive keyset
+        let request = read_fixture_as::<PostSwapRequest>("post_swap_request_64_20.json")?;
+        let inactive_outputs = request
+            .outputs
+            .iter()
+            .map(|output| BlindedMessage {
+                id: old_keyset_id.clone(),
+                ..output.clone()
+            })
+            .collect::<Vec<_>>();
+        let result = mint
+            .swap(&request.inputs, &inactive_outputs, &mint.keyset())
+            .await;
+        assert!(matches!(result, Err(MokshaMintError::InactiveKeyset(id)) if id == old_keyset_id));
+
+        let active_outputs = request
+            .outputs
+            .iter()
+            .map(|output| BlindedMessage {
+                id: new_keyset.keyset_id.clone(),
+                ..output.clone()
+            })
+            .collect::<Vec<_>>();
+        let result = mint
+            .swap(&request.inputs, &active_outputs, &mint.keyset())
+            .await?;
+        assert_eq!(result.total_amount(), 64);
+        assert!(result.iter().all(|sig| sig.id == new_keyset.keyset_id));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_check_state_after_swap() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let mint = create_mint_from_mocks(
+            create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+            None,
+        )
+        .await?;
+        let request = read_fixture_as::<PostSwapRequest>("post_swap_request_64_20.json")?;
+        let spent_y = request.inputs.proofs()[0].y()?;
+        let unspent_y = dhke::public_key_from_hex(
+            "02634a2c2b34bec9e8a4aba4361f6bf202d7fa2365379b0840afe249a7a9d71239",
+        );
+
+        let before = mint.check_state(&[spent_y]).await?;
+        assert_eq!(State::Unspent, before[0].state);
+
+        mint.swap(&request.inputs, &request.outputs, &mint.keyset())
+            .await?;
+
+        let after = mint.check_state(&[spent_y, unspent_y]).await?;
+        assert_eq!(2, after.len());
+        assert_eq!(spent_y, after[0].y);
+        assert_eq!(State::Spent, after[0].state);
+        assert_eq!(unspent_y, after[1].y);
+        assert_eq!(State::Unspent, after[1].state);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_restore_after_swap() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let mint = create_mint_from_mocks(
+            create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+            None,
+        )
+        .await?;
+        let request = read_fixture_as::<PostSwapRequest>("post_swap_request_64_20.json")?;
+        let signatures = mint
+            .swap(&request.inputs, &request.outputs, &mint.keyset())
+            .await?;
+
+        let unknown = BlindedMessage {
+            amount: 8,
+            b_: dhke::public_key_from_hex(
+                "02634a2c2b34bec9e8a4aba4361f6bf202d7fa2365379b0840afe249a7a9d71239",
+            ),
+            id: mint.keyset().keyset_id.clone(),
+            witness: None,
+        };
+        let mut outputs = vec![unknown];
+        outputs.extend(request.outputs.clone());
+
+        let (restored_outputs, restored_signatures) = mint.restore(&outputs).await?;
+        assert_eq!(request.outputs.len(), restored_outputs.len());
+        assert_eq!(signatures.len(), restored_signatures.len());
+        for ((output, restored_output), (signature, restored_signature)) in request
+            .outputs
+            .iter()
+            .zip(restored_outputs)
+            .zip(signatures.iter().zip(restored_signatures))
+        {
+            assert_eq!(output.b_, restored_output.b_);
+            assert_eq!(signature.c_, restored_signature.c_);
+            assert_eq!(signature.amount, restored_signature.amount);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swap_duplicate_key() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+        let mint = create_mint_from_mocks(
+            create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+            None,
+        )
+        .await?;
+        let request = read_fixture_as::<PostSwapRequest>("post_swap_request_duplicate_key.json")?;
+
+        let result = mint
+            .swap(&request.inputs, &request.outputs, &mint.keyset())
+            .await;
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swap_duplicate_inputs() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let mint = create_mint_from_mocks(
+            create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+            None,
+        )
+        .await?;
+        let request = read_fixture_as::<PostSwapRequest>("post_swap_request_64_20.json")?;
+        let proof = request.inputs.proofs()[0].clone();
+        let inputs: Proofs = vec![proof.clone(), proof].into();
+
+        let result = mint.swap(&inputs, &request.outputs, &mint.keyset()).await;
+        assert!(matches!(result, Err(MokshaMintError::ProofAlreadyUsed(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swap_concurrent_double_spend() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let mint = create_mint_from_mocks(
+            create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+            None,
+        )
+        .await?;
+        let request = read_fixture_as::<PostSwapRequest>("post_swap_request_64_20.json")?;
+
+        let keyset = mint.keyset();
+        let (first, second) = tokio::join!(
+            mint.swap(&request.inputs, &request.outputs, &keyset),
+            mint.swap(&request.inputs, &request.outputs, &keyset)
+        );
+        let results = [first, second];
+        assert_eq!(1, results.iter().filter(|r| r.is_ok()).count());
+        assert!(results
+            .iter()
+            .any(|r| matches!(r, Err(MokshaMintError::ProofAlreadyUsed(_)))));
+
+        let result = mint
+            .swap(&request.inputs, &request.outputs, &mint.keyset())
+            .await;
+        assert!(matches!(result, Err(MokshaMintError::ProofAlreadyUsed(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swap_same_outputs_twice() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+        let mint = create_mint_from_mocks(
+            create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+            None,
+        )
+        .await?;
+        let request = read_fixture_as::<PostSwapRequest>("post_swap_request_64_20.json")?;
+        mint.swap(&request.inputs, &request.outputs, &mint.keyset())
+            .await?;
+
+        // fresh inputs, but outputs that have already been signed
+        let secret = "a8f3b2c1d4e5f60718293a4b5c6d7e8f".to_owned();
+        let (c, _) = mint.dhke.step2_bob(
+            dhke::Dhke::hash_to_curve(secret.as_bytes())?,
+            &mint.keyset().private_keys[&64],
+        )?;
+        let proof = Proof::new(64, secret, c, mint.keyset().keyset_id.clone());
+        let result = mint
+            .swap(&proof.clone().into(), &request.outputs, &mint.keyset())
+            .await;
+        let err = result.expect_err("outputs already signed");
+        assert!(matches!(err, MokshaMintError::OutputAlreadySigned(_)));
+        assert_eq!(axum::http::StatusCode::BAD_REQUEST, err.status());
+        assert_eq!(ErrorCode::OutputAlreadySigned, err.code());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swap_forged_proof() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+        let mint = create_mint_from_mocks(
+            create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+            None,
+        )
+        .await?;
+        let mut request = read_fixture_as::<PostSwapRequest>("post_swap_request_64_20.json")?;
+        let forged = request
+            .inputs
+            .proofs()
+            .into_iter()
+            .map(|proof| Proof {
+                c: dhke::public_key_from_hex(
+                    "02634a2c2b34bec9e8a4aba4361f6bf202d7fa2365379b0840afe249a7a9d71239",
+                ),
+                ..proof
+            })
+            .collect::<Vec<_>>();
+        request.inputs = forged.into();
+
+        let result = mint
+            .swap(&request.inputs, &request.outputs, &mint.keyset())
+            .await;
+        assert!(matches!(result, Err(MokshaMintError::InvalidProof(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swap_p2pk() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+        let mint = create_mint_from_mocks(
+            create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+            None,
+        )
+        .await?;
+        let request = read_fixture_as::<PostSwapRequest>("post_swap_request_64_20.json")?;
+
+        let secp = secp256k1::Secp256k1::new();
+        let key = secp256k1::SecretKey::from_slice(&[1; 32])?;
+        let secret: String = WellKnownSecret {
+            kind: SecretKind::P2PK,
+            nonce: "5d11913ee0f92fefdc82a6764fd2457a".to_owned(),
+            data: key.public_key(&secp).to_string(),
+            tags: vec![],
+        }
+        .try_into()?;
+        let (c, _) = mint.dhke.step2_bob(
+            dhke::Dhke::hash_to_curve(secret.as_bytes())?,
+            &mint.keyset().private_keys[&64],
+        )?;
+        let mut proof = Proof::new(64, secret, c, mint.keyset().keyset_id.clone());
+
+        let result = mint
+            .swap(&proof.clone().into(), &request.outputs, &mint.keyset())
+            .await;
+        assert!(matches!(result, Err(MokshaMintError::WitnessMissing(_))));
+
+        let mut witness = Witness::default();
+        witness.sign(proof.secret.as_bytes(), &key);
+        proof.witness = Some(witness);
+        let result = mint
+            .swap(&proof.into(), &request.outputs, &mint.keyset())
+            .await?;
+        assert_eq!(64, result.total_amount());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swap_htlc() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+        let mint = create_mint_from_mocks(
+            create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+            None,
+        )
+        .await?;
+        let request = read_fixture_as::<PostSwapRequest>("post_swap_request_64_20.json")?;
+
+        // sha256 of 32 zero bytes
+        let secret: String = WellKnownSecret {
+            kind: SecretKind::HTLC,
+            nonce: "5d11913ee0f92fefdc82a6764fd2457a".to_owned(),
+            data: "66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925".to_owned(),
+            tags: vec![],
+        }
+        .try_into()?;
+        let (c, _) = mint.dhke.step2_bob(
+            dhke::Dhke::hash_to_curve(secret.as_bytes())?,
+            &mint.keyset().private_keys[&64],
+        )?;
+        let mut proof = Proof::new(64, secret, c, mint.keyset().keyset_id.clone());
+
+        let result = mint
+            .swap(&proof.clone().into(), &request.outputs, &mint.keyset())
+            .await;
+        assert!(matches!(result, Err(MokshaMintError::InvalidPreimage(_))));
+
+        proof.witness = Some(Witness {
+            preimage: Some("00".repeat(32)),
+            signatures: vec![],
+        });
+        let result = mint
+            .swap(&proof.into(), &request.outputs, &mint.keyset())
+            .await?;
+        assert_eq!(64, result.total_amount());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swap_unknown_keyset() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+        let mint = create_mint_from_mocks(
+            create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+            None,
+        )
+        .await?;
+        let mut request = read_fixture_as::<PostSwapRequest>("post_swap_request_64_20.json")?;
+        let unknown = request
+            .inputs
+            .proofs()
+            .into_iter()
+            .map(|proof| Proof {
+                keyset_id: "00ffd48b8f5ecf80".to_owned(),
+                ..proof
+            })
+            .collect::<Vec<_>>();
+        request.inputs = unknown.into();
+
+        let result = mint
+            .swap(&request.inputs, &request.outputs, &mint.keyset())
+            .await;
+        assert!(matches!(result, Err(MokshaMintError::KeysetNotFound(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    /// melt 20 sats with 60 tokens and receive 40 tokens as change
+    async fn test_melt_overpay() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let mut lightning = create_mock_lightning();
+        lightning.expect_pay_invoice().returning(|_| {
+            Ok(PayInvoiceResult {
+                payment_hash: "hash".to_string(),
+                total_fees: 2,
+            })
+            .map_err(|_err: LightningError| MokshaMintError::InvoiceNotFound("".to_string()))
+        });
+
+        let mint = create_mint_from_mocks(
+            create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+            Some(lightning),
+        )
+        .await?;
+        let quote = create_melt_quote(&mint.db, 4).await?;
+
+        let tokens = create_token_from_fixture("token_60.cashu").expect("can not read fixture");
+        let change = read_fixture_as::<Vec<BlindedMessage>>("blinded_messages_blank_4000.json")?;
+
+        let (paid, _payment_hash, change) = mint
+            .melt_bolt11(
+                &quote.quote_id,
+                &tokens.proofs(),
+                Some(change),
+                &mint.keyset(),
+            )
+            .await?;
+
+        assert!(paid);
+        assert!(change.total_amount() == 2);
+
+        let states = mint.check_state(&proof_ys(&tokens.proofs())?).await?;
+        assert!(states.iter().all(|state| state.state == State::Spent));
+        let mut tx = mint.db.begin_tx().await?;
+        assert!(
+            mint.db
+                .get_bolt11_melt_quote(&mut tx, &quote.quote_id)
+                .await?
+                .paid
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    /// the backend paid more fees than reserved, the melt succeeds without change
+    async fn test_melt_fees_above_fee_reserve() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let mut lightning = create_mock_lightning();
+        lightning.expect_pay_invoice().returning(|_| {
+            Ok(PayInvoiceResult {
+                payment_hash: "hash".to_string(),
+                total_fees: 6,
+            })
+        });
+
+        let mint = create_mint_from_mocks(
+            create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+            Some(lightning),
+        )
+        .await?;
+        let quote = create_melt_quote(&mint.db, 4).await?;
+
+        let tokens = create_token_from_fixture("token_60.cashu").expect("can not read fixture");
+        let change = read_fixture_as::<Vec<BlindedMessage>>("blinded_messages_blank_4000.json")?;
+
+        let (paid, _payment_hash, change) = mint
+            .melt_bolt11(
+                &quote.quote_id,
+                &tokens.proofs(),
+                Some(change),
+                &mint.keyset(),
+            )
+            .await?;
+
+        assert!(paid);
+        assert!(change.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_melt_expired_quote() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let mint = create_mint_from_mocks(
+            create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+            Some(create_mock_lightning()),
+        )
+        .await?;
+        let quote = Bolt11MeltQuote {
+            quote_id: Uuid::new_v4(),
+            unit: CurrencyUnit::Sat,
+            amount: 20,
+            fee_reserve: 4,
+            payment_request: INVOICE_20_SAT.to_owned(),
+            expiry: (Utc::now() - Duration::minutes(1)).timestamp() as u64,
+            paid: false,
+        };
+        let mut tx = mint.db.begin_tx().await?;
+        mint.db.add_bolt11_melt_quote(&mut tx, &quote).await?;
+        tx.commit().await?;
+        let tokens = create_token_from_fixture("token_60.cashu").expect("can not read fixture");
+
+        let result = mint
+            .melt_bolt11(&quote.quote_id, &tokens.proofs(), None, &mint.keyset())
+            .await;
+        assert!(matches!(result, Err(MokshaMintError::QuoteExpired(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_init_bill_keysets() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let db = create_mock_db_empty(node.get_host_port_ipv4(5432)).await?;
+        // a bill that was requested to mint before bill keysets were persisted
+        let mut tx = db.begin_tx().await?;
+        db.add_bitcredit_request_to_mint(
+            &mut tx,
+            &BitcreditRequestToMint {
+                bill_id: "bill".to_owned(),
+                bill_key: "TEST_BILL_KEY".to_owned(),
+                maturity_date: 1_700_000_000,
+                bill_amount: 1_000,
+            },
+        )
+        .await?;
+        let quote = BitcreditMintQuote {
+            quote_id: Uuid::new_v4(),
+            bill_id: "bill".to_owned(),
+            node_id: "node".to_owned(),
+            amount: 40,
+            state: BitcreditMintQuoteState::Endorsed,
+            expiry: (Utc::now() + Duration::hours(1)).timestamp() as u64,
+        };
+        db.add_bitcredit_mint_quote(&mut tx, &quote).await?;
+        tx.commit().await?;
+
+        let mint = create_mint_from_mocks(db.clone(), None).await?;
+        mint.init_keysets().await?;
+
+        let bill_keyset = mint.bill_keyset("bill").await?;
+        assert_eq!("00bill", bill_keyset.keyset.keyset_id);
+        assert_eq!(1_700_000_000, bill_keyset.maturity_date);
+        assert_eq!(
+            bill_keyset.keyset.public_keys,
+            mint.bill_keyset("00bill").await?.keyset.public_keys
+        );
+        assert!(matches!(
+            mint.bill_keyset("unknown").await,
+            Err(MokshaMintError::KeysetNotFound(_))
+        ));
+
+        let outputs = read_fixture_as::<Vec<BlindedMessage>>("blinded_messages_40.json")?
+            .into_iter()
+            .map(|output| BlindedMessage {
+                id: bill_keyset.keyset.keyset_id.clone(),
+                ..output
+            })
+            .collect::<Vec<_>>();
+        let mut tx = mint.db.begin_tx().await?;
+        mint.mint_tokens(
+            &mut tx,
+            moksha_core::primitives::PaymentMethod::Bitcredit,
+            quote.quote_id.to_string(),
+            &outputs,
+            &bill_keyset.keyset,
+        )
+        .await?;
+        tx.commit().await?;
+
+        // the keysets are only added once
+        let mint = create_mint_from_mocks(db, None).await?;
+        mint.init_keysets().await?;
+        let keysets = mint.get_bill_keysets().await?;
+        assert_eq!(1, keysets.len());
+        assert_eq!(
+            BillKeysetInfo {
+                amount_minted: 40,
+                created_at: keysets[0].created_at,
+                ..BillKeysetInfo::new(
+                    &BitcreditRequestToMint {
+                        bill_id: "bill".to_owned(),
+                        bill_key: "TEST_BILL_KEY".to_owned(),
+                        maturity_date: 1_700_000_000,
+                        bill_amount: 1_000,
+                    },
+                    &bill_keyset.keyset,
+                    0,
+                )
+            },
+            keysets[0]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bill_key_encryption() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let db = create_mock_db_empty(node.get_host_port_ipv4(5432)).await?;
+        let bill_key_encryption = |keys: &str| -> anyhow::Result<Arc<BillKeyEncryption>> {
+            Ok(Arc::new(BillKeyEncryption::new(
+                &BillKeyEncryptionConfig {
+                    keys: keys
+                        .split(',')
+                        .map(str::parse)
+                        .collect::<Result<_, _>>()
+                        .map_err(anyhow::Error::msg)?,
+                    key_file: None,
+                },
+            )?))
+        };
+        let k1 = format!("k1:{}", hex::encode([1; 32]));
+        let k2 = format!("k2:{}", hex::encode([2; 32]));
+
+        let mint = Mint {
+            bill_key_encryption: bill_key_encryption(&k1)?,
+            ..create_mint_from_mocks(db.clone(), None).await?
+        };
+        let request_to_mint = BitcreditRequestToMint {
+            bill_id: "bill".to_owned(),
+            bill_key: "TEST_BILL_KEY".to_owned(),
+            maturity_date: 1_700_000_000,
+            bill_amount: 1_000,
+        };
+        mint.add_bill(&request_to_mint).await?;
+        let public_keys = mint.bill_keyset("bill").await?.keyset.public_keys;
+
+        let mut tx = db.begin_tx().await?;
+        let stored = db
+            .get_bitcredit_request_to_mint(&mut tx, &"bill".to_owned())
+            .await?;
+        tx.commit().await?;
+        assert!(stored.bill_key.starts_with("enc:v1:k1:"));
+
+        // the bill keys are re-encrypted with the new key on startup
+        let mint = Mint {
+            bill_key_encryption: bill_key_encryption(&format!("{k2},{k1}"))?,
+            ..create_mint_from_mocks(db.clone(), None).await?
+        };
+        mint.init_keysets().await?;
+        assert_eq!(
+            public_keys,
+            mint.bill_keyset("bill").await?.keyset.public_keys
+        );
+        let mut tx = db.begin_tx().await?;
+        let stored = db
+            .get_bitcredit_request_to_mint(&mut tx, &"bill".to_owned())
+            .await?;
+        tx.commit().await?;
+        assert!(stored.bill_key.starts_with("enc:v1:k2:"));
+
+        let mint = Mint {
+            bill_key_encryption: bill_key_encryption(&k2)?,
+            ..create_mint_from_mocks(db.clone(), None).await?
+        };
+        mint.init_keysets().await?;
+        assert_eq!(
+            public_keys,
+            mint.bill_keyset("bill").await?.keyset.public_keys
+        );
+
+        // the retired key can not read the bill keys anymore
+        let mint = Mint {
+            bill_key_encryption: bill_key_encryption(&k1)?,
+            ..create_mint_from_mocks(db, None).await?
+        };
+        let result = mint.init_keysets().await;
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::UnknownKeyEncryptionKey(id)) if id == "k2"
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_request_to_mint() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let holder_key = secp256k1::SecretKey::from_slice(&[1; 32])?;
+        let holder_public_key = holder_key
+            .public_key(&secp256k1::Secp256k1::new())
+            .to_string();
+        let mut bill_registry = MockBillRegistry::new();
+        bill_registry.expect_get_bill().returning(move |bill_id| {
+            Ok(BillInfo {
+                holder_public_key: holder_public_key.clone(),
+                ..bill_info(bill_id, 1_000, 1_700_000_000)
+            })
+        });
+        let mint = Mint {
+            bill_registry: Arc::new(bill_registry),
+            ..create_mint_from_mocks(
+                create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+                None,
+            )
+            .await?
+        };
+        let request = |amount, key| {
+            PostRequestToMintBitcreditRequest::new_signed(
+                "bill".to_owned(),
+                BillKeys {
+                    private_key_pem: "TEST_BILL_KEY".to_owned(),
+                    public_key_pem: "TEST_BILL_PUBLIC_KEY".to_owned(),
+                },
+                1_700_000_000,
+                amount,
+                key,
+            )
+        };
+
+        // validly signed by a key that is not the holder's
+        let other_key = secp256k1::SecretKey::from_slice(&[2; 32])?;
+        let other_request = request(1_000, &other_key);
+        assert!(other_request.verify_signature());
+        let result = mint.request_to_mint(&other_request).await;
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::BitcreditRequestNotSignedByHolder(bill_id)) if bill_id == "bill"
+        ));
+        let forged = PostRequestToMintBitcreditRequest {
+            bill_amount: 2_000,
+            ..request(1_000, &holder_key)
+        };
+        let result = mint.request_to_mint(&forged).await;
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::BitcreditRequestNotSignedByHolder(_))
+        ));
+        let result = mint.request_to_mint(&request(2_000, &holder_key)).await;
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::BitcreditRequestBillMismatch(_))
+        ));
+        assert!(mint.get_bill_keysets().await?.is_empty());
+
+        let request_to_mint = mint.request_to_mint(&request(1_000, &holder_key)).await?;
+        assert_eq!("TEST_BILL_KEY", request_to_mint.bill_key);
+        assert_eq!(1, mint.get_bill_keysets().await?.len());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bitcredit_mint_quote_pricing() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let now = 1_700_000_000;
+        let mut bill_registry = MockBillRegistry::new();
+        bill_registry
+            .expect_get_bill()
+            .returning(move |bill_id| Ok(bill_info(bill_id, 100_000, now + 365 * 24 * 60 * 60)));
+        let mint = Mint {
+            bill_registry: Arc::new(bill_registry),
+            clock: Arc::new(FixedClock::new(now as u64)),
+            ..create_mint_from_mocks(
+                create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+                None,
+            )
+            .await?
+        };
+
+        // the default policy discounts 5% per year
+        let result = mint
+            .create_bitcredit_mint_quote("bill".to_owned(), "node".to_owned(), 96_000)
+            .await;
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::BitcreditAmountAboveOffer(96_000, 95_000))
+        ));
+
+        let mut tx = mint.db.begin_tx().await?;
+        let rejected = mint
+            .db
+            .check_bitcredit_quote(
+                &mut tx,
+                &BitcreditQuoteCheck {
+                    bill_id: "bill".to_owned(),
+                    node_id: "node".to_owned(),
+                },
+            )
+            .await?;
+        assert_eq!(BitcreditMintQuoteState::Rejected, rejected.state);
+        let transitions = mint
+            .db
+            .get_bitcredit_mint_quote_transitions(&mut tx, &rejected.quote_id)
+            .await?;
+        assert_eq!(
+            Some("requested 96000 but the offer is 95000".to_owned()),
+            transitions[1].reason
+        );
+        tx.commit().await?;
+
+        // the holder can ask again for less
+        let quote = mint
+            .create_bitcredit_mint_quote("bill".to_owned(), "node".to_owned(), 90_000)
+            .await?;
+        assert_eq!(BitcreditMintQuoteState::Offered, quote.state);
+        assert_eq!(95_000, quote.amount);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_keyset_timestamps_use_mint_clock() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let mint = Mint {
+            clock: Arc::new(FixedClock::new(1_234)),
+            ..create_mint_from_mocks(
+                create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+                None,
+            )
+            .await?
+        };
+        mint.init_keysets().await?;
+        mint.rotate_keyset(&CurrencyUnit::Sat, None).await?;
+        let info = mint
+            .add_bill(&BitcreditRequestToMint {
+                bill_id: "bill".to_owned(),
+                bill_key: "TEST_BILL_KEY".to_owned(),
+                maturity_date: 1_000,
+                bill_amount: 40,
+            })
+            .await?;
+        assert_eq!(1_234, info.created_at);
+
+        let keysets = mint.get_keysets().await?;
+        assert_eq!(2, keysets.len());
+        assert!(keysets.iter().all(|keyset| keyset.created_at == 1_234));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bitcredit_mint_quote_lifecycle() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let mut bill_registry = MockBillRegistry::new();
+        bill_registry.expect_is_holder().returning(|_| Ok(true));
+        bill_registry
+            .expect_get_bill()
+            .returning(|bill_id| Ok(bill_info(bill_id, 40, 1_000)));
+        let clock = Arc::new(FixedClock::new(1_000));
+        let mint = Mint {
+            bill_registry: Arc::new(bill_registry),
+            clock: clock.clone(),
+            ..create_mint_from_mocks(
+                create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+                None,
+            )
+            .await?
+        };
+
+        let quote = mint
+            .create_bitcredit_mint_quote("bill".to_owned(), "node".to_owned(), 40)
+            .await?;
+        assert_eq!(BitcreditMintQuoteState::Offered, quote.state);
+        assert_eq!(1_000 + BITCREDIT_QUOTE_EXPIRY_SECS, quote.expiry);
+        mint.add_bill(&BitcreditRequestToMint {
+            bill_id: "bill".to_owned(),
+            bill_key: "TEST_BILL_KEY".to_owned(),
+            maturity_date: 1_000,
+            bill_amount: 40,
+        })
+        .await?;
+
+        let bill_keyset = mint.bill_keyset("bill").await?.keyset;
+        let outputs = read_fixture_as::<Vec<BlindedMessage>>("blinded_messages_40.json")?
+            .into_iter()
+            .map(|output| BlindedMessage {
+                id: bill_keyset.keyset_id.clone(),
+                ..output
+            })
+            .collect::<Vec<_>>();
+        let mut tx = mint.db.begin_tx().await?;
+        let result = mint
+            .mint_tokens(
+                &mut tx,
+                moksha_core::primitives::PaymentMethod::Bitcredit,
+                quote.quote_id.to_string(),
+                &outputs,
+                &bill_keyset,
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::BitcreditQuoteNotAccepted(_))
+        ));
+        tx.rollback().await?;
+
+        let quote = mint.accept_bitcredit_mint_quote(&quote.quote_id).await?;
+        assert_eq!(BitcreditMintQuoteState::Accepted, quote.state);
+
+        // the bill is minted as cr-sat, not as spendable sat
+        let mut tx = mint.db.begin_tx().await?;
+        let sat_outputs = read_fixture_as::<Vec<BlindedMessage>>("blinded_messages_40.json")?;
+        let result = mint
+            .mint_tokens(
+                &mut tx,
+                moksha_core::primitives::PaymentMethod::Bitcredit,
+                quote.quote_id.to_string(),
+                &sat_outputs,
+                &mint.keyset(),
+            )
+            .await;
+        assert!(matches!(result, Err(MokshaMintError::UnitMismatch(_))));
+        tx.rollback().await?;
+
+        let mut tx = mint.db.begin_tx().await?;
+        let signatures = mint
+            .mint_tokens(
+                &mut tx,
+                moksha_core::primitives::PaymentMethod::Bitcredit,
+                quote.quote_id.to_string(),
+                &outputs,
+                &bill_keyset,
+            )
+            .await?;
+        assert!(signatures
+            .iter()
+            .all(|signature| signature.id == bill_keyset.keyset_id));
+        tx.commit().await?;
+
+        let mut tx = mint.db.begin_tx().await?;
+        let states = mint
+            .db
+            .get_bitcredit_mint_quote_transitions(&mut tx, &quote.quote_id)
+            .await?
+            .into_iter()
+            .map(|transition| (transition.state, transition.timestamp))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (BitcreditMintQuoteState::Requested, 1_000),
+                (BitcreditMintQuoteState::Offered, 1_000),
+                (BitcreditMintQuoteState::Accepted, 1_000),
+                (BitcreditMintQuoteState::Endorsed, 1_000),
+                (BitcreditMintQuoteState::Minted, 1_000),
+            ],
+            states
+        );
+        let result = mint
+            .mint_tokens(
+                &mut tx,
+                moksha_core::primitives::PaymentMethod::Bitcredit,
+                quote.quote_id.to_string(),
+                &outputs,
+                &bill_keyset,
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::BitcreditQuoteAlreadySent)
+        ));
+        tx.rollback().await?;
+
+        // quotes that are not endorsed in time expire
+        let quote = mint
+            .create_bitcredit_mint_quote("other bill".to_owned(), "node".to_owned(), 40)
+            .await?;
+        clock.advance(BITCREDIT_QUOTE_EXPIRY_SECS + 1);
+        let mut tx = mint.db.begin_tx().await?;
+        let result = mint
+            .mint_tokens(
+                &mut tx,
+                moksha_core::primitives::PaymentMethod::Bitcredit,
+                quote.quote_id.to_string(),
+                &outputs,
+                &bill_keyset,
+            )
+            .await;
+        assert!(matches!(result, Err(MokshaMintError::QuoteExpired(_))));
+        tx.rollback().await?;
+
+        // the expiry is stored although minting failed
+        let mut tx = mint.db.begin_tx().await?;
+        let quote = mint
+            .db
+            .get_bitcredit_mint_quote(&mut tx, &quote.quote_id)
+            .await?;
+        assert_eq!(BitcreditMintQuoteState::Expired, quote.state);
+        let result = mint
+            .transition_bitcredit_mint_quote(
+                &mut tx,
+                &quote,
+                BitcreditMintQuoteState::Accepted,
+                None,
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::InvalidQuoteTransition(
+                _,
+                BitcreditMintQuoteState::Expired,
+                BitcreditMintQuoteState::Accepted
+            ))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_treasury() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let now = 1_700_000_000;
+        let maturity_date = now + 365 * 24 * 60 * 60;
+        let mut bill_registry = MockBillRegistry::new();
+        bill_registry.expect_is_holder().returning(|_| Ok(true));
+        bill_registry
+            .expect_get_bill()
+            .returning(move |bill_id| Ok(bill_info(bill_id, 50, maturity_date)));
+        let mut mint = Mint {
+            bill_registry: Arc::new(bill_registry),
+            clock: Arc::new(FixedClock::new(now as u64)),
+            ..create_mint_from_mocks(
+                create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+                None,
+            )
+            .await?
+        };
+        mint.config.pricing.annual_discount_rate = 0.2;
+        mint.config.treasury.issue_ecash = true;
+        mint.add_bill(&BitcreditRequestToMint {
+            bill_id: "bill".to_owned(),
+            bill_key: "TEST_BILL_KEY".to_owned(),
+            maturity_date,
+            bill_amount: 50,
+        })
+        .await?;
+
+        // the mint buys the bill for 40 and earns the discount of 10
+        let quote = mint
+            .create_bitcredit_mint_quote("bill".to_owned(), "node".to_owned(), 40)
+            .await?;
+        assert_eq!(40, quote.amount);
+        mint.accept_bitcredit_mint_quote(&quote.quote_id).await?;
+        let bill_keyset = mint.bill_keyset("bill").await?.keyset;
+        let outputs = read_fixture_as::<Vec<BlindedMessage>>("blinded_messages_40.json")?
+            .into_iter()
+            .map(|output| BlindedMessage {
+                id: bill_keyset.keyset_id.clone(),
+                ..output
+            })
+            .collect::<Vec<_>>();
+        let mut tx = mint.db.begin_tx().await?;
+        mint.mint_tokens(
+            &mut tx,
+            moksha_core::primitives::PaymentMethod::Bitcredit,
+            quote.quote_id.to_string(),
+            &outputs,
+            &bill_keyset,
+        )
+        .await?;
+        let treasury_proofs = mint.db.get_treasury_proofs(&mut tx, "bill").await?;
+        assert_eq!(10, treasury_proofs.total_amount());
+        mint.verify_proofs(&mut tx, &treasury_proofs).await?;
+        tx.commit().await?;
+
+        let treasury = mint.get_treasury().await?;
+        assert_eq!(1, treasury.entries.len());
+        assert_eq!(TreasuryEntryKind::Earned, treasury.entries[0].kind);
+        assert_eq!(
+            Some(quote.quote_id.to_string()),
+            treasury.entries[0].reference
+        );
+
+        let proofs = mint.withdraw_treasury("bill", 4).await?;
+        assert_eq!(4, proofs.total_amount());
+        let mut tx = mint.db.begin_tx().await?;
+        mint.verify_proofs(&mut tx, &proofs).await?;
+        let treasury_proofs = mint.db.get_treasury_proofs(&mut tx, "bill").await?;
+        assert_eq!(6, treasury_proofs.total_amount());
+        mint.verify_proofs(&mut tx, &treasury_proofs).await?;
+        tx.commit().await?;
+        assert_eq!(
+            vec![TreasuryBalance {
+                bill_id: "bill".to_owned(),
+                earned: 10,
+                withdrawn: 4,
+                melted: 0,
+                pending: 0,
+                balance: 6,
+            }],
+            mint.get_treasury().await?.balances
+        );
+        // proofs that were issued to the treasury are not minted twice
+        assert_eq!(50, mint.get_bill_keysets().await?[0].amount_minted);
+
+        let result = mint.withdraw_treasury("bill", 7).await;
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::TreasuryBalanceExceeded(_, 7, 6))
+        ));
+        let result = mint
+            .melt_treasury("bill", "lnbcrt1u1pn...".to_owned())
+            .await;
+        assert!(matches!(result, Err(MokshaMintError::BillNotMature(_))));
+
+        // concurrent withdrawals can not overdraw the balance
+        let (first, second) = tokio::join!(
+            mint.withdraw_treasury("bill", 4),
+            mint.withdraw_treasury("bill", 4)
+        );
+        assert_eq!(1, [&first, &second].iter().filter(|r| r.is_ok()).count());
+        assert!(matches!(
+            first.and(second),
+            Err(MokshaMintError::TreasuryBalanceExceeded(_, 4, 2))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_melt_treasury_pending_payment() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let mut bill_registry = MockBillRegistry::new();
+        bill_registry.expect_get_bill().returning(|bill_id| {
+            Ok(BillInfo {
+                paid: true,
+                ..bill_info(bill_id, 100, 1_000)
+            })
+        });
+        let mut lightning = create_mock_lightning();
+        lightning.expect_pay_invoice().returning(|payment_request| {
+            Err(MokshaMintError::PayInvoice(
+                payment_request,
+                LightningError::PaymentFailed,
+            ))
+        });
+        lightning
+            .expect_payment_status()
+            .times(1)
+            .returning(|_| Ok(PaymentStatus::Pending));
+        lightning
+            .expect_payment_status()
+            .times(1)
+            .returning(|_| Ok(PaymentStatus::Paid { total_fees: 1 }));
+        lightning
+            .expect_payment_status()
+            .times(1)
+            .returning(|_| Ok(PaymentStatus::Failed));
+        let mint = Mint {
+            bill_registry: Arc::new(bill_registry),
+            clock: Arc::new(FixedClock::new(2_000)),
+            ..create_mint_from_mocks(
+                create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+                Some(lightning),
+            )
+            .await?
+        };
+        mint.add_bill(&BitcreditRequestToMint {
+            bill_id: "bill".to_owned(),
+            bill_key: "TEST_BILL_KEY".to_owned(),
+            maturity_date: 1_000,
+            bill_amount: 100,
+        })
+        .await?;
+        let mut tx = mint.db.begin_tx().await?;
+        mint.add_bill_earnings(
+            &mut tx,
+            &BitcreditMintQuote {
+                quote_id: Uuid::new_v4(),
+                bill_id: "bill".to_owned(),
+                node_id: "node".to_owned(),
+                amount: 20,
+                state: BitcreditMintQuoteState::Minted,
+                expiry: 0,
+            },
+        )
+        .await?;
+        tx.commit().await?;
+
+        // the invoice and the fee reserve stay reserved while the payment is in flight
+        let result = mint.melt_treasury("bill", INVOICE_20_SAT.to_owned()).await;
+        assert!(matches!(result, Err(MokshaMintError::PayInvoice(_, _))));
+        let balance = mint.get_treasury().await?.balances.remove(0);
+        assert_eq!(
+            (80, 24, 56),
+            (balance.earned, balance.pending, balance.balance)
+        );
+
+        mint.reconcile_pending_melts().await?;
+        let treasury = mint.get_treasury().await?;
+        assert_eq!(
+            vec![TreasuryBalance {
+                bill_id: "bill".to_owned(),
+                earned: 80,
+                withdrawn: 0,
+                melted: 21,
+                pending: 0,
+                balance: 59,
+            }],
+            treasury.balances
+        );
+        assert_eq!(TreasuryEntryKind::Melted, treasury.entries[1].kind);
+
+        // a failed payment releases the reserve
+        let result = mint.melt_treasury("bill", INVOICE_20_SAT.to_owned()).await;
+        assert!(matches!(result, Err(MokshaMintError::PayInvoice(_, _))));
+        let balance = mint.get_treasury().await?.balances.remove(0);
+        assert_eq!((0, 59), (balance.pending, balance.balance));
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::btconchain::MockBtcOnchain;
-    use crate::config::{DatabaseConfig, MintConfig};
-    use crate::database::postgres::PostgresDB;
-    use crate::database::Database;
-    use crate::error::MokshaMintError;
-    use crate::lightning::error::LightningError;
-    use crate::lightning::{LightningType, MockLightning};
-    use crate::mint::Mint;
-    use crate::model::{Invoice, PayInvoiceResult};
-    use moksha_core::blind::{BlindedMessage, TotalAmount};
-    use moksha_core::dhke;
-    use moksha_core::fixture::read_fixture_as;
-    use moksha_core::primitives::PostSwapRequest;
-    use moksha_core::proof::Proofs;
-    use moksha_core::token::TokenV3;
-    use pretty_assertions::assert_eq;
-    use std::str::FromStr;
-    use std::sync::Arc;
-    use testcontainers::clients::Cli;
-    use testcontainers::RunnableImage;
-    use testcontainers_modules::postgres::Postgres;
+    #[tokio::test]
+    async fn test_redeem_bitcredit() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let now = 1_700_000_000;
+        let maturity_date = now + 100;
+        let paid = Arc::new(AtomicBool::new(false));
+        let mut bill_registry = MockBillRegistry::new();
+        bill_registry.expect_get_bill().returning({
+            let paid = paid.clone();
+            move |bill_id| {
+                Ok(BillInfo {
+                    paid: bill_id == "paid bill" && paid.load(Ordering::SeqCst),
+                    ..bill_info(bill_id, 40, maturity_date)
+                })
+            }
+        });
+        let clock = Arc::new(FixedClock::new(now as u64));
+        let mut mint = Mint {
+            bill_registry: Arc::new(bill_registry),
+            clock: clock.clone(),
+            ..create_mint_from_mocks(
+                create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+                None,
+            )
+            .await?
+        };
+        mint.config.redemption.grace_period_secs = 50;
+        for (bill_id, bill_key) in [
+            ("paid bill", "PAID_BILL_KEY"),
+            ("unpaid bill", "UNPAID_BILL_KEY"),
+        ] {
+            mint.add_bill(&BitcreditRequestToMint {
+                bill_id: bill_id.to_owned(),
+                bill_key: bill_key.to_owned(),
+                maturity_date,
+                bill_amount: 40,
+            })
+            .await?;
+        }
+        let paid_keyset = mint.bill_keyset("paid bill").await?.keyset;
+        let unpaid_keyset = mint.bill_keyset("unpaid bill").await?.keyset;
+        let paid_proofs = mint.issue_proofs(40, &paid_keyset)?;
+        let unpaid_proofs = mint.issue_proofs(40, &unpaid_keyset)?;
+        let outputs = |seed: u8, id: &str| -> anyhow::Result<Vec<BlindedMessage>> {
+            Amount(40)
+                .split()
+                .into_iter()
+                .enumerate()
+                .map(|(i, amount)| {
+                    Ok(BlindedMessage {
+                        amount,
+                        b_: SecretKey::from_slice(&[seed + i as u8; 32])?
+                            .public_key(&Secp256k1::new()),
+                        id: id.to_owned(),
+                        witness: None,
+                    })
+                })
+                .collect()
+        };
+        let sat_outputs = outputs(1, &mint.keyset().keyset_id)?;
+
+        let result = mint.redeem_bitcredit(&paid_proofs, &sat_outputs).await;
+        assert!(matches!(result, Err(MokshaMintError::BillNotMature(_))));
+
+        // the drawee has not paid yet but the grace period is not over
+        clock.advance(100);
+        let result = mint.redeem_bitcredit(&paid_proofs, &sat_outputs).await;
+        assert!(matches!(result, Err(MokshaMintError::BillNotPaid(_))));
+
+        // only cr-sat is redeemed for sat
+        let result = mint
+            .redeem_bitcredit(&paid_proofs, &outputs(1, &paid_keyset.keyset_id)?)
+            .await;
+        assert!(matches!(result, Err(MokshaMintError::UnitMismatch(_))));
+        let sat_proofs = mint.issue_proofs(40, &mint.keyset())?;
+        let result = mint.redeem_bitcredit(&sat_proofs, &sat_outputs).await;
+        assert!(matches!(result, Err(MokshaMintError::UnitMismatch(_))));
+
+        paid.store(true, Ordering::SeqCst);
+        let signatures = mint.redeem_bitcredit(&paid_proofs, &sat_outputs).await?;
+        assert_eq!(40, signatures.total_amount());
+        assert!(signatures
+            .iter()
+            .all(|signature| signature.id == mint.keyset().keyset_id));
+        let result = mint
+            .redeem_bitcredit(&paid_proofs, &outputs(10, &mint.keyset().keyset_id)?)
+            .await;
+        assert!(matches!(result, Err(MokshaMintError::ProofAlreadyUsed(_))));
+
+        // the unpaid bill defaults after the grace period
+        clock.advance(50);
+        let result = mint
+            .redeem_bitcredit(&unpaid_proofs, &outputs(20, &mint.keyset().keyset_id)?)
+            .await;
+        assert!(matches!(result, Err(MokshaMintError::BillDefaulted(_))));
+        let mut tx = mint.db.begin_tx().await?;
+        assert_eq!(
+            BillKeysetStatus::Settled,
+            mint.db
+                .get_bill_keyset_status(&mut tx, &paid_keyset.keyset_id)
+                .await?
+        );
+        assert_eq!(
+            BillKeysetStatus::Defaulted,
+            mint.db
+                .get_bill_keyset_status(&mut tx, &unpaid_keyset.keyset_id)
+                .await?
+        );
+        tx.commit().await?;
+
+        mint.config.redemption.default_policy = DefaultPolicy::Redeem;
+        let signatures = mint
+            .redeem_bitcredit(&unpaid_proofs, &outputs(20, &mint.keyset().keyset_id)?)
+            .await?;
+        assert_eq!(40, signatures.total_amount());
+        Ok(())
+    }
 
     #[tokio::test]
-    async fn test_fee_reserve() -> anyhow::Result<()> {
+    async fn test_is_mature_at_boundary() -> anyhow::Result<()> {
         let docker = Cli::default();
         let image = create_postgres_image();
         let node = docker.run(image);
 
-        let mint = create_mint_from_mocks(
-            create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
-            None,
-        )
-        .await?;
-        let fee = mint.fee_reserve_msat(10000);
-        assert_eq!(4000, fee);
+        let clock = Arc::new(FixedClock::new(1_700_000_000));
+        let mint = Mint {
+            clock: clock.clone(),
+            ..create_mint_from_mocks(
+                create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+                None,
+            )
+            .await?
+        };
+
+        assert!(!mint.is_mature(1_700_000_001).await?);
+        assert!(mint.is_mature(1_700_000_000).await?);
+        assert!(mint.is_mature(1_699_999_999).await?);
+
+        clock.advance(1);
+        assert!(mint.is_mature(1_700_000_001).await?);
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_create_blindsignatures() -> anyhow::Result<()> {
+    async fn test_melt_paid_quote() -> anyhow::Result<()> {
         let docker = Cli::default();
         let image = create_postgres_image();
         let node = docker.run(image);
 
         let mint = create_mint_from_mocks(
             create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
-            None,
+            Some(create_mock_lightning()),
         )
         .await?;
+        let quote = Bolt11MeltQuote {
+            paid: true,
+            ..create_melt_quote(&mint.db, 4).await?
+        };
+        let mut tx = mint.db.begin_tx().await?;
+        mint.db.update_bolt11_melt_quote(&mut tx, &quote).await?;
+        tx.commit().await?;
+        let tokens = create_token_from_fixture("token_60.cashu").expect("can not read fixture");
 
-        let blinded_messages = vec![BlindedMessage {
-            amount: 8,
-            b_: dhke::public_key_from_hex(
-                "02634a2c2b34bec9e8a4aba4361f6bf202d7fa2365379b0840afe249a7a9d71239",
-            ),
-            id: "00ffd48b8f5ecf80".to_owned(),
-        }];
-
-        let result = mint.create_blinded_signatures(&blinded_messages, &mint.keyset)?;
-
-        assert_eq!(1, result.len());
-        assert_eq!(8, result[0].amount);
-        assert_eq!(
-            dhke::public_key_from_hex(
-                "037074c4f53e326ee14ed67125f387d160e0e729351471b69ad41f7d5d21071e15"
-            ),
-            result[0].c_
-        );
+        let result = mint
+            .melt_bolt11(&quote.quote_id, &tokens.proofs(), None, &mint.keyset())
+            .await;
+        assert!(matches!(result, Err(MokshaMintError::QuoteAlreadyPaid(_))));
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_mint_empty() -> anyhow::Result<()> {
+    async fn test_melt_inputs_below_fee_reserve() -> anyhow::Result<()> {
         let docker = Cli::default();
         let image = create_postgres_image();
         let node = docker.run(image);
 
-        let mut lightning = MockLightning::new();
-        lightning.expect_is_invoice_paid().returning(|_| Ok(true));
         let mint = create_mint_from_mocks(
-            create_mock_db_pending_invoice(node.get_host_port_ipv4(5432)).await?,
-            Some(lightning),
+            create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+            Some(create_mock_lightning()),
         )
         .await?;
+        // 20 sat invoice + 50 sat fee reserve > 60 sat inputs
+        let quote = create_melt_quote(&mint.db, 50).await?;
+        let tokens = create_token_from_fixture("token_60.cashu").expect("can not read fixture");
 
-        let mut tx = mint.db.begin_tx().await?;
-        let outputs = vec![];
         let result = mint
-            .mint_tokens(
-                &mut tx,
-                moksha_core::primitives::PaymentMethod::Bolt11,
-                "somehash".to_string(),
-                &outputs,
-                &mint.keyset,
-                true,
-            )
-            .await?;
-        assert!(result.is_empty());
+            .melt_bolt11(&quote.quote_id, &tokens.proofs(), None, &mint.keyset())
+            .await;
+        assert!(matches!(result, Err(MokshaMintError::NotEnoughTokens(70))));
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_mint_valid() -> anyhow::Result<()> {
+    async fn test_melt_failed_payment_releases_proofs() -> anyhow::Result<()> {
         let docker = Cli::default();
         let image = create_postgres_image();
         let node = docker.run(image);
 
-        let mut lightning = MockLightning::new();
-        lightning.expect_is_invoice_paid().returning(|_| Ok(true));
+        let mut lightning = create_mock_lightning();
+        lightning.expect_pay_invoice().returning(|payment_request| {
+            Err(MokshaMintError::PayInvoice(
+                payment_request,
+                LightningError::PaymentFailed,
+            ))
+        });
+        lightning
+            .expect_payment_status()
+            .returning(|_| Ok(PaymentStatus::Failed));
+
         let mint = create_mint_from_mocks(
-            create_mock_db_pending_invoice(node.get_host_port_ipv4(5432)).await?,
+            create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
             Some(lightning),
         )
         .await?;
+        let quote = create_melt_quote(&mint.db, 4).await?;
+        let tokens = create_token_from_fixture("token_60.cashu").expect("can not read fixture");
 
-        let outputs = read_fixture_as::<Vec<BlindedMessage>>("blinded_messages_40.json")?;
-        let mut tx = mint.db.begin_tx().await?;
         let result = mint
-            .mint_tokens(
-                &mut tx,
-                moksha_core::primitives::PaymentMethod::Bolt11,
-                "somehash".to_string(),
-                &outputs,
-                &mint.keyset,
-                true,
-            )
-            .await?;
-        assert_eq!(40, result.total_amount());
+            .melt_bolt11(&quote.quote_id, &tokens.proofs(), None, &mint.keyset())
+            .await;
+        assert!(matches!(result, Err(MokshaMintError::PayInvoice(_, _))));
+
+        let states = mint.check_state(&proof_ys(&tokens.proofs())?).await?;
+        assert!(states.iter().all(|state| state.state == State::Unspent));
+        let mut tx = mint.db.begin_tx().await?;
+        assert!(mint.db.get_pending_quote_ids(&mut tx).await?.is_empty());
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_swap_zero() -> anyhow::Result<()> {
+    async fn test_melt_unknown_payment_keeps_proofs_pending() -> anyhow::Result<()> {
         let docker = Cli::default();
         let image = create_postgres_image();
         let node = docker.run(image);
 
-        let blinded_messages = vec![];
+        let mut lightning = create_mock_lightning();
+        lightning.expect_pay_invoice().returning(|payment_request| {
+            Err(MokshaMintError::PayInvoice(
+                payment_request,
+                LightningError::PaymentFailed,
+            ))
+        });
+        lightning
+            .expect_payment_status()
+            .returning(|_| Ok(PaymentStatus::Unknown));
+
         let mint = create_mint_from_mocks(
             create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
-            None,
+            Some(lightning),
         )
         .await?;
+        let quote = create_melt_quote(&mint.db, 4).await?;
+        let tokens = create_token_from_fixture("token_60.cashu").expect("can not read fixture");
+        let ys = proof_ys(&tokens.proofs())?;
 
-        let proofs = Proofs::empty();
-        let result = mint.swap(&proofs, &blinded_messages, &mint.keyset).await?;
+        let result = mint
+            .melt_bolt11(&quote.quote_id, &tokens.proofs(), None, &mint.keyset())
+            .await;
+        assert!(matches!(result, Err(MokshaMintError::PayInvoice(_, _))));
+        let states = mint.check_state(&ys).await?;
+        assert!(states.iter().all(|state| state.state == State::Pending));
 
-        assert!(result.is_empty());
+        // the payment may still settle, so reconciliation keeps the proofs pending as well
+        mint.reconcile_pending_melts().await?;
+        let states = mint.check_state(&ys).await?;
+        assert!(states.iter().all(|state| state.state == State::Pending));
+        let mut tx = mint.db.begin_tx().await?;
+        assert_eq!(
+            vec![quote.quote_id],
+            mint.db.get_pending_quote_ids(&mut tx).await?
+        );
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_swap_64_in_20() -> anyhow::Result<()> {
+    async fn test_melt_pending_payment_reconciled_as_paid() -> anyhow::Result<()> {
         let docker = Cli::default();
         let image = create_postgres_image();
         let node = docker.run(image);
 
+        let mut lightning = create_mock_lightning();
+        lightning.expect_pay_invoice().returning(|payment_request| {
+            Err(MokshaMintError::PayInvoice(
+                payment_request,
+                LightningError::PaymentFailed,
+            ))
+        });
+        lightning
+            .expect_payment_status()
+            .times(1)
+            .returning(|_| Ok(PaymentStatus::Pending));
+        lightning
+            .expect_payment_status()
+            .times(1)
+            .returning(|_| Ok(PaymentStatus::Paid { total_fees: 1 }));
+
         let mint = create_mint_from_mocks(
             create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
-            None,
+            Some(lightning),
         )
         .await?;
-        let request = read_fixture_as::<PostSwapRequest>("post_swap_request_64_20.json")?;
+        let quote = create_melt_quote(&mint.db, 4).await?;
+        let tokens = create_token_from_fixture("token_60.cashu").expect("can not read fixture");
+        let ys = proof_ys(&tokens.proofs())?;
 
         let result = mint
-            .swap(&request.inputs, &request.outputs, &mint.keyset)
-            .await?;
-        assert_eq!(result.total_amount(), 64);
+            .melt_bolt11(&quote.quote_id, &tokens.proofs(), None, &mint.keyset())
+            .await;
+        assert!(result.is_err());
+        let states = mint.check_state(&ys).await?;
+        assert!(states.iter().all(|state| state.state == State::Pending));
 
-        let prv_last = result.get(result.len() - 2).expect("element not found");
-        let last = result.last().expect("element not found");
+        // neither the quote nor the proofs can be used again while the payment is in flight
+        let result = mint
+            .melt_bolt11(&quote.quote_id, &tokens.proofs(), None, &mint.keyset())
+            .await;
+        assert!(matches!(result, Err(MokshaMintError::QuotePending(_))));
+        let other_quote = create_melt_quote(&mint.db, 4).await?;
+        let result = mint
+            .melt_bolt11(
+                &other_quote.quote_id,
+                &tokens.proofs(),
+                None,
+                &mint.keyset(),
+            )
+            .await;
+        assert!(matches!(result, Err(MokshaMintError::ProofPending(_))));
 
-        assert_eq!(prv_last.amount, 4);
-        assert_eq!(last.amount, 16);
+        mint.reconcile_pending_melts().await?;
+
+        let states = mint.check_state(&ys).await?;
+        assert!(states.iter().all(|state| state.state == State::Spent));
+        let mut tx = mint.db.begin_tx().await?;
+        assert!(
+            mint.db
+                .get_bolt11_melt_quote(&mut tx, &quote.quote_id)
+                .await?
+                .paid
+        );
+        assert!(mint.db.get_pending_quote_ids(&mut tx).await?.is_empty());
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_swap_duplicate_key() -> anyhow::Result<()> {
+    async fn test_melt_pending_payment_reconciled_as_failed() -> anyhow::Result<()> {
         let docker = Cli::default();
         let image = create_postgres_image();
         let node = docker.run(image);
+
+        let mut lightning = create_mock_lightning();
+        lightning.expect_pay_invoice().returning(|payment_request| {
+            Err(MokshaMintError::PayInvoice(
+                payment_request,
+                LightningError::PaymentFailed,
+            ))
+        });
+        lightning
+            .expect_payment_status()
+            .times(1)
+            .returning(|_| Ok(PaymentStatus::Pending));
+        lightning
+            .expect_payment_status()
+            .times(1)
+            .returning(|_| Ok(PaymentStatus::Failed));
+
         let mint = create_mint_from_mocks(
             create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
-            None,
+            Some(lightning),
         )
         .await?;
-        let request = read_fixture_as::<PostSwapRequest>("post_swap_request_duplicate_key.json")?;
+        let quote = create_melt_quote(&mint.db, 4).await?;
+        let tokens = create_token_from_fixture("token_60.cashu").expect("can not read fixture");
 
         let result = mint
-            .swap(&request.inputs, &request.outputs, &mint.keyset)
+            .melt_bolt11(&quote.quote_id, &tokens.proofs(), None, &mint.keyset())
             .await;
         assert!(result.is_err());
+
+        mint.reconcile_pending_melts().await?;
+
+        let states = mint.check_state(&proof_ys(&tokens.proofs())?).await?;
+        assert!(states.iter().all(|state| state.state == State::Unspent));
+        let mut tx = mint.db.begin_tx().await?;
+        assert!(
+            !mint
+                .db
+                .get_bolt11_melt_quote(&mut tx, &quote.quote_id)
+                .await?
+                .paid
+        );
         Ok(())
     }
 
     #[tokio::test]
-    /// melt 20 sats with 60 tokens and receive 40 tokens as change
-    async fn test_melt_overpay() -> anyhow::Result<()> {
-        use lightning_invoice::Bolt11Invoice as LNInvoice;
+    async fn test_melt_onchain_failed_send_releases_proofs() -> anyhow::Result<()> {
         let docker = Cli::default();
         let image = create_postgres_image();
         let node = docker.run(image);
 
-        let mut lightning = MockLightning::new();
+        let mut onchain = MockBtcOnchain::default();
+        onchain
+            .expect_send_coins()
+            .returning(|_, _, _, _| Err(MokshaMintError::Lightning(LightningError::PaymentFailed)));
+        onchain
+            .expect_find_sent_transaction()
+            .returning(|_| Ok(None));
 
-        lightning.expect_decode_invoice().returning(|_| {
-            Ok(
-                // 20 sat
-                LNInvoice::from_str("lnbc200n1pj9eanxsp5agdl4rd0twdljpcgmg67dwj9mseu5m4lwfhslkws4uh4m5f5pcrqpp5lvspx676rykr64l02s97wjztcxe355qck0naydrsvvkqw42cc35sdq2f38xy6t5wvxqzjccqpjrzjq027t9tsc6jn5ve2k6gnn689unn8h239juuf9s3ce09aty6ed73t5z7nqsqqsygqqyqqqqqqqqqqqqgq9q9qyysgqs5msn4j9v53fq000zhw0gulkcx2dlnfdt953v2ur7z765jj3m0fx6cppkpjwntq5nsqm273u4eevva508pvepg8mh27sqcd29sfjr4cq255a40").expect("invalid invoice")
+        let mint = Mint {
+            onchain: Some(Arc::new(onchain)),
+            ..create_mint_from_mocks(
+                create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+                None,
             )
-        });
-        lightning.expect_pay_invoice().returning(|_| {
-            Ok(PayInvoiceResult {
-                payment_hash: "hash".to_string(),
-                total_fees: 2,
-            })
-            .map_err(|_err: LightningError| MokshaMintError::InvoiceNotFound("".to_string()))
-        });
+            .await?
+        };
+        let quote = create_onchain_melt_quote();
+        let tokens = create_token_from_fixture("token_60.cashu").expect("can not read fixture");
 
-        let db = create_mock_db_empty(node.get_host_port_ipv4(5432)).await?;
+        let result = mint.melt_onchain(&quote, &tokens.proofs()).await;
+        assert!(result.is_err());
 
-        let mint = Mint::new(
-            // "TEST_PRIVATE_KEY".to_string(),
-            // "0/0/0/0".to_string(),
-            Arc::new(lightning),
-            LightningType::Lnbits(Default::default()),
-            db,
-            Default::default(),
-            Default::default(),
-            Some(Arc::new(MockBtcOnchain::default())),
-        );
+        let states = mint.check_state(&proof_ys(&tokens.proofs())?).await?;
+        assert!(states.iter().all(|state| state.state == State::Unspent));
+        Ok(())
+    }
 
-        let tokens = create_token_from_fixture("token_60.cashu").expect("can not read fixture");
-        let invoice = "some invoice".to_string();
-        let change = read_fixture_as::<Vec<BlindedMessage>>("blinded_messages_blank_4000.json")?;
+    #[tokio::test]
+    async fn test_melt_onchain_unknown_send_reconciled_as_sent() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
 
-        let mut tx = mint.db.begin_tx().await?;
-        let (paid, _payment_hash, change) = mint
-            .melt_bolt11(
-                &mut tx,
-                invoice,
-                4,
-                &tokens.proofs(),
-                Some(change),
-                &mint.keyset,
+        let mut onchain = MockBtcOnchain::default();
+        onchain
+            .expect_send_coins()
+            .returning(|_, _, _, _| Err(MokshaMintError::Lightning(LightningError::PaymentFailed)));
+        onchain
+            .expect_find_sent_transaction()
+            .times(1)
+            .returning(|_| Err(MokshaMintError::Lightning(LightningError::PaymentFailed)));
+        onchain
+            .expect_find_sent_transaction()
+            .times(1)
+            .returning(|label| Ok(Some(format!("txid of {label}"))));
+
+        let mint = Mint {
+            onchain: Some(Arc::new(onchain)),
+            ..create_mint_from_mocks(
+                create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+                None,
             )
-            .await?;
+            .await?
+        };
+        let quote = create_onchain_melt_quote();
+        let mut tx = mint.db.begin_tx().await?;
+        mint.db.add_onchain_melt_quote(&mut tx, &quote).await?;
+        tx.commit().await?;
+        let tokens = create_token_from_fixture("token_60.cashu").expect("can not read fixture");
+        let ys = proof_ys(&tokens.proofs())?;
 
-        assert!(paid);
-        assert!(change.total_amount() == 2);
+        // the wallet can not tell if the coins were sent, the inputs stay pending
+        let result = mint.melt_onchain(&quote, &tokens.proofs()).await;
+        assert!(result.is_err());
+        let states = mint.check_state(&ys).await?;
+        assert!(states.iter().all(|state| state.state == State::Pending));
+
+        mint.reconcile_pending_melts().await?;
+
+        let states = mint.check_state(&ys).await?;
+        assert!(states.iter().all(|state| state.state == State::Spent));
+        let mut tx = mint.db.begin_tx().await?;
+        assert!(mint.db.get_pending_quote_ids(&mut tx).await?.is_empty());
         Ok(())
     }
 
+    fn create_onchain_melt_quote() -> BtcOnchainMeltQuote {
+        BtcOnchainMeltQuote {
+            quote_id: Uuid::new_v4(),
+            amount: 20,
+            address: "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4".to_owned(),
+            fee_total: 0,
+            fee_sat_per_vbyte: 1,
+            expiry: 0,
+            paid: false,
+            description: "".to_owned(),
+        }
+    }
+
+    const INVOICE_20_SAT: &str = "lnbc200n1pj9eanxsp5agdl4rd0twdljpcgmg67dwj9mseu5m4lwfhslkws4uh4m5f5pcrqpp5lvspx676rykr64l02s97wjztcxe355qck0naydrsvvkqw42cc35sdq2f38xy6t5wvxqzjccqpjrzjq027t9tsc6jn5ve2k6gnn689unn8h239juuf9s3ce09aty6ed73t5z7nqsqqsygqqyqqqqqqqqqqqqgq9q9qyysgqs5msn4j9v53fq000zhw0gulkcx2dlnfdt953v2ur7z765jj3m0fx6cppkpjwntq5nsqm273u4eevva508pvepg8mh27sqcd29sfjr4cq255a40";
+
+    fn create_mock_lightning() -> MockLightning {
+        use lightning_invoice::Bolt11Invoice as LNInvoice;
+        let mut lightning = MockLightning::new();
+        lightning
+            .expect_decode_invoice()
+            .returning(|payment_request| {
+                Ok(LNInvoice::from_str(&payment_request).expect("invalid invoice"))
+            });
+        lightning
+    }
+
+    async fn create_melt_quote(
+        db: &PostgresDB,
+        fee_reserve: u64,
+    ) -> anyhow::Result<Bolt11MeltQuote> {
+        let quote = Bolt11MeltQuote {
+            quote_id: Uuid::new_v4(),
+            unit: CurrencyUnit::Sat,
+            amount: 20,
+            fee_reserve,
+            payment_request: INVOICE_20_SAT.to_owned(),
+            expiry: (Utc::now() + Duration::hours(1)).timestamp() as u64,
+            paid: false,
+        };
+        let mut tx = db.begin_tx().await?;
+        db.add_bolt11_melt_quote(&mut tx, &quote).await?;
+        tx.commit().await?;
+        Ok(quote)
+    }
+
+    fn proof_ys(proofs: &Proofs) -> anyhow::Result<Vec<PublicKey>> {
+        Ok(proofs
+            .proofs()
+            .iter()
+            .map(|proof| proof.y())
+            .collect::<Result<Vec<_>, _>>()?)
+    }
+
     fn create_token_from_fixture(fixture: &str) -> Result<TokenV3, anyhow::Error> {
         let base_dir = std::env::var("CARGO_MANIFEST_DIR")?;
         let raw_token = std::fs::read_to_string(format!("{base_dir}/src/fixtures/{fixture}"))?;
         Ok(raw_token.trim().to_string().try_into()?)
     }
 
+    fn bill_info(bill_id: &str, amount: u64, maturity_date: i64) -> BillInfo {
+        BillInfo {
+            id: bill_id.to_owned(),
+            amount,
+            maturity_date,
+            drawee_node_id: "drawee".to_owned(),
+            holder_node_id: "mint".to_owned(),
+            holder_public_key: "".to_owned(),
+            paid: false,
+        }
+    }
+
     async fn create_mint_from_mocks(
         mock_db: PostgresDB,
         mock_ln: Option<MockLightning>,
@@ -769,6 +4621,7 @@ mod tests {
             },
             Default::default(),
             Some(Arc::new(MockBtcOnchain::default())),
+            Arc::new(MockBillRegistry::new()),
         ))
     }
 
@@ -784,7 +4637,9 @@ mod tests {
         Ok(db)
     }
 
-    async fn create_mock_db_pending_invoice(port: u16) -> anyhow::Result<PostgresDB> {
+    const MINT_QUOTE_ID: &str = "8c6f0b2e-4d1a-4c3b-9f1e-2a7d5e6b3c10";
+
+    async fn create_mock_db_pending_invoice(port: u16, expiry: i64) -> anyhow::Result<PostgresDB> {
         let db = create_mock_db_empty(port).await?;
 
         let mut tx = db.begin_tx().await?;
@@ -792,8 +4647,19 @@ mod tests {
             amount: 100,
             payment_request: "lnbcrt1u1pjgamjepp5cr2dzhcuy9tjwl7u45kxa9h02khvsd2a7f2x9yjxgst8trduld4sdqqcqzzsxqyz5vqsp5kaclwkq79ylef295qj7x6c9kvhaq6272ge4tgz7stlzv46csrzks9qyyssq9szxlvhh0uen2jmh07hp242nj5529wje3x5e434kepjzeqaq5hnsje8rzrl97s0j8cxxt3kgz5gfswrrchr45u8fq3twz2jjc029klqpd6jmgv".to_string(),
         };
-        db.add_pending_invoice(&mut tx, "somehash".to_string(), &invoice)
+        db.add_pending_invoice(&mut tx, MINT_QUOTE_ID.to_string(), &invoice)
             .await?;
+        db.add_bolt11_mint_quote(
+            &mut tx,
+            &Bolt11MintQuote {
+                quote_id: Uuid::from_str(MINT_QUOTE_ID)?,
+                unit: CurrencyUnit::Sat,
+                payment_request: invoice.payment_request.clone(),
+                expiry: (Utc::now().timestamp() + expiry) as u64,
+                paid: false,
+            },
+        )
+        .await?;
         tx.commit().await?;
         Ok(db)
     }
@@ -1,3 +1,10 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use moksha_core::{
+    keyset::{Keyset, MintKeyset},
+    primitives::{BitcreditRequestToMint, CurrencyUnit},
+};
+use secp256k1::PublicKey;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -15,6 +22,152 @@ impl Invoice {
     }
 }
 
+/// A keyset of the mint as it is persisted in the database. The private keys are not stored,
+/// they are derived from the mint private key and the `derivation_path`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MintKeysetInfo {
+    pub id: String,
+    pub unit: CurrencyUnit,
+    pub derivation_path: String,
+    pub active: bool,
+    /// fee per input in parts per thousand (Nut-02)
+    pub input_fee_ppk: u64,
+    /// unix timestamp in seconds
+    pub created_at: i64,
+}
+
+impl From<MintKeysetInfo> for Keyset {
+    fn from(keyset: MintKeysetInfo) -> Self {
+        Self {
+            id: keyset.id,
+            unit: keyset.unit,
+            active: keyset.active,
+            input_fee_ppk: keyset.input_fee_ppk,
+            maturity_date: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BillKeysetStatus {
+    /// tokens of the bill can be minted and swapped
+    Active,
+    /// the drawee paid the bill
+    Settled,
+    /// the bill was not paid at maturity
+    Defaulted,
+}
+
+impl fmt::Display for BillKeysetStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Active => write!(f, "active"),
+            Self::Settled => write!(f, "settled"),
+            Self::Defaulted => write!(f, "defaulted"),
+        }
+    }
+}
+
+impl FromStr for BillKeysetStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "active" => Ok(Self::Active),
+            "settled" => Ok(Self::Settled),
+            "defaulted" => Ok(Self::Defaulted),
+            _ => Err(format!("unknown bill keyset status {s}")),
+        }
+    }
+}
+
+/// The keyset of a bitcredit bill as it is persisted in the database. The private keys are not
+/// stored, they are derived from the key of the bill in its request to mint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BillKeysetInfo {
+    pub id: String,
+    pub bill_id: String,
+    pub public_keys: HashMap<u64, PublicKey>,
+    /// unix timestamp in seconds
+    pub maturity_date: i64,
+    /// amount of the bill in sat
+    pub face_value: u64,
+    /// sum of all tokens issued for the bill in sat
+    pub amount_minted: u64,
+    pub status: BillKeysetStatus,
+    /// unix timestamp in seconds
+    pub created_at: i64,
+}
+
+impl BillKeysetInfo {
+    pub fn new(
+        request_to_mint: &BitcreditRequestToMint,
+        keyset: &MintKeyset,
+        created_at: i64,
+    ) -> Self {
+        Self {
+            id: keyset.keyset_id.clone(),
+            bill_id: request_to_mint.bill_id.clone(),
+            public_keys: keyset.public_keys.clone(),
+            maturity_date: request_to_mint.maturity_date,
+            face_value: request_to_mint.bill_amount,
+            amount_minted: 0,
+            status: BillKeysetStatus::Active,
+            created_at,
+        }
+    }
+}
+
+impl From<BillKeysetInfo> for Keyset {
+    fn from(keyset: BillKeysetInfo) -> Self {
+        Self {
+            id: keyset.id,
+            unit: CurrencyUnit::CrSat,
+            active: keyset.status == BillKeysetStatus::Active,
+            input_fee_ppk: 0,
+            maturity_date: Some(keyset.maturity_date),
+        }
+    }
+}
+
+/// A bill keyset with its private keys, as it is cached by the mint
+#[derive(Debug, Clone)]
+pub struct BillKeyset {
+    pub bill_id: String,
+    /// unix timestamp in seconds
+    pub maturity_date: i64,
+    pub keyset: MintKeyset,
+}
+
+impl BillKeyset {
+    pub fn new(request_to_mint: &BitcreditRequestToMint) -> Self {
+        Self {
+            bill_id: request_to_mint.bill_id.clone(),
+            maturity_date: request_to_mint.maturity_date,
+            keyset: MintKeyset::new_with_id(
+                &request_to_mint.bill_key,
+                "",
+                request_to_mint.bill_id.clone(),
+            ),
+        }
+    }
+}
+
+/// A payment from the treasury of a bill that has been started but not settled. The invoice
+/// amount and the fee reserve stay reserved until the payment succeeds or fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreasuryMelt {
+    pub payment_hash: String,
+    pub bill_id: String,
+    pub payment_request: String,
+    /// amount of the invoice in sat
+    pub amount: u64,
+    /// fee reserve in sat
+    pub fee_reserve: u64,
+    /// unix timestamp in seconds
+    pub created_at: u64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateInvoiceResult {
     pub payment_hash: Vec<u8>,
@@ -0,0 +1,150 @@
+//! Prices bills that are minted as `cr-sat` tokens.
+//!
+//! The mint buys a bill at a discount on its face value. The discount grows with the days until the
+//! bill matures and with the risk of the drawee: every drawee belongs to a risk tier whose premium
+//! is added to the annual discount rate.
+
+use crate::{config::PricingConfig, error::MokshaMintError};
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+const DAYS_PER_YEAR: u128 = 365;
+/// rates are rounded to parts per million so that offers are computed with integers
+const RATE_PRECISION: f64 = 1_000_000.0;
+
+/// Returns the annual discount rate including the premium of the drawee's risk tier
+pub fn annual_rate(config: &PricingConfig, drawee_node_id: &str) -> Result<f64, MokshaMintError> {
+    let tier = config
+        .drawee_tiers
+        .iter()
+        .find(|drawee| drawee.drawee_node_id == drawee_node_id)
+        .map_or(config.default_risk_tier.as_str(), |drawee| {
+            drawee.tier.as_str()
+        });
+    let tier = config
+        .risk_tiers
+        .iter()
+        .find(|risk_tier| risk_tier.name == tier)
+        .ok_or_else(|| MokshaMintError::UnknownRiskTier(tier.to_owned()))?;
+    Ok(config.annual_discount_rate + tier.premium)
+}
+
+/// Returns the amount the mint offers for a bill. Started days until maturity count as full days,
+/// bills that are already mature are bought at their face value.
+pub fn offer(
+    config: &PricingConfig,
+    face_value: u64,
+    maturity_date: i64,
+    now: u64,
+    drawee_node_id: &str,
+) -> Result<u64, MokshaMintError> {
+    let rate = annual_rate(config, drawee_node_id)?;
+    let seconds = maturity_date.saturating_sub(now as i64).max(0);
+    let days = (seconds + SECONDS_PER_DAY - 1) / SECONDS_PER_DAY;
+    let rate_ppm = (rate * RATE_PRECISION).round().max(0.0) as u128;
+    let discount = (face_value as u128 * rate_ppm * days as u128)
+        .div_ceil(RATE_PRECISION as u128 * DAYS_PER_YEAR);
+    Ok(face_value.saturating_sub(discount.min(u64::MAX as u128) as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        config::{DraweeTier, PricingConfig, RiskTier},
+        error::MokshaMintError,
+        pricing::{annual_rate, offer},
+    };
+
+    const DAY: i64 = 24 * 60 * 60;
+
+    fn config() -> PricingConfig {
+        PricingConfig {
+            annual_discount_rate: 0.05,
+            risk_tiers: vec![
+                RiskTier {
+                    name: "default".to_owned(),
+                    premium: 0.0,
+                },
+                RiskTier {
+                    name: "high".to_owned(),
+                    premium: 0.1,
+                },
+            ],
+            drawee_tiers: vec![DraweeTier {
+                drawee_node_id: "risky_drawee".to_owned(),
+                tier: "high".to_owned(),
+            }],
+            default_risk_tier: "default".to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_annual_rate() -> anyhow::Result<()> {
+        let config = config();
+        assert_eq!(0.05, annual_rate(&config, "drawee")?);
+        assert!((annual_rate(&config, "risky_drawee")? - 0.15).abs() < f64::EPSILON);
+
+        let config = PricingConfig {
+            default_risk_tier: "unknown".to_owned(),
+            ..config
+        };
+        assert!(matches!(
+            annual_rate(&config, "drawee"),
+            Err(MokshaMintError::UnknownRiskTier(tier)) if tier == "unknown"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_offer() -> anyhow::Result<()> {
+        let config = config();
+        let now = 1_700_000_000;
+
+        // 5% for a year
+        assert_eq!(
+            95_000,
+            offer(&config, 100_000, now + 365 * DAY, now as u64, "drawee")?
+        );
+        // 15% for a year
+        assert_eq!(
+            85_000,
+            offer(
+                &config,
+                100_000,
+                now + 365 * DAY,
+                now as u64,
+                "risky_drawee"
+            )?
+        );
+        // a started day counts as a full day
+        assert_eq!(
+            offer(&config, 100_000, now + DAY, now as u64, "drawee")?,
+            offer(&config, 100_000, now + 1, now as u64, "drawee")?
+        );
+        assert_eq!(100_000, offer(&config, 100_000, now, now as u64, "drawee")?);
+        assert_eq!(
+            100_000,
+            offer(&config, 100_000, now - DAY, now as u64, "drawee")?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_tiers() {
+        assert_eq!(
+            Ok(RiskTier {
+                name: "high".to_owned(),
+                premium: 0.1
+            }),
+            "high:0.1".parse()
+        );
+        assert!("high".parse::<RiskTier>().is_err());
+        assert!("high:much".parse::<RiskTier>().is_err());
+        assert_eq!(
+            Ok(DraweeTier {
+                drawee_node_id: "node".to_owned(),
+                tier: "high".to_owned()
+            }),
+            "node:high".parse()
+        );
+    }
+}
@@ -13,7 +13,7 @@ use uuid::Uuid;
 
 use crate::database::Database;
 use crate::{error::MokshaMintError, mint::Mint};
-use chrono::{Duration, Utc};
+use chrono::Duration;
 use std::str::FromStr;
 
 #[utoipa::path(
@@ -29,10 +29,12 @@ pub async fn post_mint_quote_btconchain(
     State(mint): State<Mint>,
     Json(request): Json<PostMintQuoteBtcOnchainRequest>,
 ) -> Result<Json<PostMintQuoteBtcOnchainResponse>, MokshaMintError> {
-    let onchain_config = mint.config.btconchain_backend.unwrap_or_default();
+    let onchain_config = mint.config.btconchain_backend.clone().unwrap_or_default();
 
     if request.unit != CurrencyUnit::Sat {
-        return Err(MokshaMintError::CurrencyNotSupported(request.unit));
+        return Err(MokshaMintError::CurrencyNotSupported(
+            request.unit.to_string(),
+        ));
     }
 
     if request.amount < onchain_config.min_amount {
@@ -57,12 +59,13 @@ pub async fn post_mint_quote_btconchain(
         .new_address()
         .await?;
 
+    let expiry = quote_onchain_expiry(&mint).await?;
     let quote = BtcOnchainMintQuote {
         quote_id,
         address,
         unit: request.unit,
         amount: request.amount,
-        expiry: quote_onchain_expiry(),
+        expiry,
         paid: false,
     };
 
@@ -132,8 +135,7 @@ pub async fn post_mint_btconchain(
             PaymentMethod::BtcOnchain,
             request.quote.clone(),
             &request.outputs,
-            &mint.keyset,
-            false,
+            &mint.keyset(),
         )
         .await?;
 
@@ -174,10 +176,10 @@ pub async fn post_melt_quote_btconchain(
         unit,
     } = melt_request;
 
-    let onchain_config = mint.config.btconchain_backend.unwrap_or_default();
+    let onchain_config = mint.config.btconchain_backend.clone().unwrap_or_default();
 
     if unit != CurrencyUnit::Sat {
-        return Err(MokshaMintError::CurrencyNotSupported(unit));
+        return Err(MokshaMintError::CurrencyNotSupported(unit.to_string()));
     }
 
     if amount < onchain_config.min_amount {
@@ -203,13 +205,14 @@ pub async fn post_melt_quote_btconchain(
 
     info!("post_melt_quote_onchain fee_reserve: {:#?}", &fee_response);
 
+    let expiry = quote_onchain_expiry(&mint).await?;
     let quote = BtcOnchainMeltQuote {
         quote_id: Uuid::new_v4(),
         address,
         amount,
         fee_total: fee_response.fee_in_sat,
         fee_sat_per_vbyte: fee_response.sat_per_vbyte,
-        expiry: quote_onchain_expiry(),
+        expiry,
         paid: false,
         description: format!("{} sat per vbyte", fee_response.sat_per_vbyte),
     };
@@ -333,8 +336,8 @@ async fn is_onchain_paid(
         .await
 }
 
-fn quote_onchain_expiry() -> u64 {
+async fn quote_onchain_expiry(mint: &Mint) -> Result<u64, MokshaMintError> {
     // FIXME add config option for expiry
-    let now = Utc::now() + Duration::try_minutes(5).expect("invalid duration");
-    now.timestamp() as u64
+    let expiry = Duration::try_minutes(5).expect("invalid duration");
+    Ok(mint.clock.now().await? + expiry.num_seconds() as u64)
 }
@@ -3,18 +3,17 @@ use axum::{
     Json,
 };
 use moksha_core::{
-    keyset::Keysets,
+    keyset::{Keyset, Keysets},
     primitives::{
         Bolt11MeltQuote, Bolt11MintQuote, CurrencyUnit, KeyResponse, KeysResponse,
-        MintInfoResponse, Nuts, PaymentMethod, PostMeltBolt11Request, PostMeltBolt11Response,
-        PostMeltQuoteBolt11Request, PostMeltQuoteBolt11Response, PostMintBolt11Request,
-        PostMintBolt11Response, PostMintQuoteBolt11Request, PostMintQuoteBolt11Response,
-        PostSwapRequest, PostSwapResponse,
+        MintInfoResponse, Nut10, Nut11, Nut12, Nut14, Nut4, Nut5, Nut7, Nut9, Nuts, PaymentMethod,
+        PaymentMethodConfig, PostCheckStateRequest, PostCheckStateResponse, PostMeltBolt11Request,
+        PostMeltBolt11Response, PostMeltQuoteBolt11Request, PostMeltQuoteBolt11Response,
+        PostMintBolt11Request, PostMintBolt11Response, PostMintQuoteBolt11Request,
+        PostMintQuoteBolt11Response, PostRestoreRequest, PostRestoreResponse, PostSwapRequest,
+        PostSwapResponse,
     },
 };
-use std::fs::File;
-use std::io::Write;
-use std::path::PathBuf;
 use tracing::{debug, instrument};
 use uuid::Uuid;
 
@@ -22,34 +21,17 @@ use crate::database::Database;
 use crate::{
     config::{BtcOnchainConfig, MintConfig},
     error::MokshaMintError,
-    mint::Mint,
-    time,
+    mint::{msat_to_unit, unit_to_msat, Mint},
 };
-use chrono::{Duration, Utc};
-use moksha_core::amount::Amount;
-use moksha_core::blind::{BlindedMessage, BlindedSignature};
-use moksha_core::keyset::{KeysetId, MintKeyset};
-use moksha_core::primitives::CurrencyUnit::CrSat;
+use chrono::Duration;
 use moksha_core::primitives::{
-    BillKeys, BitcreditMintQuote, BitcreditQuoteCheck, BitcreditRequestToMint,
-    CheckBitcreditQuoteResponse, ParamsBitcreditGetKeysetsById, ParamsBitcreditQuoteCheck,
-    ParamsGetKeys, PostMintBitcreditRequest, PostMintBitcreditResponse,
-    PostMintQuoteBitcreditRequest, PostMintQuoteBitcreditResponse,
+    BitcreditQuoteCheck, CheckBitcreditQuoteResponse, GetMintQuoteBitcreditResponse,
+    ParamsBitcreditGetKeysetsById, ParamsBitcreditQuoteCheck, ParamsGetKeys,
+    PostMintBitcreditRequest, PostMintBitcreditResponse, PostMintQuoteBitcreditRequest,
+    PostMintQuoteBitcreditResponse, PostRedeemBitcreditRequest, PostRedeemBitcreditResponse,
     PostRequestToMintBitcreditRequest, PostRequestToMintBitcreditResponse,
 };
-use moksha_core::proof::Proof;
-use moksha_core::token::TokenV3;
-use moksha_wallet::error::MokshaWalletError;
-use moksha_wallet::http::CrossPlatformHttpClient;
-use moksha_wallet::localstore::sqlite::SqliteLocalStore;
-use moksha_wallet::localstore::{LocalStore, WalletKeyset};
-use moksha_wallet::wallet::Wallet;
 use std::str::FromStr;
-use std::{fs, thread};
-use tracing::log::error;
-use url::Url;
-
-pub const MINT_URL: &str = "http://127.0.0.1:3338";
 
 #[utoipa::path(
         post,
@@ -64,8 +46,9 @@ pub async fn post_swap(
     State(mint): State<Mint>,
     Json(swap_request): Json<PostSwapRequest>,
 ) -> Result<Json<PostSwapResponse>, MokshaMintError> {
+    let keyset = mint.swap_keyset(&swap_request.outputs).await?;
     let response = mint
-        .swap(&swap_request.inputs, &swap_request.outputs, &mint.keyset)
+        .swap(&swap_request.inputs, &swap_request.outputs, &keyset)
         .await?;
 
     Ok(Json(PostSwapResponse {
@@ -146,6 +129,43 @@ pub async fn mjk_get_keys_by_id(
     get_keys_by_id(params, state).await
 }
 
+#[utoipa::path(
+        post,
+        path = "/v1/checkstate",
+        request_body = PostCheckStateRequest,
+        responses(
+            (status = 200, description = "post checkstate", body = [PostCheckStateResponse])
+        ),
+)]
+#[instrument(name = "post_checkstate", skip(mint), err)]
+pub async fn post_checkstate(
+    State(mint): State<Mint>,
+    Json(request): Json<PostCheckStateRequest>,
+) -> Result<Json<PostCheckStateResponse>, MokshaMintError> {
+    let states = mint.check_state(&request.ys).await?;
+    Ok(Json(PostCheckStateResponse { states }))
+}
+
+#[utoipa::path(
+        post,
+        path = "/v1/restore",
+        request_body = PostRestoreRequest,
+        responses(
+            (status = 200, description = "post restore", body = [PostRestoreResponse])
+        ),
+)]
+#[instrument(name = "post_restore", skip(mint), err)]
+pub async fn post_restore(
+    State(mint): State<Mint>,
+    Json(request): Json<PostRestoreRequest>,
+) -> Result<Json<PostRestoreResponse>, MokshaMintError> {
+    let (outputs, signatures) = mint.restore(&request.outputs).await?;
+    Ok(Json(PostRestoreResponse {
+        outputs,
+        signatures,
+    }))
+}
+
 #[utoipa::path(
         post,
         path = "/{id}/{unit}/v1/swap",
@@ -164,43 +184,41 @@ pub async fn mjk_post_swap(
     State(mint): State<Mint>,
     Json(swap_request): Json<PostSwapRequest>,
 ) -> Result<Json<PostSwapResponse>, MokshaMintError> {
-    let mut tx = mint.db.begin_tx().await?;
-    let request_to_mint = &mint
-        .db
-        .get_bitcredit_request_to_mint(&mut tx, &params.id)
+    // matured tokens are exchanged for sat with post_redeem_bitcredit
+    let bill_keyset = mint.bill_keyset(&params.id).await?;
+    let response = mint
+        .swap(
+            &swap_request.inputs,
+            &swap_request.outputs,
+            &bill_keyset.keyset,
+        )
         .await?;
 
-    let keyset = MintKeyset::new_with_id(
-        request_to_mint.bill_key.as_str(),
-        String::default().as_str(),
-        params.id.clone(),
-    );
-
-    tx.commit().await?;
-
-    let current_timestamp = time::TimeApi::get_atomic_time().await.timestamp;
-
-    println!("Current timestamp: {}", current_timestamp);
-    println!("Maturity_date timestamp: {}", request_to_mint.maturity_date);
-
-    let response;
-
-    if request_to_mint.maturity_date <= current_timestamp as i64 {
-        //if credit keyset timestamp <= current timestamp --> return debit token
-        response = mint
-            .swap(&swap_request.inputs, &swap_request.outputs, &mint.keyset)
-            .await?;
-    } else {
-        response = mint
-            .swap(&swap_request.inputs, &swap_request.outputs, &keyset)
-            .await?;
-    }
-
     Ok(Json(PostSwapResponse {
         signatures: response,
     }))
 }
 
+#[utoipa::path(
+        post,
+        path = "/v1/redeem/bitcredit",
+        request_body = PostRedeemBitcreditRequest,
+        responses(
+            (status = 200, description = "redeem matured cr-sat for sat", body = [PostRedeemBitcreditResponse]),
+            (status = 400, description = "the bill is not mature, not paid yet or defaulted", body = [CashuErrorResponse])
+        ),
+)]
+#[instrument(name = "post_redeem_bitcredit", skip_all, err)]
+pub async fn post_redeem_bitcredit(
+    State(mint): State<Mint>,
+    Json(request): Json<PostRedeemBitcreditRequest>,
+) -> Result<Json<PostRedeemBitcreditResponse>, MokshaMintError> {
+    let signatures = mint
+        .redeem_bitcredit(&request.inputs, &request.outputs)
+        .await?;
+    Ok(Json(PostRedeemBitcreditResponse { signatures }))
+}
+
 #[utoipa::path(
     get,
     path = "/v1/keys/{unit}",
@@ -216,11 +234,13 @@ pub async fn get_keys(
     Path(unit): Path<String>,
     State(mint): State<Mint>,
 ) -> Result<Json<KeysResponse>, MokshaMintError> {
+    let unit = unit.parse::<CurrencyUnit>()?;
+    let keyset = mint.active_keyset(&unit)?;
     Ok(Json(KeysResponse {
         keysets: vec![KeyResponse {
-            id: mint.keyset.keyset_id.clone(),
-            unit: CurrencyUnit::from(unit),
-            keys: mint.keyset.public_keys,
+            id: keyset.keyset_id.clone(),
+            unit,
+            keys: keyset.public_keys.clone(),
         }],
     }))
 }
@@ -236,13 +256,19 @@ pub async fn get_keys(
 )]
 #[instrument(skip(mint), err)]
 pub async fn get_keys_old(State(mint): State<Mint>) -> Result<Json<KeysResponse>, MokshaMintError> {
-    Ok(Json(KeysResponse {
-        keysets: vec![KeyResponse {
-            id: mint.keyset.keyset_id.clone(),
-            unit: CurrencyUnit::Sat,
-            keys: mint.keyset.public_keys,
-        }],
-    }))
+    let keysets = mint
+        .units()
+        .into_iter()
+        .map(|unit| {
+            let keyset = mint.active_keyset(&unit)?;
+            Ok(KeyResponse {
+                id: keyset.keyset_id.clone(),
+                unit,
+                keys: keyset.public_keys.clone(),
+            })
+        })
+        .collect::<Result<Vec<_>, MokshaMintError>>()?;
+    Ok(Json(KeysResponse { keysets }))
 }
 
 #[utoipa::path(
@@ -261,39 +287,33 @@ pub async fn get_keys_by_id(
     params: Path<ParamsGetKeys>,
     State(mint): State<Mint>,
 ) -> Result<Json<KeysResponse>, MokshaMintError> {
-    if CurrencyUnit::from(params.unit.clone()).eq(&CurrencyUnit::CrSat) {
-        //TODO: add check if this keyset exist in database
-        let mut tx = mint.db.begin_tx().await?;
-        let request_to_mint = &mint
-            .db
-            .get_bitcredit_request_to_mint(&mut tx, &params.id)
-            .await?;
-
-        let keys = MintKeyset::new_with_id(
-            request_to_mint.bill_key.as_str(),
-            String::default().as_str(),
-            params.id.clone(),
-        );
-
-        tx.commit().await?;
+    let unit = params.unit.parse::<CurrencyUnit>()?;
+    if unit == CurrencyUnit::CrSat {
+        let keys = mint.bill_keyset(&params.id).await?.keyset;
 
         Ok(Json(KeysResponse {
             keysets: vec![KeyResponse {
                 id: keys.keyset_id.clone(),
-                unit: CurrencyUnit::from(params.unit.clone()),
+                unit,
                 keys: keys.public_keys,
             }],
         }))
     } else {
-        if params.id != mint.keyset.keyset_id {
+        if !mint
+            .get_keysets()
+            .await?
+            .iter()
+            .any(|keyset| keyset.id == params.id && keyset.unit == unit)
+        {
             return Err(MokshaMintError::KeysetNotFound(params.id.clone()));
         }
+        let keyset = mint.get_keyset(&params.id).await?;
 
         Ok(Json(KeysResponse {
             keysets: vec![KeyResponse {
-                id: mint.keyset.keyset_id.clone(),
-                unit: CurrencyUnit::from(params.unit.clone()),
-                keys: mint.keyset.public_keys,
+                id: keyset.keyset_id,
+                unit,
+                keys: keyset.public_keys,
             }],
         }))
     }
@@ -314,11 +334,27 @@ pub async fn get_keysets(
     Path(unit): Path<String>,
     State(mint): State<Mint>,
 ) -> Result<Json<Keysets>, MokshaMintError> {
-    Ok(Json(Keysets::new(
-        mint.keyset.keyset_id,
-        CurrencyUnit::from(unit),
-        true,
-    )))
+    let unit = unit.parse::<CurrencyUnit>()?;
+    if unit == CurrencyUnit::CrSat {
+        return Ok(Json(Keysets {
+            keysets: mint
+                .get_bill_keysets()
+                .await?
+                .into_iter()
+                .map(Keyset::from)
+                .collect(),
+        }));
+    }
+    mint.active_keyset(&unit)?;
+    Ok(Json(Keysets {
+        keysets: mint
+            .get_keysets()
+            .await?
+            .into_iter()
+            .filter(|keyset| keyset.unit == unit)
+            .map(Keyset::from)
+            .collect(),
+    }))
 }
 
 #[utoipa::path(
@@ -332,11 +368,14 @@ pub async fn get_keysets(
 )]
 #[instrument(skip(mint), err)]
 pub async fn get_keysets_old(State(mint): State<Mint>) -> Result<Json<Keysets>, MokshaMintError> {
-    Ok(Json(Keysets::new(
-        mint.keyset.keyset_id,
-        CurrencyUnit::Sat,
-        true,
-    )))
+    Ok(Json(Keysets {
+        keysets: mint
+            .get_keysets()
+            .await?
+            .into_iter()
+            .map(Keyset::from)
+            .collect(),
+    }))
 }
 
 //Bitcredit specific function
@@ -355,38 +394,21 @@ pub async fn get_keysets_by_id(
     params: Path<ParamsBitcreditGetKeysetsById>,
     State(mint): State<Mint>,
 ) -> Result<Json<Keysets>, MokshaMintError> {
-    let mut tx = mint.db.begin_tx().await?;
-    let request_to_mint = &mint
-        .db
-        .get_bitcredit_request_to_mint(&mut tx, &params.id)
-        .await?;
-
-    let keys = MintKeyset::new_with_id(
-        request_to_mint.bill_key.as_str(),
-        String::default().as_str(),
-        params.id.clone(),
-    );
-
-    //add maturity date in request to mint
-    //remove maturity date from get keysets and get keys
-    match mint
-        .db
-        .add_mint_keyset(&mut tx, &keys.keyset_id, &keys.mint_pubkey.to_string())
-        .await
-    {
-        Err(e) => {
-            error!("{}", e);
-        }
-        _ => {}
+    let unit = params.unit.parse::<CurrencyUnit>()?;
+    if unit != CurrencyUnit::CrSat {
+        return Err(MokshaMintError::UnitMismatch(unit.to_string()));
     }
-
-    tx.commit().await?;
-
-    Ok(Json(Keysets::new(
-        keys.keyset_id,
-        CurrencyUnit::from(params.unit.clone()),
-        true,
-    )))
+    let keyset_id = mint.bill_keyset(&params.id).await?.keyset.keyset_id;
+
+    Ok(Json(Keysets {
+        keysets: mint
+            .get_bill_keysets()
+            .await?
+            .into_iter()
+            .filter(|keyset| keyset.id == keyset_id)
+            .map(Keyset::from)
+            .collect(),
+    }))
 }
 
 #[utoipa::path(
@@ -402,14 +424,25 @@ pub async fn post_mint_quote_bolt11(
     State(mint): State<Mint>,
     Json(request): Json<PostMintQuoteBolt11Request>,
 ) -> Result<Json<PostMintQuoteBolt11Response>, MokshaMintError> {
-    // FIXME check currency unit
+    mint.active_keyset(&request.unit)?;
+    let amount_msat = unit_to_msat(&request.unit, request.amount)?;
+    // the lightning backends create invoices in sat
+    if amount_msat % 1_000 != 0 {
+        return Err(MokshaMintError::InvalidAmount(format!(
+            "{} {} is not a whole sat amount",
+            request.amount, request.unit
+        )));
+    }
     let key = Uuid::new_v4();
-    let (pr, _hash) = mint.create_invoice(key.to_string(), request.amount).await?;
+    let (pr, _hash) = mint
+        .create_invoice(key.to_string(), amount_msat / 1_000)
+        .await?;
 
     let quote = Bolt11MintQuote {
         quote_id: key,
+        unit: request.unit,
         payment_request: pr.clone(),
-        expiry: quote_expiry(), // FIXME use timestamp type in DB
+        expiry: quote_expiry(&mint).await?, // FIXME use timestamp type in DB
         paid: false,
     };
 
@@ -433,20 +466,9 @@ pub async fn post_mint_quote_bitcredit(
     Json(request): Json<PostMintQuoteBitcreditRequest>,
 ) -> Result<Json<PostMintQuoteBitcreditResponse>, MokshaMintError> {
     // FIXME check currency unit
-    let key = Uuid::new_v4();
-
-    let quote = BitcreditMintQuote {
-        quote_id: key,
-        bill_id: request.bill_id,
-        node_id: request.node_id,
-        sent: false,
-        amount: request.amount,
-        endorsed: false,
-    };
-
-    let mut tx = mint.db.begin_tx().await?;
-    mint.db.add_bitcredit_mint_quote(&mut tx, &quote).await?;
-    tx.commit().await?;
+    let quote = mint
+        .create_bitcredit_mint_quote(request.bill_id, request.node_id, request.amount)
+        .await?;
     Ok(Json(quote.into()))
 }
 
@@ -458,30 +480,13 @@ pub async fn post_mint_quote_bitcredit(
         (status = 200, description = "post request to mint", body = [PostRequestToMintBitcreditResponse])
     ),
 )]
-#[instrument(name = "post_request_to_mint_bitcredit", skip(mint), err)]
+#[instrument(name = "post_request_to_mint_bitcredit", fields(bill_id = %request.bill_id), skip_all, err)]
 pub async fn post_request_to_mint_bitcredit(
     State(mint): State<Mint>,
     Json(request): Json<PostRequestToMintBitcreditRequest>,
     //TODO: correct response
 ) -> Result<Json<PostRequestToMintBitcreditResponse>, MokshaMintError> {
-    let request_to_mint = BitcreditRequestToMint {
-        bill_key: request.bill_keys.private_key_pem.clone(),
-        bill_id: request.bill_id.clone(),
-        maturity_date: request.maturity_date.clone(),
-        bill_amount: request.bill_amount,
-    };
-
-    write_bill_keys_to_file(
-        request.bill_id,
-        request.bill_keys.private_key_pem,
-        request.bill_keys.public_key_pem,
-    );
-
-    let mut tx = mint.db.begin_tx().await?;
-    mint.db
-        .add_bitcredit_request_to_mint(&mut tx, &request_to_mint)
-        .await?;
-    tx.commit().await?;
+    let request_to_mint = mint.request_to_mint(&request).await?;
     Ok(Json(request_to_mint.into()))
 }
 
@@ -502,22 +507,20 @@ pub async fn post_mint_bolt11(
     Json(request): Json<PostMintBolt11Request>,
 ) -> Result<Json<PostMintBolt11Response>, MokshaMintError> {
     let mut tx = mint.db.begin_tx().await?;
+    let old_quote = &mint
+        .db
+        .get_bolt11_mint_quote(&mut tx, &Uuid::from_str(request.quote.as_str())?)
+        .await?;
     let signatures = mint
         .mint_tokens(
             &mut tx,
             PaymentMethod::Bolt11,
             request.quote.clone(),
             &request.outputs,
-            &mint.keyset,
-            false,
+            &mint.active_keyset(&old_quote.unit)?,
         )
         .await?;
 
-    let old_quote = &mint
-        .db
-        .get_bolt11_mint_quote(&mut tx, &Uuid::from_str(request.quote.as_str())?)
-        .await?;
-
     mint.db
         .update_bolt11_mint_quote(
             &mut tx,
@@ -548,17 +551,12 @@ pub async fn post_mint_bitcredit(
     State(mint): State<Mint>,
     Json(request): Json<PostMintBitcreditRequest>,
 ) -> Result<Json<PostMintBitcreditResponse>, MokshaMintError> {
-    let mut tx = mint.db.begin_tx().await?;
-
     let quote = mint
-        .db
-        .get_bitcredit_mint_quote(&mut tx, &Uuid::from_str(request.quote.as_str())?)
+        .get_bitcredit_mint_quote(&Uuid::from_str(request.quote.as_str())?)
         .await?;
+    let bill_keyset = mint.bill_keyset(&quote.bill_id).await?;
 
-    let request_to_mint = mint
-        .db
-        .get_bitcredit_request_to_mint(&mut tx, &quote.bill_id)
-        .await?;
+    let mut tx = mint.db.begin_tx().await?;
 
     let signatures = mint
         .mint_tokens(
@@ -566,197 +564,14 @@ pub async fn post_mint_bitcredit(
             PaymentMethod::Bitcredit,
             request.quote.clone(),
             &request.outputs,
-            &mint.keyset,
-            false,
+            &bill_keyset.keyset,
         )
         .await?;
 
-    let mut mint_clone = mint.clone();
-    mint_clone.keyset = mint.keyset;
-    let fee_amount = Amount(request_to_mint.bill_amount - quote.amount);
-    let fee_amount_to_show = fee_amount.clone();
-    let token =
-        thread::spawn(move || generate_mint_fee(fee_amount, mint_clone, quote.bill_id.clone()))
-            .join()
-            .expect("Thread panicked");
-    println!("AMOUNT FOR WILDCAT FEE: {:#?}", fee_amount_to_show);
-    println!("DISCOUNT FEE FOR WILDCAT: {:#?}", token);
-
-    let old_quote = &mint
-        .db
-        .get_bitcredit_mint_quote(&mut tx, &Uuid::from_str(request.quote.as_str())?)
-        .await?;
-
-    mint.db
-        .update_bitcredit_mint_quote(
-            &mut tx,
-            &BitcreditMintQuote {
-                endorsed: true,
-                sent: true,
-                ..old_quote.clone()
-            },
-        )
-        .await?;
     tx.commit().await?;
     Ok(Json(PostMintBitcreditResponse { signatures }))
 }
 
-#[tokio::main]
-async fn generate_mint_fee(amount: Amount, mut mint: Mint, bill_id: String) -> String {
-    init_wallet().await;
-
-    let wallet = create_mint_wallet().await;
-
-    let split_amount = amount.split();
-
-    let mut tx = mint.db.begin_tx().await.unwrap();
-
-    let request_to_mint = &mint
-        .db
-        .get_bitcredit_request_to_mint(&mut tx, &bill_id)
-        .await
-        .unwrap();
-
-    let keys = MintKeyset::new_with_id(
-        request_to_mint.bill_key.as_str(),
-        String::default().as_str(),
-        bill_id.clone(),
-    );
-
-    mint.keyset = keys;
-
-    add_keyset(wallet.clone(), mint.keyset.clone()).await;
-
-    let secret_range = wallet
-        .create_secrets(
-            &KeysetId::new(&mint.keyset.keyset_id).unwrap(),
-            split_amount.len() as u32,
-        )
-        .await
-        .unwrap();
-
-    let blinded_messages = split_amount
-        .into_iter()
-        .zip(secret_range)
-        .map(|(amount, (secret, blinding_factor))| {
-            let b_ = wallet.dhke.step1_alice(&secret, &blinding_factor)?;
-            Ok((
-                BlindedMessage {
-                    amount,
-                    b_,
-                    id: mint.keyset.keyset_id.to_string(),
-                },
-                blinding_factor,
-                secret,
-            ))
-        })
-        .collect::<Result<Vec<(_, _, _)>, MokshaWalletError>>()
-        .unwrap();
-
-    let blinded_message = blinded_messages
-        .clone()
-        .into_iter()
-        .map(|(msg, _, _)| msg)
-        .collect::<Vec<BlindedMessage>>();
-
-    let signatures = mint
-        .create_blinded_signatures(&blinded_message, &mint.keyset)
-        .unwrap();
-
-    let wallet_keyset = WalletKeyset::new(
-        &KeysetId::new(&mint.keyset.keyset_id).unwrap(),
-        &Url::parse(MINT_URL).expect("Invalid url"),
-        &CrSat,
-        0,
-        mint.keyset.public_keys,
-        true,
-    );
-
-    let current_keyset_id = wallet_keyset.keyset_id.to_string();
-
-    let proofs = signatures
-        .iter()
-        .zip(blinded_messages)
-        .map(|(p, (_, priv_key, secret))| {
-            let key = wallet_keyset
-                .public_keys
-                .get(&p.amount)
-                .expect("msg amount not found in mint keys");
-            let pub_alice = wallet.dhke.step3_alice(p.c_, priv_key, *key).unwrap();
-            Proof::new(p.amount, secret, pub_alice, current_keyset_id.clone())
-        })
-        .collect::<Vec<Proof>>()
-        .into();
-
-    let tokens: TokenV3 = (Url::parse(MINT_URL).expect("Invalid url"), CrSat, proofs).into();
-    let token = tokens.serialize(Option::from(CrSat)).unwrap();
-    token
-}
-
-async fn add_keyset(
-    wallet: Wallet<SqliteLocalStore, CrossPlatformHttpClient>,
-    mint_keyset: MintKeyset,
-) {
-    let mut tx = wallet.localstore.begin_tx().await.unwrap();
-
-    let wallet_keyset = WalletKeyset::new(
-        &KeysetId::new(&mint_keyset.keyset_id).unwrap(),
-        &Url::parse(MINT_URL).expect("Invalid url"),
-        &CrSat,
-        0,
-        mint_keyset.public_keys,
-        true,
-    );
-
-    wallet
-        .localstore
-        .upsert_keyset(&mut tx, &wallet_keyset)
-        .await
-        .unwrap();
-    tx.commit().await.unwrap();
-}
-
-async fn create_mint_wallet() -> Wallet<SqliteLocalStore, CrossPlatformHttpClient> {
-    let dir = PathBuf::from("./data/wallet".to_string());
-    let db_path = dir.join("wallet.db").to_str().unwrap().to_string();
-
-    let localstore = SqliteLocalStore::with_path(db_path.clone())
-        .await
-        .expect("Cannot parse local store");
-
-    let wallet: Wallet<_, CrossPlatformHttpClient> = Wallet::builder()
-        .with_localstore(localstore)
-        .build()
-        .await
-        .expect("Could not create wallet");
-
-    wallet
-}
-
-pub async fn init_wallet() {
-    let dir = PathBuf::from("./data/wallet".to_string());
-    if !dir.exists() {
-        fs::create_dir_all(dir.clone()).unwrap();
-    }
-    let db_path = dir.join("wallet.db").to_str().unwrap().to_string();
-
-    let _localstore = SqliteLocalStore::with_path(db_path.clone())
-        .await
-        .expect("Cannot parse local store");
-
-    // //TODO: take from params
-    // let mint_url = Url::parse(CONFIG.mint_url.as_str()).expect("Invalid url");
-    //
-    // let identity: Identity = read_identity_from_file();
-    // let bitcoin_key = identity.node_id.clone();
-    //
-    // let wallet: Wallet<_, CrossPlatformHttpClient> = Wallet::builder()
-    //     .with_localstore(localstore)
-    //     .build()
-    //     .await
-    //     .expect("Could not create wallet");
-}
-
 #[utoipa::path(
         post,
         path = "/v1/melt/quote/bolt11",
@@ -770,6 +585,7 @@ pub async fn post_melt_quote_bolt11(
     State(mint): State<Mint>,
     Json(melt_request): Json<PostMeltQuoteBolt11Request>,
 ) -> Result<Json<PostMeltQuoteBolt11Response>, MokshaMintError> {
+    mint.active_keyset(&melt_request.unit)?;
     let invoice = mint
         .lightning
         .decode_invoice(melt_request.request.clone())
@@ -777,16 +593,17 @@ pub async fn post_melt_quote_bolt11(
     let amount = invoice
         .amount_milli_satoshis()
         .ok_or_else(|| MokshaMintError::InvalidAmount("invalid invoice".to_owned()))?;
-    let fee_reserve = mint.fee_reserve_msat(amount) / 1_000; // FIXME check if this is correct
+    let fee_reserve = msat_to_unit(&melt_request.unit, mint.fee_reserve_msat(amount))?;
     debug!("fee_reserve: {}", fee_reserve);
 
-    let amount_sat = amount / 1_000;
+    // round up, the mint pays the full invoice amount
     let key = Uuid::new_v4();
     let quote = Bolt11MeltQuote {
         quote_id: key,
-        amount: amount_sat,
+        amount: msat_to_unit(&melt_request.unit, amount)?,
+        unit: melt_request.unit,
         fee_reserve,
-        expiry: quote_expiry(),
+        expiry: quote_expiry(&mint).await?,
         payment_request: melt_request.request.clone(),
         paid: false,
     };
@@ -797,10 +614,10 @@ pub async fn post_melt_quote_bolt11(
     Ok(Json(quote.into()))
 }
 
-fn quote_expiry() -> u64 {
+async fn quote_expiry(mint: &Mint) -> Result<u64, MokshaMintError> {
     // FIXME add config option for expiry
-    let now = Utc::now() + Duration::try_minutes(30).expect("invalid duration");
-    now.timestamp() as u64
+    let expiry = Duration::try_minutes(30).expect("invalid duration");
+    Ok(mint.clock.now().await? + expiry.num_seconds() as u64)
 }
 
 #[utoipa::path(
@@ -816,28 +633,19 @@ pub async fn post_melt_bolt11(
     State(mint): State<Mint>,
     Json(melt_request): Json<PostMeltBolt11Request>,
 ) -> Result<Json<PostMeltBolt11Response>, MokshaMintError> {
+    let quote_id = Uuid::from_str(melt_request.quote.as_str())?;
     let mut tx = mint.db.begin_tx().await?;
-    let quote = mint
-        .db
-        .get_bolt11_melt_quote(&mut tx, &Uuid::from_str(melt_request.quote.as_str())?)
-        .await?;
-
-    debug!("post_melt_bolt11 fee_reserve: {:#?}", &quote);
+    let quote = mint.db.get_bolt11_melt_quote(&mut tx, &quote_id).await?;
+    tx.commit().await?;
 
     let (paid, payment_preimage, change) = mint
         .melt_bolt11(
-            &mut tx,
-            quote.payment_request.to_owned(),
-            quote.fee_reserve,
+            &quote_id,
             &melt_request.inputs,
             melt_request.outputs,
-            &mint.keyset,
+            &mint.active_keyset(&quote.unit)?,
         )
         .await?;
-    mint.db
-        .update_bolt11_melt_quote(&mut tx, &Bolt11MeltQuote { paid, ..quote })
-        .await?;
-    tx.commit().await?;
 
     Ok(Json(PostMeltBolt11Response {
         paid,
@@ -909,7 +717,7 @@ pub async fn check_bitcredit_quote(
     get,
     path = "/v1/mint/quote/bitcredit/{quote_id}",
     responses(
-        (status = 200, description = "get bitcredit mint quote by id", body = [PostMintQuoteBitcreditResponse])
+        (status = 200, description = "get bitcredit mint quote by id", body = [GetMintQuoteBitcreditResponse])
     ),
     params(
         ("quote_id" = String, Path, description = "quote id"),
@@ -919,17 +727,40 @@ pub async fn check_bitcredit_quote(
 pub async fn get_mint_quote_bitcredit(
     Path(quote_id): Path<String>,
     State(mint): State<Mint>,
-) -> Result<Json<PostMintQuoteBitcreditResponse>, MokshaMintError> {
+) -> Result<Json<GetMintQuoteBitcreditResponse>, MokshaMintError> {
     debug!("get_quote: {}", quote_id);
 
+    let quote_id = Uuid::from_str(quote_id.as_str())?;
+    let quote = mint.get_bitcredit_mint_quote(&quote_id).await?;
     let mut tx = mint.db.begin_tx().await?;
-    let quote = mint
+    let transitions = mint
         .db
-        .get_bitcredit_mint_quote(&mut tx, &Uuid::from_str(quote_id.as_str())?)
+        .get_bitcredit_mint_quote_transitions(&mut tx, &quote_id)
         .await?;
     tx.commit().await?;
 
-    Ok(Json(BitcreditMintQuote { ..quote }.into()))
+    Ok(Json(GetMintQuoteBitcreditResponse::new(quote, transitions)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/mint/quote/bitcredit/{quote_id}/accept",
+    responses(
+        (status = 200, description = "accept the offer of a bitcredit mint quote", body = [PostMintQuoteBitcreditResponse])
+    ),
+    params(
+        ("quote_id" = String, Path, description = "quote id"),
+    )
+)]
+#[instrument(name = "post_accept_mint_quote_bitcredit", skip(mint), err)]
+pub async fn post_accept_mint_quote_bitcredit(
+    Path(quote_id): Path<String>,
+    State(mint): State<Mint>,
+) -> Result<Json<PostMintQuoteBitcreditResponse>, MokshaMintError> {
+    let quote = mint
+        .accept_bitcredit_mint_quote(&Uuid::from_str(quote_id.as_str())?)
+        .await?;
+    Ok(Json(quote.into()))
 }
 
 #[utoipa::path(
@@ -988,10 +819,11 @@ pub async fn get_info(State(mint): State<Mint>) -> Result<Json<MintInfoResponse>
         .collect::<Vec<Vec<String>>>(),
     );
 
+    let pubkey = mint.keyset().mint_pubkey;
     let mint_info = MintInfoResponse {
-        nuts: get_nuts(&mint.config),
+        nuts: get_nuts(&mint.config, &mint.units()),
         name: mint.config.info.name,
-        pubkey: mint.keyset.mint_pubkey,
+        pubkey,
         version: match mint.config.info.version {
             true => Some(mint.build_params.full_version()),
             _ => None,
@@ -1004,28 +836,64 @@ pub async fn get_info(State(mint): State<Mint>) -> Result<Json<MintInfoResponse>
     Ok(Json(mint_info))
 }
 
-fn get_nuts(cfg: &MintConfig) -> Nuts {
+fn get_nuts(cfg: &MintConfig, units: &[CurrencyUnit]) -> Nuts {
     let default_config = BtcOnchainConfig::default();
     let config = cfg.btconchain_backend.as_ref().unwrap_or(&default_config);
+    let nut4 = Nut4::default();
+    let nut5 = Nut5::default();
     Nuts {
+        // bills are minted in cr-sat
+        nut4: Nut4 {
+            payment_methods: bolt11_methods(nut4.payment_methods, units)
+                .into_iter()
+                .chain(std::iter::once(PaymentMethodConfig {
+                    payment_method: PaymentMethod::Bitcredit,
+                    unit: CurrencyUnit::CrSat,
+                    min_amount: None,
+                    max_amount: None,
+                }))
+                .collect(),
+            ..nut4
+        },
+        nut5: Nut5 {
+            payment_methods: bolt11_methods(nut5.payment_methods, units),
+            ..nut5
+        },
+        nut7: Some(Nut7 { supported: true }),
+        nut9: Some(Nut9 { supported: true }),
+        nut10: Some(Nut10 { supported: true }),
+        nut11: Some(Nut11 { supported: true }),
+        nut12: Some(Nut12 { supported: true }),
+        nut14: Some(Nut14 { supported: true }),
         nut17: Some(config.to_owned().into()),
         nut18: Some(config.to_owned().into()),
         ..Nuts::default()
     }
 }
 
-fn write_bill_keys_to_file(bill_name: String, private_key: String, public_key: String) {
-    let keys: BillKeys = BillKeys {
-        private_key_pem: private_key,
-        public_key_pem: public_key,
-    };
-
-    //TODO: this static path only for testing. Remove it
-    // let output_path = "/home/mtbitcr/RustroverProjects/E-Bills/bills_keys".to_string()
-    //     + "/"
-    //     + bill_name.as_str()
-    //     + ".json";
-    // let mut file = File::create(output_path.clone()).unwrap();
-    // file.write_all(serde_json::to_string_pretty(&keys).unwrap().as_bytes())
-    //     .unwrap();
+/// Offers the bolt11 methods (limits in sat) for every unit with a conversion to msat
+fn bolt11_methods(
+    methods: Vec<PaymentMethodConfig>,
+    units: &[CurrencyUnit],
+) -> Vec<PaymentMethodConfig> {
+    units
+        .iter()
+        .filter(|unit| unit_to_msat(unit, 0).is_ok())
+        .flat_map(|unit| {
+            methods.iter().filter_map(move |method| {
+                let convert = |amount: Option<u64>| {
+                    amount
+                        .map(|amount| msat_to_unit(unit, amount * 1_000))
+                        .transpose()
+                        .ok()
+                };
+                Some(PaymentMethodConfig {
+                    unit: unit.clone(),
+                    min_amount: convert(method.min_amount)?,
+                    max_amount: convert(method.max_amount)?,
+                    ..method.clone()
+                })
+            })
+        })
+        .collect()
 }
@@ -1,2 +1,3 @@
 pub mod btconchain;
 pub mod default;
+pub mod treasury;
@@ -0,0 +1,88 @@
+use axum::{extract::State, Json};
+use moksha_core::{
+    keyset::Keyset,
+    primitives::{
+        GetTreasuryResponse, PostRotateKeysetRequest, PostTreasuryMeltRequest,
+        PostTreasuryMeltResponse, PostTreasuryWithdrawRequest, PostTreasuryWithdrawResponse,
+    },
+};
+use tracing::instrument;
+
+use crate::{error::MokshaMintError, mint::Mint};
+
+#[utoipa::path(
+    get,
+    path = "/v1/treasury",
+    responses(
+        (status = 200, description = "earnings of the mint per bill", body = [GetTreasuryResponse])
+    ),
+)]
+#[instrument(name = "get_treasury", skip(mint), err)]
+pub async fn get_treasury(
+    State(mint): State<Mint>,
+) -> Result<Json<GetTreasuryResponse>, MokshaMintError> {
+    Ok(Json(mint.get_treasury().await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/treasury/withdraw",
+    request_body = PostTreasuryWithdrawRequest,
+    responses(
+        (status = 200, description = "withdraw earnings as ecash", body = [PostTreasuryWithdrawResponse])
+    ),
+)]
+#[instrument(name = "post_treasury_withdraw", skip(mint), err)]
+pub async fn post_treasury_withdraw(
+    State(mint): State<Mint>,
+    Json(request): Json<PostTreasuryWithdrawRequest>,
+) -> Result<Json<PostTreasuryWithdrawResponse>, MokshaMintError> {
+    let proofs = mint
+        .withdraw_treasury(&request.bill_id, request.amount)
+        .await?;
+    Ok(Json(PostTreasuryWithdrawResponse { proofs }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/treasury/melt",
+    request_body = PostTreasuryMeltRequest,
+    responses(
+        (status = 200, description = "pay an invoice from the earnings", body = [PostTreasuryMeltResponse])
+    ),
+)]
+#[instrument(name = "post_treasury_melt", skip(mint), err)]
+pub async fn post_treasury_melt(
+    State(mint): State<Mint>,
+    Json(request): Json<PostTreasuryMeltRequest>,
+) -> Result<Json<PostTreasuryMeltResponse>, MokshaMintError> {
+    Ok(Json(
+        mint.melt_treasury(&request.bill_id, request.request)
+            .await?,
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/keysets/rotate",
+    request_body = PostRotateKeysetRequest,
+    responses(
+        (status = 200, description = "the new active keyset of the unit", body = [Keyset])
+    ),
+)]
+#[instrument(name = "post_rotate_keyset", skip(mint), err)]
+pub async fn post_rotate_keyset(
+    State(mint): State<Mint>,
+    Json(request): Json<PostRotateKeysetRequest>,
+) -> Result<Json<Keyset>, MokshaMintError> {
+    let keyset = mint
+        .rotate_keyset(&request.unit, request.derivation_path)
+        .await?;
+    Ok(Json(Keyset {
+        id: keyset.keyset_id,
+        unit: request.unit,
+        active: true,
+        input_fee_ppk: keyset.input_fee_ppk,
+        maturity_date: None,
+    }))
+}
@@ -7,41 +7,54 @@ use crate::routes::default::{
     check_bitcredit_quote, get_info, get_keys, get_keys_by_id, get_keys_old, get_keysets,
     get_keysets_by_id, get_keysets_old, get_melt_quote_bolt11, get_mint_quote_bitcredit,
     get_mint_quote_bolt11, mjk_get_info, mjk_get_keys, mjk_get_keys_by_id, mjk_get_keysets,
-    mjk_post_swap, post_melt_bolt11, post_melt_quote_bolt11, post_mint_bitcredit, post_mint_bolt11,
-    post_mint_quote_bitcredit, post_mint_quote_bolt11, post_request_to_mint_bitcredit, post_swap,
+    mjk_post_swap, post_accept_mint_quote_bitcredit, post_checkstate, post_melt_bolt11,
+    post_melt_quote_bolt11, post_mint_bitcredit, post_mint_bolt11, post_mint_quote_bitcredit,
+    post_mint_quote_bolt11, post_redeem_bitcredit, post_request_to_mint_bitcredit, post_restore,
+    post_swap,
+};
+use crate::routes::treasury::{
+    get_treasury, post_rotate_keyset, post_treasury_melt, post_treasury_withdraw,
 };
 use axum::extract::Request;
-use axum::http::{HeaderName, HeaderValue, StatusCode};
+use axum::http::{header::AUTHORIZATION, HeaderName, HeaderValue, StatusCode};
 use axum::middleware::Next;
 use axum::response::IntoResponse;
 use axum::routing::{get, get_service, post};
 use axum::{middleware, Router};
 
 use moksha_core::keyset::{Keyset, Keysets};
+use moksha_core::proof::Proof;
+use moksha_core::proof::ProofDleq;
 use moksha_core::proof::Proofs;
-use moksha_core::proof::{P2SHScript, Proof};
 
 use utoipa_swagger_ui::SwaggerUi;
 
+use crate::error::MokshaMintError;
 use crate::mint::Mint;
 
 use moksha_core::blind::BlindedMessage;
-use moksha_core::blind::BlindedSignature;
+use moksha_core::blind::{BlindSignatureDleq, BlindedSignature};
 use moksha_core::primitives::{
-    CheckBitcreditQuoteResponse, CurrencyUnit, GetMeltBtcOnchainResponse, KeyResponse,
-    KeysResponse, MintInfoResponse, Nut10, Nut11, Nut12, Nut17, Nut18, Nut4, Nut5, Nut7, Nut8,
-    Nut9, Nuts, PaymentMethod, PostMeltBolt11Request, PostMeltBolt11Response,
-    PostMeltQuoteBolt11Request, PostMeltQuoteBolt11Response, PostMeltQuoteBtcOnchainRequest,
-    PostMeltQuoteBtcOnchainResponse, PostMintBitcreditRequest, PostMintBitcreditResponse,
-    PostMintBolt11Request, PostMintBolt11Response, PostMintQuoteBitcreditRequest,
-    PostMintQuoteBitcreditResponse, PostMintQuoteBolt11Request, PostMintQuoteBolt11Response,
-    PostMintQuoteBtcOnchainRequest, PostMintQuoteBtcOnchainResponse,
-    PostRequestToMintBitcreditRequest, PostRequestToMintBitcreditResponse, PostSwapRequest,
-    PostSwapResponse,
+    BitcreditMintQuoteState, BitcreditMintQuoteTransition, CashuErrorResponse,
+    CheckBitcreditQuoteResponse, CurrencyUnit, GetMeltBtcOnchainResponse,
+    GetMintQuoteBitcreditResponse, GetTreasuryResponse, KeyResponse, KeysResponse,
+    MintInfoResponse, Nut10, Nut11, Nut12, Nut14, Nut17, Nut18, Nut4, Nut5, Nut7, Nut8, Nut9, Nuts,
+    PaymentMethod, PostCheckStateRequest, PostCheckStateResponse, PostMeltBolt11Request,
+    PostMeltBolt11Response, PostMeltQuoteBolt11Request, PostMeltQuoteBolt11Response,
+    PostMeltQuoteBtcOnchainRequest, PostMeltQuoteBtcOnchainResponse, PostMintBitcreditRequest,
+    PostMintBitcreditResponse, PostMintBolt11Request, PostMintBolt11Response,
+    PostMintQuoteBitcreditRequest, PostMintQuoteBitcreditResponse, PostMintQuoteBolt11Request,
+    PostMintQuoteBolt11Response, PostMintQuoteBtcOnchainRequest, PostMintQuoteBtcOnchainResponse,
+    PostRedeemBitcreditRequest, PostRedeemBitcreditResponse, PostRequestToMintBitcreditRequest,
+    PostRequestToMintBitcreditResponse, PostRestoreRequest, PostRestoreResponse,
+    PostRotateKeysetRequest, PostSwapRequest, PostSwapResponse, PostTreasuryMeltRequest,
+    PostTreasuryMeltResponse, PostTreasuryWithdrawRequest, PostTreasuryWithdrawResponse,
+    ProofState, State, TreasuryBalance, TreasuryEntry, TreasuryEntryKind,
 };
 
 use tower_http::services::ServeDir;
 
+use subtle::ConstantTimeEq;
 use tower_http::cors::{Any, CorsLayer};
 use tracing::info;
 
@@ -76,6 +89,8 @@ pub async fn run_server(mint: Mint) -> anyhow::Result<()> {
 
     info!("tracing jaeger-endpoint: {:?}", mint.config.tracing);
 
+    mint.reconcile_pending_melts().await?;
+
     let listener = tokio::net::TcpListener::bind(&mint.config.server.host_port).await?;
 
     axum::serve(
@@ -109,10 +124,14 @@ pub async fn run_server(mint: Mint) -> anyhow::Result<()> {
         crate::routes::default::post_request_to_mint_bitcredit,
         crate::routes::default::get_mint_quote_bolt11,
         crate::routes::default::get_mint_quote_bitcredit,
+        crate::routes::default::post_accept_mint_quote_bitcredit,
         crate::routes::default::post_melt_bolt11,
         crate::routes::default::post_melt_quote_bolt11,
         crate::routes::default::get_melt_quote_bolt11,
         crate::routes::default::post_swap,
+        crate::routes::default::post_redeem_bitcredit,
+        crate::routes::default::post_checkstate,
+        crate::routes::default::post_restore,
         crate::routes::default::get_info,
         get_health,
         crate::routes::btconchain::post_mint_quote_btconchain,
@@ -121,7 +140,11 @@ pub async fn run_server(mint: Mint) -> anyhow::Result<()> {
         crate::routes::btconchain::post_melt_quote_btconchain,
         crate::routes::btconchain::get_melt_quote_btconchain,
         crate::routes::btconchain::post_melt_btconchain,
-        crate::routes::btconchain::get_melt_btconchain
+        crate::routes::btconchain::get_melt_btconchain,
+        crate::routes::treasury::get_treasury,
+        crate::routes::treasury::post_treasury_withdraw,
+        crate::routes::treasury::post_treasury_melt,
+        crate::routes::treasury::post_rotate_keyset
     ),
     components(schemas(
         MintInfoResponse,
@@ -134,6 +157,7 @@ pub async fn run_server(mint: Mint) -> anyhow::Result<()> {
         Nut10,
         Nut11,
         Nut12,
+        Nut14,
         CurrencyUnit,
         PaymentMethod,
         KeysResponse,
@@ -142,10 +166,15 @@ pub async fn run_server(mint: Mint) -> anyhow::Result<()> {
         Keyset,
         BlindedMessage,
         BlindedSignature,
+        BlindSignatureDleq,
         Proof,
+        ProofDleq,
         Proofs,
         PostMintQuoteBitcreditRequest,
         PostMintQuoteBitcreditResponse,
+        GetMintQuoteBitcreditResponse,
+        BitcreditMintQuoteState,
+        BitcreditMintQuoteTransition,
         PostRequestToMintBitcreditRequest,
         CheckBitcreditQuoteResponse,
         PostRequestToMintBitcreditResponse,
@@ -161,14 +190,31 @@ pub async fn run_server(mint: Mint) -> anyhow::Result<()> {
         PostMintBitcreditResponse,
         PostSwapRequest,
         PostSwapResponse,
-        P2SHScript,
+        PostRedeemBitcreditRequest,
+        PostRedeemBitcreditResponse,
+        CashuErrorResponse,
+        PostCheckStateRequest,
+        PostCheckStateResponse,
+        ProofState,
+        State,
+        PostRestoreRequest,
+        PostRestoreResponse,
         Nut17,
         Nut18,
         PostMintQuoteBtcOnchainRequest,
         PostMintQuoteBtcOnchainResponse,
         PostMeltQuoteBtcOnchainRequest,
         PostMeltQuoteBtcOnchainResponse,
-        GetMeltBtcOnchainResponse
+        GetMeltBtcOnchainResponse,
+        TreasuryEntryKind,
+        TreasuryEntry,
+        TreasuryBalance,
+        GetTreasuryResponse,
+        PostTreasuryWithdrawRequest,
+        PostTreasuryWithdrawResponse,
+        PostTreasuryMeltRequest,
+        PostTreasuryMeltResponse,
+        PostRotateKeysetRequest
     ))
 )]
 struct ApiDoc;
@@ -204,12 +250,19 @@ fn app(mint: Mint) -> Router {
             "/v1/mint/quote/bitcredit/:quote",
             get(get_mint_quote_bitcredit),
         )
+        .route(
+            "/v1/mint/quote/bitcredit/:quote/accept",
+            post(post_accept_mint_quote_bitcredit),
+        )
         .route("/v1/mint/bolt11", post(post_mint_bolt11))
         .route("/v1/mint/bitcredit", post(post_mint_bitcredit))
         .route("/v1/melt/quote/bolt11", post(post_melt_quote_bolt11))
         .route("/v1/melt/quote/bolt11/:quote", get(get_melt_quote_bolt11))
         .route("/v1/melt/bolt11", post(post_melt_bolt11))
         .route("/v1/swap", post(post_swap))
+        .route("/v1/redeem/bitcredit", post(post_redeem_bitcredit))
+        .route("/v1/checkstate", post(post_checkstate))
+        .route("/v1/restore", post(post_restore))
         .route("/v1/info", get(get_info));
 
     let btconchain_routes = if mint.onchain.is_some() {
@@ -237,6 +290,31 @@ fn app(mint: Mint) -> Router {
         Router::new()
     };
 
+    // the treasury and the operator api are only served if they are protected by an api key
+    let treasury_routes = if mint.config.treasury.api_key.is_some() {
+        Router::new()
+            .route("/v1/treasury", get(get_treasury))
+            .route("/v1/treasury/withdraw", post(post_treasury_withdraw))
+            .route("/v1/treasury/melt", post(post_treasury_melt))
+            .layer(middleware::from_fn_with_state(
+                mint.clone(),
+                authorize_treasury,
+            ))
+    } else {
+        Router::new()
+    };
+
+    let operator_routes = if mint.config.operator_api_key.is_some() {
+        Router::new()
+            .route("/v1/keysets/rotate", post(post_rotate_keyset))
+            .layer(middleware::from_fn_with_state(
+                mint.clone(),
+                authorize_operator,
+            ))
+    } else {
+        Router::new()
+    };
+
     let general_routes = Router::new().route("/health", get(get_health));
 
     let server_config = mint.config.server.clone();
@@ -245,6 +323,8 @@ fn app(mint: Mint) -> Router {
     let router = Router::new()
         .nest(&prefix, default_routes)
         .nest(&prefix, btconchain_routes)
+        .nest(&prefix, treasury_routes)
+        .nest(&prefix, operator_routes)
         .nest("", general_routes)
         .with_state(mint);
 
@@ -291,6 +371,41 @@ async fn add_response_headers(
     Ok(res)
 }
 
+/// Rejects requests to the treasury api that do not carry the treasury api key as bearer token
+async fn authorize_treasury(
+    axum::extract::State(mint): axum::extract::State<Mint>,
+    req: Request,
+    next: Next,
+) -> Result<impl IntoResponse, MokshaMintError> {
+    verify_api_key(&req, mint.config.treasury.api_key.as_deref())?;
+    Ok(next.run(req).await)
+}
+
+/// Rejects requests to the operator api that do not carry the operator api key as bearer token
+async fn authorize_operator(
+    axum::extract::State(mint): axum::extract::State<Mint>,
+    req: Request,
+    next: Next,
+) -> Result<impl IntoResponse, MokshaMintError> {
+    verify_api_key(&req, mint.config.operator_api_key.as_deref())?;
+    Ok(next.run(req).await)
+}
+
+fn verify_api_key(req: &Request, api_key: Option<&str>) -> Result<(), MokshaMintError> {
+    let token = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match (token, api_key) {
+        // compared in constant time, so the response time does not leak the api key
+        (Some(token), Some(api_key)) if bool::from(token.as_bytes().ct_eq(api_key.as_bytes())) => {
+            Ok(())
+        }
+        _ => Err(MokshaMintError::Unauthorized),
+    }
+}
+
 #[utoipa::path(
     get,
     path = "/health",
@@ -309,6 +424,7 @@ mod tests {
     use std::sync::Arc;
 
     use crate::{
+        billregistry::{BillInfo, MockBillRegistry},
         btconchain::MockBtcOnchain,
         config::{DatabaseConfig, MintConfig},
         database::postgres::PostgresDB,
@@ -320,8 +436,15 @@ mod tests {
     };
     use http_body_util::BodyExt;
     use moksha_core::{
-        keyset::Keysets,
-        primitives::{CurrencyUnit, KeysResponse, MintInfoResponse},
+        fixture::read_fixture,
+        keyset::{Keyset, Keysets},
+        primitives::{
+            BitcreditMintQuoteState, BitcreditRequestToMint, CashuErrorResponse, CurrencyUnit,
+            ErrorCode, GetMintQuoteBitcreditResponse, GetTreasuryResponse, KeysResponse,
+            MintInfoResponse, PaymentMethod, PaymentMethodConfig, PostMeltQuoteBolt11Response,
+            PostMintQuoteBitcreditResponse, PostRedeemBitcreditRequest,
+        },
+        token::TokenV3,
     };
 
     use testcontainers::{clients::Cli, RunnableImage};
@@ -332,8 +455,11 @@ mod tests {
         config::MintInfoConfig,
         lightning::{LightningType, MockLightning},
         mint::Mint,
+        model::CreateInvoiceResult,
     };
+    use lightning_invoice::Bolt11Invoice as LNInvoice;
     use pretty_assertions::assert_eq;
+    use std::str::FromStr;
 
     #[tokio::test]
     async fn test_get_keys() -> anyhow::Result<()> {
@@ -390,6 +516,8 @@ mod tests {
         Ok(db)
     }
 
+    const INVOICE_20_SAT: &str = "lnbc200n1pj9eanxsp5agdl4rd0twdljpcgmg67dwj9mseu5m4lwfhslkws4uh4m5f5pcrqpp5lvspx676rykr64l02s97wjztcxe355qck0naydrsvvkqw42cc35sdq2f38xy6t5wvxqzjccqpjrzjq027t9tsc6jn5ve2k6gnn689unn8h239juuf9s3ce09aty6ed73t5z7nqsqqsygqqyqqqqqqqqqqqqgq9q9qyysgqs5msn4j9v53fq000zhw0gulkcx2dlnfdt953v2ur7z765jj3m0fx6cppkpjwntq5nsqm273u4eevva508pvepg8mh27sqcd29sfjr4cq255a40";
+
     fn create_postgres_image() -> RunnableImage<Postgres> {
         let node = Postgres::default().with_host_auth();
         RunnableImage::from(node).with_tag("16.2-alpine")
@@ -410,9 +538,145 @@ mod tests {
             },
             Default::default(),
             Some(Arc::new(MockBtcOnchain::default())),
+            Arc::new(MockBillRegistry::new()),
         ))
     }
 
+    #[tokio::test]
+    async fn test_bolt11_quotes_per_unit() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let mut lightning = MockLightning::new();
+        lightning
+            .expect_decode_invoice()
+            .returning(|payment_request| {
+                Ok(LNInvoice::from_str(&payment_request).expect("invalid invoice"))
+            });
+        lightning
+            .expect_create_invoice()
+            .withf(|amount| *amount == 2)
+            .returning(|_| {
+                Ok(CreateInvoiceResult {
+                    payment_hash: vec![],
+                    payment_request: INVOICE_20_SAT.to_owned(),
+                })
+            });
+        let mint = create_mock_mint(Default::default(), node.get_host_port_ipv4(5432)).await?;
+        let mint = Mint::new(
+            Arc::new(lightning),
+            mint.lightning_type,
+            mint.db,
+            MintConfig {
+                units: vec![CurrencyUnit::Sat, CurrencyUnit::MSat, CurrencyUnit::Usd],
+                ..mint.config
+            },
+            mint.build_params,
+            mint.onchain,
+            mint.bill_registry,
+        );
+        mint.init_keysets().await?;
+        let app = app(mint);
+
+        let post = |uri: &str, body: &str| {
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("Content-Type", "application/json")
+                .body(Body::from(body.to_owned()))
+        };
+
+        let response = app
+            .clone()
+            .oneshot(post(
+                "/v1/melt/quote/bolt11",
+                &format!(r#"{{"unit":"msat","request":"{INVOICE_20_SAT}"}}"#),
+            )?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await?.to_bytes();
+        let quote = serde_json::from_slice::<PostMeltQuoteBolt11Response>(&body)?;
+        assert_eq!(20_000, quote.amount);
+        assert_eq!(4_000, quote.fee_reserve);
+
+        // usd has a keyset, but no conversion to lightning payments
+        let response = app
+            .clone()
+            .oneshot(post(
+                "/v1/melt/quote/bolt11",
+                &format!(r#"{{"unit":"usd","request":"{INVOICE_20_SAT}"}}"#),
+            )?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = app
+            .clone()
+            .oneshot(post(
+                "/v1/mint/quote/bolt11",
+                r#"{"unit":"usd","amount":20}"#,
+            )?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // invoices are created in whole sat
+        let response = app
+            .clone()
+            .oneshot(post(
+                "/v1/mint/quote/bolt11",
+                r#"{"unit":"msat","amount":1500}"#,
+            )?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = app
+            .clone()
+            .oneshot(post(
+                "/v1/mint/quote/bolt11",
+                r#"{"unit":"msat","amount":2000}"#,
+            )?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .oneshot(Request::builder().uri("/v1/info").body(Body::empty())?)
+            .await?;
+        let body = response.into_body().collect().await?.to_bytes();
+        let info = serde_json::from_slice::<MintInfoResponse>(&body)?;
+        let methods = |methods: Vec<PaymentMethodConfig>| {
+            methods
+                .into_iter()
+                .map(|m| (m.payment_method, m.unit, m.min_amount, m.max_amount))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec![
+                (
+                    PaymentMethod::Bolt11,
+                    CurrencyUnit::Sat,
+                    Some(1),
+                    Some(10_000_000)
+                ),
+                (
+                    PaymentMethod::Bolt11,
+                    CurrencyUnit::MSat,
+                    Some(1_000),
+                    Some(10_000_000_000)
+                ),
+                (PaymentMethod::Bitcredit, CurrencyUnit::CrSat, None, None),
+            ],
+            methods(info.nuts.nut4.payment_methods)
+        );
+        assert_eq!(
+            vec![CurrencyUnit::Sat, CurrencyUnit::MSat],
+            info.nuts
+                .nut5
+                .payment_methods
+                .into_iter()
+                .map(|m| m.unit)
+                .collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
     // ################ v1 api tests #####################
 
     #[tokio::test]
@@ -501,6 +765,252 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_get_mint_quote_invalid_uuid() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let app = app(create_mock_mint(Default::default(), node.get_host_port_ipv4(5432)).await?);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/mint/quote/bolt11/invalid-quote")
+                    .body(Body::empty())?,
+            )
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.into_body().collect().await?.to_bytes();
+        let error = serde_json::from_slice::<CashuErrorResponse>(&body)?;
+        assert_eq!(error.code, ErrorCode::BadRequest);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_v1_keysets_unknown_unit() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let app = app(create_mock_mint(Default::default(), node.get_host_port_ipv4(5432)).await?);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/keysets/unknown%20unit")
+                    .body(Body::empty())?,
+            )
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.into_body().collect().await?.to_bytes();
+        let error = serde_json::from_slice::<CashuErrorResponse>(&body)?;
+        assert_eq!(error.code, ErrorCode::UnitNotSupported);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_v1_keysets_bills() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let mint = create_mock_mint(Default::default(), node.get_host_port_ipv4(5432)).await?;
+        mint.add_bill(&BitcreditRequestToMint {
+            bill_id: "bill".to_owned(),
+            bill_key: "TEST_BILL_KEY".to_owned(),
+            maturity_date: 1_700_000_000,
+            bill_amount: 1_000,
+        })
+        .await?;
+
+        let app = app(mint);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/keysets/cr-sat")
+                    .body(Body::empty())?,
+            )
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await?.to_bytes();
+        let keysets = serde_json::from_slice::<Keysets>(&body)?;
+        assert_eq!(
+            Keysets {
+                keysets: vec![Keyset {
+                    id: "00bill".to_owned(),
+                    unit: CurrencyUnit::CrSat,
+                    active: true,
+                    input_fee_ppk: 0,
+                    maturity_date: Some(1_700_000_000),
+                }]
+            },
+            keysets
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_mint_quote_bitcredit() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let mut bill_registry = MockBillRegistry::new();
+        bill_registry.expect_get_bill().returning(|bill_id| {
+            Ok(BillInfo {
+                id: bill_id.to_owned(),
+                amount: 40,
+                maturity_date: 0,
+                drawee_node_id: "drawee".to_owned(),
+                holder_node_id: "mint".to_owned(),
+                holder_public_key: "".to_owned(),
+                paid: false,
+            })
+        });
+        let mint = Mint {
+            bill_registry: Arc::new(bill_registry),
+            ..create_mock_mint(Default::default(), node.get_host_port_ipv4(5432)).await?
+        };
+        let quote = mint
+            .create_bitcredit_mint_quote("bill".to_owned(), "node".to_owned(), 40)
+            .await?;
+
+        let app = app(mint);
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/v1/mint/quote/bitcredit/{}", quote.quote_id))
+                    .body(Body::empty())?,
+            )
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await?.to_bytes();
+        let response = serde_json::from_slice::<GetMintQuoteBitcreditResponse>(&body)?;
+        assert_eq!(quote.quote_id.to_string(), response.quote);
+        assert_eq!(BitcreditMintQuoteState::Offered, response.state);
+        assert_eq!(
+            vec![
+                BitcreditMintQuoteState::Requested,
+                BitcreditMintQuoteState::Offered
+            ],
+            response
+                .transitions
+                .into_iter()
+                .map(|transition| transition.state)
+                .collect::<Vec<_>>()
+        );
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(format!(
+                        "/v1/mint/quote/bitcredit/{}/accept",
+                        quote.quote_id
+                    ))
+                    .body(Body::empty())?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await?.to_bytes();
+        let response = serde_json::from_slice::<PostMintQuoteBitcreditResponse>(&body)?;
+        assert_eq!(BitcreditMintQuoteState::Accepted, response.state);
+        assert_eq!(40, response.amount);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_treasury() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let response =
+            app(create_mock_mint(Default::default(), node.get_host_port_ipv4(5432)).await?)
+                .oneshot(Request::builder().uri("/v1/treasury").body(Body::empty())?)
+                .await?;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let mut mint = create_mock_mint(Default::default(), node.get_host_port_ipv4(5432)).await?;
+        mint.config.treasury.api_key = Some("operator".to_owned());
+        let app = app(mint);
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/treasury")
+                    .header("Authorization", "Bearer wrong")
+                    .body(Body::empty())?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/treasury")
+                    .header("Authorization", "Bearer operator")
+                    .body(Body::empty())?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await?.to_bytes();
+        let treasury = serde_json::from_slice::<GetTreasuryResponse>(&body)?;
+        assert!(treasury.balances.is_empty());
+        assert!(treasury.entries.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_post_redeem_bitcredit_sat_inputs() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let app = app(create_mock_mint(Default::default(), node.get_host_port_ipv4(5432)).await?);
+        let request = PostRedeemBitcreditRequest {
+            inputs: TokenV3::from_str(&read_fixture("token_60.cashu")?)?.proofs(),
+            outputs: vec![],
+        };
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/redeem/bitcredit")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_string(&request)?))?,
+            )
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.into_body().collect().await?.to_bytes();
+        let error = serde_json::from_slice::<CashuErrorResponse>(&body)?;
+        assert_eq!(error.code, ErrorCode::UnitNotSupported);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_v1_keys_unsupported_unit() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let app = app(create_mock_mint(Default::default(), node.get_host_port_ipv4(5432)).await?);
+        let response = app
+            .oneshot(Request::builder().uri("/v1/keys/usd").body(Body::empty())?)
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.into_body().collect().await?.to_bytes();
+        let error = serde_json::from_slice::<CashuErrorResponse>(&body)?;
+        assert_eq!(error.code, ErrorCode::UnitNotSupported);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_get_v1_keys_id() -> anyhow::Result<()> {
         let docker = Cli::default();
@@ -557,6 +1067,98 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_get_v1_keysets_after_rotation() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let mut mint = create_mock_mint(Default::default(), node.get_host_port_ipv4(5432)).await?;
+        mint.init_keysets().await?;
+        mint.config.input_fee_ppk = 100;
+        let rotated = mint.rotate_keyset(&CurrencyUnit::Sat, None).await?;
+
+        let app = app(mint);
+        let response = app
+            .oneshot(Request::builder().uri("/v1/keysets").body(Body::empty())?)
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await?.to_bytes();
+        let keys: Keysets = serde_json::from_slice(&body)?;
+        assert_eq!(2, keys.keysets.len());
+        let old = keys
+            .keysets
+            .iter()
+            .find(|k| k.id == "00f545318e4fad2b")
+            .expect("keyset not found");
+        let new = keys
+            .keysets
+            .iter()
+            .find(|k| k.id == rotated.keyset_id)
+            .expect("keyset not found");
+        assert!(!old.active);
+        assert_eq!(0, old.input_fee_ppk);
+        assert!(new.active);
+        assert_eq!(100, new.input_fee_ppk);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_post_rotate_keyset() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let mut mint = create_mock_mint(Default::default(), node.get_host_port_ipv4(5432)).await?;
+        mint.init_keysets().await?;
+        mint.config.treasury.api_key = Some("treasury".to_owned());
+
+        let rotate = |token: &str| {
+            Request::builder()
+                .method("POST")
+                .uri("/v1/keysets/rotate")
+                .header("Authorization", format!("Bearer {token}"))
+                .header("Content-Type", "application/json")
+                .body(Body::from(r#"{"unit":"sat"}"#))
+        };
+
+        // keyset rotation is not served without an operator api key, only GET /v1/keysets/:id
+        // matches the path
+        let response = app(mint.clone()).oneshot(rotate("treasury")?).await?;
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+        mint.config.operator_api_key = Some("operator".to_owned());
+        let app = app(mint);
+
+        let response = app.clone().oneshot(rotate("wrong")?).await?;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = app.clone().oneshot(rotate("treasury")?).await?;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = app.clone().oneshot(rotate("operator")?).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await?.to_bytes();
+        let rotated: Keyset = serde_json::from_slice(&body)?;
+        assert_ne!("00f545318e4fad2b", rotated.id);
+        assert!(rotated.active);
+
+        // the rotated keyset is used by all later requests
+        let response = app
+            .oneshot(Request::builder().uri("/v1/keysets").body(Body::empty())?)
+            .await?;
+        let body = response.into_body().collect().await?.to_bytes();
+        let keys: Keysets = serde_json::from_slice(&body)?;
+        let active = keys
+            .keysets
+            .iter()
+            .filter(|k| k.active)
+            .map(|k| k.id.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![rotated.id], active);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_get_health() -> anyhow::Result<()> {
         let docker = Cli::default();
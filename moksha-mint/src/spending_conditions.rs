@@ -0,0 +1,361 @@
+//! Verifies the spending conditions of proofs as described in [Nut-10](https://github.com/cashubtc/nuts/blob/main/10.md), [Nut-11](https://github.com/cashubtc/nuts/blob/main/11.md) and [Nut-14](https://github.com/cashubtc/nuts/blob/main/14.md)
+//!
+//! Secrets that are not well-known secrets (or have a kind that is not supported) are treated as anyone-can-spend.
+
+use std::str::FromStr;
+
+use moksha_core::{
+    blind::BlindedMessage,
+    proof::{Proof, Proofs},
+    secret::{
+        HTLCConditions, P2PKConditions, SigFlag, SpendingConditions, WellKnownSecret, Witness,
+    },
+};
+
+use crate::error::MokshaMintError;
+
+/// Checks the witnesses of all inputs and, if the inputs use `SIG_ALL`, of all outputs.
+/// `now` is the current unix timestamp in seconds and is compared against the `locktime` tag.
+pub fn verify_spending_conditions(
+    inputs: &Proofs,
+    outputs: &[BlindedMessage],
+    now: u64,
+) -> Result<(), MokshaMintError> {
+    let inputs = inputs
+        .proofs()
+        .into_iter()
+        .map(|proof| Ok((parse_conditions(&proof)?, proof)))
+        .collect::<Result<Vec<_>, MokshaMintError>>()?;
+
+    for (condition, proof) in inputs.iter() {
+        if let Some((_, conditions)) = condition {
+            verify_witness(
+                &proof.secret,
+                proof.secret.as_bytes(),
+                proof.witness.as_ref(),
+                conditions,
+                now,
+            )?;
+        }
+    }
+
+    let sig_all = inputs.iter().find_map(|(condition, _)| {
+        condition
+            .as_ref()
+            .filter(|(_, conditions)| conditions.signature_conditions().sig_flag == SigFlag::SigAll)
+    });
+    let Some((sig_all_secret, sig_all_conditions)) = sig_all else {
+        return Ok(());
+    };
+
+    // all inputs must be locked with the same conditions if SIG_ALL is used
+    let same_conditions = inputs.iter().all(|(condition, _)| {
+        condition.as_ref().is_some_and(|(secret, _)| {
+            secret.kind == sig_all_secret.kind
+                && secret.data == sig_all_secret.data
+                && secret.tags == sig_all_secret.tags
+        })
+    });
+    if !same_conditions {
+        return Err(MokshaMintError::SigAllMismatch);
+    }
+
+    // outputs only have to be signed, the preimage of a HTLC is only required for the inputs
+    for output in outputs {
+        let b_ = output.b_.to_string();
+        let witness = output.witness.as_ref();
+        let conditions = sig_all_conditions.signature_conditions();
+        if !is_refundable(b_.as_bytes(), witness, conditions, now) {
+            verify_signatures(&b_, b_.as_bytes(), witness, conditions)?;
+        }
+    }
+    Ok(())
+}
+
+fn parse_conditions(
+    proof: &Proof,
+) -> Result<Option<(WellKnownSecret, SpendingConditions)>, MokshaMintError> {
+    let Ok(secret) = WellKnownSecret::from_str(&proof.secret) else {
+        return Ok(None);
+    };
+
+    let conditions = SpendingConditions::try_from(&secret)
+        .map_err(|e| MokshaMintError::InvalidSecret(e.to_string()))?;
+    Ok(Some((secret, conditions)))
+}
+
+fn verify_witness(
+    name: &str,
+    msg: &[u8],
+    witness: Option<&Witness>,
+    conditions: &SpendingConditions,
+    now: u64,
+) -> Result<(), MokshaMintError> {
+    let signature_conditions = conditions.signature_conditions();
+    if is_refundable(msg, witness, signature_conditions, now) {
+        return Ok(());
+    }
+
+    if let SpendingConditions::HTLC(htlc) = conditions {
+        verify_preimage(name, witness, htlc)?;
+    }
+    verify_signatures(name, msg, witness, signature_conditions)
+}
+
+/// After the locktime the proof can be spent with a signature of one of the refund keys or by anyone if there are no refund keys
+fn is_refundable(
+    msg: &[u8],
+    witness: Option<&Witness>,
+    conditions: &P2PKConditions,
+    now: u64,
+) -> bool {
+    let expired = conditions.locktime.is_some_and(|locktime| now >= locktime);
+    expired
+        && (conditions.refund_keys.is_empty()
+            || witness.is_some_and(|witness| {
+                witness.count_valid_signatures(msg, &conditions.refund_keys) >= 1
+            }))
+}
+
+fn verify_preimage(
+    name: &str,
+    witness: Option<&Witness>,
+    htlc: &HTLCConditions,
+) -> Result<(), MokshaMintError> {
+    let preimage = witness.and_then(|witness| witness.preimage.as_deref());
+    if !preimage.is_some_and(|preimage| htlc.verify_preimage(preimage)) {
+        return Err(MokshaMintError::InvalidPreimage(name.to_owned()));
+    }
+    Ok(())
+}
+
+fn verify_signatures(
+    name: &str,
+    msg: &[u8],
+    witness: Option<&Witness>,
+    conditions: &P2PKConditions,
+) -> Result<(), MokshaMintError> {
+    // a HTLC without pubkeys only requires the preimage
+    if conditions.pubkeys.is_empty() {
+        return Ok(());
+    }
+
+    let Some(witness) = witness.filter(|w| !w.signatures.is_empty()) else {
+        return Err(MokshaMintError::WitnessMissing(name.to_owned()));
+    };
+
+    let valid = witness.count_valid_signatures(msg, &conditions.pubkeys) as u64;
+    if valid == 0 {
+        return Err(MokshaMintError::InvalidWitnessSignature(name.to_owned()));
+    }
+    if valid < conditions.n_sigs {
+        return Err(MokshaMintError::NotEnoughSignatures(
+            conditions.n_sigs,
+            valid,
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use moksha_core::{
+        blind::BlindedMessage,
+        proof::{Proof, Proofs},
+        secret::{SecretKind, WellKnownSecret, Witness},
+    };
+    use secp256k1::{PublicKey, Secp256k1, SecretKey};
+
+    use crate::{error::MokshaMintError, spending_conditions::verify_spending_conditions};
+
+    fn keypair(byte: u8) -> (SecretKey, PublicKey) {
+        let key = SecretKey::from_slice(&[byte; 32]).expect("invalid key");
+        (key, PublicKey::from_secret_key(&Secp256k1::new(), &key))
+    }
+
+    fn locked_proof(pubkey: &PublicKey, tags: Vec<Vec<&str>>) -> Proof {
+        proof_with_secret(SecretKind::P2PK, pubkey.to_string(), tags)
+    }
+
+    fn proof_with_secret(kind: SecretKind, data: String, tags: Vec<Vec<&str>>) -> Proof {
+        let (_, pubkey) = keypair(1);
+        let secret = WellKnownSecret {
+            kind,
+            nonce: "5d11913ee0f92fefdc82a6764fd2457a".to_owned(),
+            data,
+            tags: tags
+                .into_iter()
+                .map(|tag| tag.into_iter().map(String::from).collect())
+                .collect(),
+        };
+        Proof::new(
+            8,
+            secret.try_into().expect("invalid secret"),
+            pubkey,
+            "00f4683f9caf8793".to_owned(),
+        )
+    }
+
+    const PREIMAGE: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+    // sha256(PREIMAGE)
+    const HASH: &str = "66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925";
+
+    fn htlc_proof(tags: Vec<Vec<&str>>) -> Proof {
+        proof_with_secret(SecretKind::HTLC, HASH.to_owned(), tags)
+    }
+
+    fn with_preimage(mut proof: Proof, preimage: &str) -> Proof {
+        let mut witness = proof.witness.take().unwrap_or_default();
+        witness.preimage = Some(preimage.to_owned());
+        proof.witness = Some(witness);
+        proof
+    }
+
+    fn sign(mut proof: Proof, key: &SecretKey) -> Proof {
+        let mut witness = proof.witness.take().unwrap_or_default();
+        witness.sign(proof.secret.as_bytes(), key);
+        proof.witness = Some(witness);
+        proof
+    }
+
+    #[test]
+    fn test_plain_secret_is_anyone_can_spend() -> anyhow::Result<()> {
+        let (_, pubkey) = keypair(1);
+        let proof = Proof::new(8, "plain".to_owned(), pubkey, "00f4683f9caf8793".to_owned());
+        verify_spending_conditions(&proof.into(), &[], 0)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_p2pk_signatures() -> anyhow::Result<()> {
+        let (key, pubkey) = keypair(1);
+        let (other_key, _) = keypair(2);
+        let proof = locked_proof(&pubkey, vec![]);
+
+        let result = verify_spending_conditions(&proof.clone().into(), &[], 0);
+        assert!(matches!(result, Err(MokshaMintError::WitnessMissing(_))));
+
+        let result = verify_spending_conditions(&sign(proof.clone(), &other_key).into(), &[], 0);
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::InvalidWitnessSignature(_))
+        ));
+
+        verify_spending_conditions(&sign(proof, &key).into(), &[], 0)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_p2pk_n_sigs() -> anyhow::Result<()> {
+        let (key, pubkey) = keypair(1);
+        let (other_key, other_pubkey) = keypair(2);
+        let other_pubkey = other_pubkey.to_string();
+        let proof = locked_proof(
+            &pubkey,
+            vec![vec!["n_sigs", "2"], vec!["pubkeys", &other_pubkey]],
+        );
+
+        let result = verify_spending_conditions(&sign(proof.clone(), &key).into(), &[], 0);
+        assert!(matches!(
+            result,
+            Err(MokshaMintError::NotEnoughSignatures(2, 1))
+        ));
+
+        let signed = sign(sign(proof, &key), &other_key);
+        verify_spending_conditions(&signed.into(), &[], 0)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_p2pk_locktime_and_refund() -> anyhow::Result<()> {
+        let (_, pubkey) = keypair(1);
+        let (refund_key, refund_pubkey) = keypair(3);
+        let refund_pubkey = refund_pubkey.to_string();
+
+        // without refund keys anyone can spend after the locktime
+        let proof = locked_proof(&pubkey, vec![vec!["locktime", "100"]]);
+        assert!(verify_spending_conditions(&proof.clone().into(), &[], 99).is_err());
+        verify_spending_conditions(&proof.into(), &[], 100)?;
+
+        let proof = locked_proof(
+            &pubkey,
+            vec![vec!["locktime", "100"], vec!["refund", &refund_pubkey]],
+        );
+        let refunded = sign(proof.clone(), &refund_key);
+        assert!(verify_spending_conditions(&refunded.clone().into(), &[], 99).is_err());
+        verify_spending_conditions(&refunded.into(), &[], 100)?;
+
+        let result = verify_spending_conditions(&proof.into(), &[], 100);
+        assert!(matches!(result, Err(MokshaMintError::WitnessMissing(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_htlc_preimage() -> anyhow::Result<()> {
+        let proof = htlc_proof(vec![]);
+
+        let result = verify_spending_conditions(&proof.clone().into(), &[], 0);
+        assert!(matches!(result, Err(MokshaMintError::InvalidPreimage(_))));
+
+        let wrong = with_preimage(proof.clone(), &PREIMAGE.replace('0', "1"));
+        let result = verify_spending_conditions(&wrong.into(), &[], 0);
+        assert!(matches!(result, Err(MokshaMintError::InvalidPreimage(_))));
+
+        verify_spending_conditions(&with_preimage(proof, PREIMAGE).into(), &[], 0)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_htlc_pubkeys_and_refund() -> anyhow::Result<()> {
+        let (key, pubkey) = keypair(1);
+        let (refund_key, refund_pubkey) = keypair(3);
+        let (pubkey, refund_pubkey) = (pubkey.to_string(), refund_pubkey.to_string());
+        let proof = htlc_proof(vec![
+            vec!["pubkeys", &pubkey],
+            vec!["locktime", "100"],
+            vec!["refund", &refund_pubkey],
+        ]);
+
+        // the preimage alone is not enough if pubkeys are set
+        let unsigned = with_preimage(proof.clone(), PREIMAGE);
+        let result = verify_spending_conditions(&unsigned.clone().into(), &[], 0);
+        assert!(matches!(result, Err(MokshaMintError::WitnessMissing(_))));
+        verify_spending_conditions(&sign(unsigned, &key).into(), &[], 0)?;
+
+        // after the locktime the refund key can spend the proof without the preimage
+        let refunded = sign(proof, &refund_key);
+        let result = verify_spending_conditions(&refunded.clone().into(), &[], 99);
+        assert!(matches!(result, Err(MokshaMintError::InvalidPreimage(_))));
+        verify_spending_conditions(&refunded.into(), &[], 100)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_p2pk_sig_all() -> anyhow::Result<()> {
+        let (key, pubkey) = keypair(1);
+        let proof = sign(
+            locked_proof(&pubkey, vec![vec!["sigflag", "SIG_ALL"]]),
+            &key,
+        );
+        let mut output = BlindedMessage {
+            amount: 8,
+            b_: pubkey,
+            id: "00f4683f9caf8793".to_owned(),
+            witness: None,
+        };
+
+        let result = verify_spending_conditions(&proof.clone().into(), &[output.clone()], 0);
+        assert!(matches!(result, Err(MokshaMintError::WitnessMissing(_))));
+
+        let plain = Proof::new(8, "plain".to_owned(), pubkey, "00f4683f9caf8793".to_owned());
+        let inputs = Proofs::new(vec![proof.clone(), plain]);
+        let result = verify_spending_conditions(&inputs, &[], 0);
+        assert!(matches!(result, Err(MokshaMintError::SigAllMismatch)));
+
+        let mut witness = Witness::default();
+        witness.sign(output.b_.to_string().as_bytes(), &key);
+        output.witness = Some(witness);
+        verify_spending_conditions(&proof.into(), &[output], 0)?;
+        Ok(())
+    }
+}
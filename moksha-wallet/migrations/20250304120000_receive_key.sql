@@ -0,0 +1,5 @@
+-- keypair for receiving tokens that are locked to a pubkey (Nut-11)
+CREATE TABLE IF NOT EXISTS receive_key (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    secret_key TEXT NOT NULL
+);
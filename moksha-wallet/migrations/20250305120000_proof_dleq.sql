@@ -0,0 +1 @@
+ALTER TABLE proofs ADD COLUMN dleq TEXT CHECK (dleq IS NULL OR json_valid(dleq));
@@ -0,0 +1 @@
+ALTER TABLE keysets ADD COLUMN input_fee_ppk INTEGER NOT NULL DEFAULT 0;
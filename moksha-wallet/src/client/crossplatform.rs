@@ -5,22 +5,25 @@ use moksha_core::{
     keyset::Keysets,
     primitives::{
         CurrencyUnit, GetMeltBtcOnchainResponse, KeysResponse, MintInfoResponse,
-        PostMeltBolt11Request, PostMeltBolt11Response, PostMeltBtcOnchainRequest,
-        PostMeltBtcOnchainResponse, PostMeltQuoteBolt11Request, PostMeltQuoteBolt11Response,
-        PostMeltQuoteBtcOnchainRequest, PostMeltQuoteBtcOnchainResponse, PostMintBolt11Request,
-        PostMintBolt11Response, PostMintBtcOnchainRequest, PostMintBtcOnchainResponse,
-        PostMintQuoteBolt11Request, PostMintQuoteBolt11Response, PostMintQuoteBtcOnchainRequest,
-        PostMintQuoteBtcOnchainResponse, PostSwapRequest, PostSwapResponse,
+        PostCheckStateRequest, PostCheckStateResponse, PostMeltBolt11Request,
+        PostMeltBolt11Response, PostMeltBtcOnchainRequest, PostMeltBtcOnchainResponse,
+        PostMeltQuoteBolt11Request, PostMeltQuoteBolt11Response, PostMeltQuoteBtcOnchainRequest,
+        PostMeltQuoteBtcOnchainResponse, PostMintBolt11Request, PostMintBolt11Response,
+        PostMintBtcOnchainRequest, PostMintBtcOnchainResponse, PostMintQuoteBolt11Request,
+        PostMintQuoteBolt11Response, PostMintQuoteBtcOnchainRequest,
+        PostMintQuoteBtcOnchainResponse, PostRestoreRequest, PostRestoreResponse, PostSwapRequest,
+        PostSwapResponse,
     },
     proof::Proofs,
 };
 
 use crate::{error::MokshaWalletError, http::CrossPlatformHttpClient};
 use moksha_core::primitives::{
-    BillKeys, CheckBitcreditQuoteResponse, PostMintBitcreditResponse,
-    PostMintQuoteBitcreditRequest, PostMintQuoteBitcreditResponse,
+    CheckBitcreditQuoteResponse, PostMintBitcreditResponse, PostMintQuoteBitcreditRequest,
+    PostMintQuoteBitcreditResponse, PostRedeemBitcreditRequest, PostRedeemBitcreditResponse,
     PostRequestToMintBitcreditRequest, PostRequestToMintBitcreditResponse,
 };
+use secp256k1::PublicKey;
 use url::Url;
 
 use super::CashuClient;
@@ -76,6 +79,38 @@ impl CashuClient for CrossPlatformHttpClient {
         self.do_post(&mint_url.join("v1/swap")?, &body).await
     }
 
+    async fn post_redeem_bitcredit(
+        &self,
+        mint_url: &Url,
+        inputs: Proofs,
+        outputs: Vec<BlindedMessage>,
+    ) -> Result<PostRedeemBitcreditResponse, MokshaWalletError> {
+        let body = PostRedeemBitcreditRequest { inputs, outputs };
+
+        self.do_post(&mint_url.join("v1/redeem/bitcredit")?, &body)
+            .await
+    }
+
+    async fn post_checkstate(
+        &self,
+        mint_url: &Url,
+        ys: Vec<PublicKey>,
+    ) -> Result<PostCheckStateResponse, MokshaWalletError> {
+        let body = PostCheckStateRequest { ys };
+
+        self.do_post(&mint_url.join("v1/checkstate")?, &body).await
+    }
+
+    async fn post_restore(
+        &self,
+        mint_url: &Url,
+        outputs: Vec<BlindedMessage>,
+    ) -> Result<PostRestoreResponse, MokshaWalletError> {
+        let body = PostRestoreRequest { outputs };
+
+        self.do_post(&mint_url.join("v1/restore")?, &body).await
+    }
+
     async fn post_melt_bolt11(
         &self,
         mint_url: &Url,
@@ -172,21 +207,24 @@ impl CashuClient for CrossPlatformHttpClient {
             .await
     }
 
+    async fn post_accept_mint_quote_bitcredit(
+        &self,
+        mint_url: &Url,
+        quote: String,
+    ) -> Result<PostMintQuoteBitcreditResponse, MokshaWalletError> {
+        self.do_post(
+            &mint_url.join(&format!("v1/mint/quote/bitcredit/{}/accept", quote))?,
+            &(),
+        )
+        .await
+    }
+
     async fn post_request_to_mint_bitcredit(
         &self,
         mint_url: &Url,
-        bill_id: String,
-        bill_keys: BillKeys,
-        maturity_date: i64,
-        bill_amount: u64,
+        request: PostRequestToMintBitcreditRequest,
     ) -> Result<PostRequestToMintBitcreditResponse, MokshaWalletError> {
-        let body = PostRequestToMintBitcreditRequest {
-            bill_id,
-            bill_keys,
-            maturity_date,
-            bill_amount,
-        };
-        self.do_post(&mint_url.join("v1/mint/request/bitcredit")?, &body)
+        self.do_post(&mint_url.join("v1/mint/request/bitcredit")?, &request)
             .await
     }
 
@@ -4,14 +4,16 @@ use moksha_core::{
     keyset::Keysets,
     primitives::{
         CurrencyUnit, GetMeltBtcOnchainResponse, KeysResponse, MintInfoResponse,
-        PostMeltBolt11Response, PostMeltBtcOnchainResponse, PostMeltQuoteBolt11Response,
-        PostMeltQuoteBtcOnchainResponse, PostMintBitcreditResponse, PostMintBolt11Response,
-        PostMintBtcOnchainResponse, PostMintQuoteBitcreditResponse, PostMintQuoteBolt11Response,
-        PostMintQuoteBtcOnchainResponse, PostRequestToMintBitcreditResponse, PostSwapResponse,
+        PostCheckStateResponse, PostMeltBolt11Response, PostMeltBtcOnchainResponse,
+        PostMeltQuoteBolt11Response, PostMeltQuoteBtcOnchainResponse, PostMintBitcreditResponse,
+        PostMintBolt11Response, PostMintBtcOnchainResponse, PostMintQuoteBitcreditResponse,
+        PostMintQuoteBolt11Response, PostMintQuoteBtcOnchainResponse, PostRedeemBitcreditResponse,
+        PostRequestToMintBitcreditResponse, PostRestoreResponse, PostSwapResponse,
     },
     proof::Proofs,
 };
 
+use secp256k1::PublicKey;
 use url::Url;
 
 use crate::error::MokshaWalletError;
@@ -20,7 +22,7 @@ pub mod crossplatform;
 
 #[cfg(test)]
 use mockall::automock;
-use moksha_core::primitives::{BillKeys, CheckBitcreditQuoteResponse};
+use moksha_core::primitives::{CheckBitcreditQuoteResponse, PostRequestToMintBitcreditRequest};
 
 #[cfg_attr(test, automock)]
 #[async_trait(?Send)]
@@ -55,6 +57,26 @@ pub trait CashuClient {
         output: Vec<BlindedMessage>,
     ) -> Result<PostSwapResponse, MokshaWalletError>;
 
+    /// Exchanges `cr-sat` proofs of matured bills for `sat` signatures
+    async fn post_redeem_bitcredit(
+        &self,
+        mint_url: &Url,
+        proofs: Proofs,
+        outputs: Vec<BlindedMessage>,
+    ) -> Result<PostRedeemBitcreditResponse, MokshaWalletError>;
+
+    async fn post_checkstate(
+        &self,
+        mint_url: &Url,
+        ys: Vec<PublicKey>,
+    ) -> Result<PostCheckStateResponse, MokshaWalletError>;
+
+    async fn post_restore(
+        &self,
+        mint_url: &Url,
+        outputs: Vec<BlindedMessage>,
+    ) -> Result<PostRestoreResponse, MokshaWalletError>;
+
     async fn post_melt_bolt11(
         &self,
         mint_url: &Url,
@@ -118,13 +140,16 @@ pub trait CashuClient {
         quote: String,
     ) -> Result<PostMintQuoteBitcreditResponse, MokshaWalletError>;
 
+    async fn post_accept_mint_quote_bitcredit(
+        &self,
+        mint_url: &Url,
+        quote: String,
+    ) -> Result<PostMintQuoteBitcreditResponse, MokshaWalletError>;
+
     async fn post_request_to_mint_bitcredit(
         &self,
         mint_url: &Url,
-        bill_id: String,
-        bill_keys: BillKeys,
-        maturity_date: i64,
-        bill_amount: u64,
+        request: PostRequestToMintBitcreditRequest,
     ) -> Result<PostRequestToMintBitcreditResponse, MokshaWalletError>;
 
     async fn check_bitcredit_quote(
@@ -1,6 +1,7 @@
 use std::string::FromUtf8Error;
 
 use lightning_invoice::ParseOrSemanticError;
+use moksha_core::primitives::{CashuErrorResponse, ErrorCode};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -25,6 +26,36 @@ pub enum MokshaWalletError {
     #[error("{1}")]
     InvoiceNotPaidYet(u64, String),
 
+    #[error("{0}")]
+    TokenAlreadySpent(String),
+
+    #[error("{0}")]
+    TokenNotVerified(String),
+
+    #[error("{0}")]
+    TransactionUnbalanced(String),
+
+    #[error("{0}")]
+    UnitNotSupported(String),
+
+    #[error("{0}")]
+    KeysetNotFound(String),
+
+    #[error("{0}")]
+    KeysetInactive(String),
+
+    #[error("{0}")]
+    TokensAlreadyIssued(String),
+
+    #[error("{0}")]
+    QuotePending(String),
+
+    #[error("{0}")]
+    InvoiceAlreadyPaid(String),
+
+    #[error("{0}")]
+    QuoteExpired(String),
+
     #[error("UnexpectedResponse - {0}")]
     UnexpectedResponse(String),
 
@@ -79,6 +110,9 @@ pub enum MokshaWalletError {
     #[error("Found multiple seeds in the database. This is not supported.")]
     MultipleSeeds,
 
+    #[error("The wallet is already initialized with a different seed")]
+    SeedMismatch,
+
     #[error("Not valid hex string")]
     Hex(#[from] hex::FromHexError),
 
@@ -87,4 +121,61 @@ pub enum MokshaWalletError {
 
     #[error("Pubkey not found")]
     PubkeyNotFound,
+
+    #[error("Invalid DLEQ proof for amount {0}")]
+    InvalidDleq(u64),
+}
+
+impl From<CashuErrorResponse> for MokshaWalletError {
+    fn from(response: CashuErrorResponse) -> Self {
+        let CashuErrorResponse { code, detail } = response;
+        match code {
+            ErrorCode::QuoteNotPaid => Self::InvoiceNotPaidYet(code.into(), detail),
+            ErrorCode::TokenAlreadySpent => Self::TokenAlreadySpent(detail),
+            ErrorCode::TokenNotVerified => Self::TokenNotVerified(detail),
+            ErrorCode::TransactionUnbalanced => Self::TransactionUnbalanced(detail),
+            ErrorCode::UnitNotSupported => Self::UnitNotSupported(detail),
+            ErrorCode::KeysetNotFound => Self::KeysetNotFound(detail),
+            ErrorCode::KeysetInactive => Self::KeysetInactive(detail),
+            ErrorCode::TokensAlreadyIssued => Self::TokensAlreadyIssued(detail),
+            ErrorCode::QuotePending => Self::QuotePending(detail),
+            ErrorCode::InvoiceAlreadyPaid => Self::InvoiceAlreadyPaid(detail),
+            ErrorCode::QuoteExpired => Self::QuoteExpired(detail),
+            _ => Self::MintError(detail),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use moksha_core::primitives::{CashuErrorResponse, ErrorCode};
+
+    use super::MokshaWalletError;
+
+    #[test]
+    fn test_error_from_cashu_error_response() {
+        let err: MokshaWalletError = CashuErrorResponse {
+            code: ErrorCode::TokenAlreadySpent,
+            detail: "Proof already used".to_owned(),
+        }
+        .into();
+        assert!(matches!(err, MokshaWalletError::TokenAlreadySpent(_)));
+
+        let err: MokshaWalletError = CashuErrorResponse {
+            code: ErrorCode::QuoteNotPaid,
+            detail: "Lightning invoice not paid yet.".to_owned(),
+        }
+        .into();
+        assert!(matches!(
+            err,
+            MokshaWalletError::InvoiceNotPaidYet(20001, _)
+        ));
+
+        let err: MokshaWalletError = CashuErrorResponse {
+            code: ErrorCode::Other(0),
+            detail: "DB Error".to_owned(),
+        }
+        .into();
+        assert!(matches!(err, MokshaWalletError::MintError(_)));
+    }
 }
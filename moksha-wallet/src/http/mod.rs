@@ -4,6 +4,10 @@ pub mod reqwest;
 #[cfg(target_arch = "wasm32")]
 pub mod wasm;
 
+use moksha_core::primitives::CashuErrorResponse;
+
+use crate::error::MokshaWalletError;
+
 #[derive(Debug, Clone)]
 pub struct CrossPlatformHttpClient {
     #[cfg(not(target_arch = "wasm32"))]
@@ -15,3 +19,11 @@ impl Default for CrossPlatformHttpClient {
         Self::new()
     }
 }
+
+/// Converts the body of an error response of the mint into a [`MokshaWalletError`]
+fn parse_error_response(response_text: String) -> MokshaWalletError {
+    match serde_json::from_str::<CashuErrorResponse>(&response_text) {
+        Ok(data) => data.into(),
+        Err(_) => MokshaWalletError::UnexpectedResponse(response_text),
+    }
+}
@@ -1,10 +1,9 @@
-use super::CrossPlatformHttpClient;
+use super::{parse_error_response, CrossPlatformHttpClient};
 use crate::error::MokshaWalletError;
 use reqwest::{
     header::{HeaderValue, CONTENT_TYPE},
     Response, StatusCode,
 };
-use serde_json::Value;
 use url::Url;
 
 impl CrossPlatformHttpClient {
@@ -17,46 +16,12 @@ impl CrossPlatformHttpClient {
     async fn extract_response_data<T: serde::de::DeserializeOwned>(
         response: Response,
     ) -> Result<T, MokshaWalletError> {
-        match response.status() {
-            StatusCode::OK => {
-                let response_text = response.text().await?;
-                match serde_json::from_str::<T>(&response_text) {
-                    Ok(data) => Ok(data),
-                    Err(_) => {
-                        // FIXME cleanup code
-                        let data: Value = serde_json::from_str(&response_text)
-                            .map_err(|_| MokshaWalletError::UnexpectedResponse(response_text))
-                            .expect("invalid value");
-                        let detail = data["detail"].as_str().expect("detail not found");
-                        // let data = serde_json::from_str::<CashuErrorResponse>(&response_text)
-                        //     .map_err(|_| MokshaWalletError::UnexpectedResponse(response_text))
-                        //     .unwrap();
-
-                        // FIXME: use the error code to return a proper error
-                        match detail {
-                            "Lightning invoice not paid yet." => {
-                                Err(MokshaWalletError::InvoiceNotPaidYet(0, detail.to_owned()))
-                            }
-                            _ => Err(MokshaWalletError::MintError(detail.to_owned())),
-                        }
-                    }
-                }
-            }
-            _ => {
-                let response_text = response.text().await?;
-                let data: Value = serde_json::from_str(&response_text)
-                    .map_err(|_| MokshaWalletError::UnexpectedResponse(response_text))
-                    .expect("invalid value");
-                let detail = data["detail"].as_str().expect("detail not found");
-
-                // FIXME: use the error code to return a proper error
-                match detail {
-                    "Lightning invoice not paid yet." => {
-                        Err(MokshaWalletError::InvoiceNotPaidYet(0, detail.to_owned()))
-                    }
-                    _ => Err(MokshaWalletError::MintError(detail.to_owned())),
-                }
-            }
+        let status = response.status();
+        let response_text = response.text().await?;
+        match status {
+            StatusCode::OK => serde_json::from_str::<T>(&response_text)
+                .map_err(|_| parse_error_response(response_text)),
+            _ => Err(parse_error_response(response_text)),
         }
     }
 
@@ -1,9 +1,7 @@
-use moksha_core::primitives::CashuErrorResponse;
-
 use crate::error::MokshaWalletError;
 use url::Url;
 
-use super::CrossPlatformHttpClient;
+use super::{parse_error_response, CrossPlatformHttpClient};
 use gloo_net::http::{Request, Response};
 
 impl CrossPlatformHttpClient {
@@ -41,40 +39,12 @@ impl CrossPlatformHttpClient {
     async fn extract_response_data<T: serde::de::DeserializeOwned>(
         response: Response,
     ) -> Result<T, MokshaWalletError> {
-        match response.status() {
-            200 => {
-                let response_text = response.text().await.unwrap(); // FIXME handle error
-                match serde_json::from_str::<T>(&response_text) {
-                    Ok(data) => Ok(data),
-                    Err(_) => {
-                        let data = serde_json::from_str::<CashuErrorResponse>(&response_text)
-                            .map_err(|_| MokshaWalletError::UnexpectedResponse(response_text))
-                            .unwrap();
-
-                        // FIXME: use the error code to return a proper error
-                        match data.detail.as_str() {
-                            "Lightning invoice not paid yet." => {
-                                Err(MokshaWalletError::InvoiceNotPaidYet(data.code, data.detail))
-                            }
-                            _ => Err(MokshaWalletError::MintError(data.detail)),
-                        }
-                    }
-                }
-            }
-            _ => {
-                let txt = response.text().await.unwrap(); // FIXME handle error
-                let data = serde_json::from_str::<CashuErrorResponse>(&txt)
-                    .map_err(|_| MokshaWalletError::UnexpectedResponse(txt))
-                    .unwrap();
-
-                // FIXME: use the error code to return a proper error
-                match data.detail.as_str() {
-                    "Lightning invoice not paid yet." => {
-                        Err(MokshaWalletError::InvoiceNotPaidYet(data.code, data.detail))
-                    }
-                    _ => Err(MokshaWalletError::MintError(data.detail)),
-                }
-            }
+        let status = response.status();
+        let response_text = response.text().await?;
+        match status {
+            200 => serde_json::from_str::<T>(&response_text)
+                .map_err(|_| parse_error_response(response_text)),
+            _ => Err(parse_error_response(response_text)),
         }
     }
 }
@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use async_trait::async_trait;
 use moksha_core::{keyset::KeysetId, primitives::CurrencyUnit, proof::Proofs};
-use secp256k1::PublicKey;
+use secp256k1::{PublicKey, SecretKey};
 use url::Url;
 
 use crate::error::MokshaWalletError;
@@ -24,6 +24,8 @@ pub struct WalletKeyset {
     pub last_index: u64,
     pub public_keys: HashMap<u64, PublicKey>,
     pub active: bool,
+    /// fee per input in parts per thousand (Nut-02)
+    pub input_fee_ppk: u64,
 }
 
 impl WalletKeysetFilter for Vec<WalletKeyset> {
@@ -54,6 +56,7 @@ impl WalletKeyset {
             last_index,
             public_keys,
             active,
+            input_fee_ppk: 0,
         }
     }
 }
@@ -104,6 +107,17 @@ pub trait LocalStore {
         &self,
         tx: &mut sqlx::Transaction<Self::DB>,
     ) -> Result<Option<String>, MokshaWalletError>;
+
+    async fn add_receive_key(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        secret_key: &SecretKey,
+    ) -> Result<(), MokshaWalletError>;
+
+    async fn get_receive_key(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Option<SecretKey>, MokshaWalletError>;
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -162,6 +176,17 @@ pub trait LocalStore {
         &self,
         _tx: &mut RexieTransaction,
     ) -> Result<Option<String>, MokshaWalletError>;
+
+    async fn add_receive_key(
+        &self,
+        _tx: &mut RexieTransaction,
+        secret_key: &SecretKey,
+    ) -> Result<(), MokshaWalletError>;
+
+    async fn get_receive_key(
+        &self,
+        _tx: &mut RexieTransaction,
+    ) -> Result<Option<SecretKey>, MokshaWalletError>;
 }
 
 #[cfg(test)]
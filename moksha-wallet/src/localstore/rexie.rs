@@ -3,6 +3,7 @@ use crate::error::MokshaWalletError;
 use async_trait::async_trait;
 use moksha_core::proof::{Proof, Proofs};
 use rexie::*;
+use secp256k1::SecretKey;
 use wasm_bindgen::JsValue;
 
 #[derive(Clone, Default)]
@@ -147,4 +148,19 @@ impl LocalStore for RexieLocalStore {
     ) -> std::result::Result<Option<String>, MokshaWalletError> {
         todo!()
     }
+
+    async fn add_receive_key(
+        &self,
+        _tx: &mut RexieTransaction,
+        _secret_key: &SecretKey,
+    ) -> std::result::Result<(), MokshaWalletError> {
+        todo!()
+    }
+
+    async fn get_receive_key(
+        &self,
+        _tx: &mut RexieTransaction,
+    ) -> std::result::Result<Option<SecretKey>, MokshaWalletError> {
+        todo!()
+    }
 }
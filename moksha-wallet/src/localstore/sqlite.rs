@@ -1,8 +1,9 @@
 use async_trait::async_trait;
 use moksha_core::keyset::KeysetId;
 use moksha_core::proof::{Proof, Proofs};
-use secp256k1::PublicKey;
+use secp256k1::{PublicKey, SecretKey};
 use std::collections::HashMap;
+use std::str::FromStr;
 use url::Url;
 
 use crate::error::MokshaWalletError;
@@ -59,9 +60,10 @@ impl LocalStore for SqliteLocalStore {
         for proof in proofs.proofs() {
             let c = proof.c.to_string();
             let amount = proof.amount as i64;
+            let dleq = proof.dleq.as_ref().map(serde_json::to_string).transpose()?;
             sqlx::query!(
-                "INSERT INTO proofs (keyset_id, amount, C, secret, time_created) VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP);",
-            proof.keyset_id,amount, c, proof.secret )
+                "INSERT INTO proofs (keyset_id, amount, C, secret, dleq, time_created) VALUES ($1, $2, $3, $4, $5, CURRENT_TIMESTAMP);",
+            proof.keyset_id,amount, c, proof.secret, dleq )
             .execute(&mut **tx)
             .await?;
         }
@@ -72,21 +74,24 @@ impl LocalStore for SqliteLocalStore {
         &self,
         tx: &mut sqlx::Transaction<Self::DB>,
     ) -> Result<Proofs, MokshaWalletError> {
-        let rows = sqlx::query!("SELECT keyset_id, amount, C, secret FROM proofs;")
+        let rows = sqlx::query!("SELECT keyset_id, amount, C, secret, dleq FROM proofs;")
             .fetch_all(&mut **tx)
             .await?;
 
         // FIXME read time_created
         Ok(rows
             .into_iter()
-            .map(|row| Proof {
-                keyset_id: row.keyset_id,
-                amount: row.amount as u64,
-                c: row.C.parse().expect("Invalid Pubkey"),
-                secret: row.secret,
-                script: None,
+            .map(|row| {
+                Ok(Proof {
+                    keyset_id: row.keyset_id,
+                    amount: row.amount as u64,
+                    c: row.C.parse().expect("Invalid Pubkey"),
+                    secret: row.secret,
+                    witness: None,
+                    dleq: row.dleq.as_deref().map(serde_json::from_str).transpose()?,
+                })
             })
-            .collect::<Vec<Proof>>()
+            .collect::<Result<Vec<Proof>, MokshaWalletError>>()?
             .into())
     }
 
@@ -100,10 +105,11 @@ impl LocalStore for SqliteLocalStore {
         let currency_unit = keyset.currency_unit.to_string();
         let last_index = keyset.last_index as i64;
         let public_keys = serde_json::to_string(&keyset.public_keys)?;
+        let input_fee_ppk = keyset.input_fee_ppk as i64;
         sqlx::query!(
-            r#"INSERT INTO keysets (keyset_id, mint_url, currency_unit, last_index, public_keys, active) VALUES ($1, $2, $3, $4, $5, $6)
-            ON CONFLICT(keyset_id, mint_url) DO UPDATE SET currency_unit = $3, public_keys = $5, active = $6;
-            "#,keyset_id, mint_url, currency_unit, last_index, public_keys, keyset.active)
+            r#"INSERT INTO keysets (keyset_id, mint_url, currency_unit, last_index, public_keys, active, input_fee_ppk) VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT(keyset_id, mint_url) DO UPDATE SET currency_unit = $3, public_keys = $5, active = $6, input_fee_ppk = $7;
+            "#,keyset_id, mint_url, currency_unit, last_index, public_keys, keyset.active, input_fee_ppk)
         .execute(&mut **tx)
         .await?;
         Ok(())
@@ -113,7 +119,7 @@ impl LocalStore for SqliteLocalStore {
         &self,
         tx: &mut sqlx::Transaction<Self::DB>,
     ) -> Result<Vec<WalletKeyset>, MokshaWalletError> {
-        let rows = sqlx::query!("SELECT id, mint_url, keyset_id, currency_unit, active, last_index, public_keys FROM keysets;")
+        let rows = sqlx::query!("SELECT id, mint_url, keyset_id, currency_unit, active, last_index, public_keys, input_fee_ppk FROM keysets;")
             .fetch_all(&mut **tx)
             .await?;
 
@@ -127,6 +133,7 @@ impl LocalStore for SqliteLocalStore {
                 let currency_unit: String = row.currency_unit.clone();
                 let active: bool = row.active;
                 let last_index: i64 = row.last_index;
+                let input_fee_ppk: i64 = row.input_fee_ppk;
                 let public_keys: String = row.public_keys.clone();
                 let public_keys: HashMap<u64, PublicKey> =
                     serde_json::from_str(&public_keys).expect("invalid json in localstore");
@@ -134,10 +141,13 @@ impl LocalStore for SqliteLocalStore {
                     id: Some(id as u64),
                     mint_url,
                     keyset_id,
-                    currency_unit: currency_unit.into(),
+                    currency_unit: currency_unit
+                        .parse()
+                        .expect("invalid currency_unit in localstore"),
                     active,
                     last_index: last_index as u64,
                     public_keys,
+                    input_fee_ppk: input_fee_ppk as u64,
                 })
             })
             .collect::<Result<Vec<WalletKeyset>, SqliteError>>()?)
@@ -188,6 +198,33 @@ impl LocalStore for SqliteLocalStore {
             _ => Err(MokshaWalletError::MultipleSeeds),
         }
     }
+
+    async fn add_receive_key(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        secret_key: &SecretKey,
+    ) -> Result<(), MokshaWalletError> {
+        let secret_key = secret_key.display_secret().to_string();
+        sqlx::query!(
+            "INSERT INTO receive_key (id, secret_key) VALUES (1, $1);",
+            secret_key
+        )
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_receive_key(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Option<SecretKey>, MokshaWalletError> {
+        let row = sqlx::query!("SELECT secret_key FROM receive_key WHERE id = 1;")
+            .fetch_optional(&mut **tx)
+            .await?;
+        Ok(row
+            .map(|row| SecretKey::from_str(&row.secret_key))
+            .transpose()?)
+    }
 }
 
 impl SqliteLocalStore {
@@ -238,6 +275,20 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_receive_key() -> anyhow::Result<()> {
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let mut tx = localstore.begin_tx().await?;
+        assert!(localstore.get_receive_key(&mut tx).await?.is_none());
+
+        let key = secp256k1::SecretKey::from_slice(&[7; 32])?;
+        localstore.add_receive_key(&mut tx, &key).await?;
+        assert_eq!(Some(key), localstore.get_receive_key(&mut tx).await?);
+        assert!(localstore.add_receive_key(&mut tx, &key).await.is_err());
+        tx.commit().await?;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_delete_proofs() -> anyhow::Result<()> {
         let localstore = SqliteLocalStore::with_in_memory().await?;
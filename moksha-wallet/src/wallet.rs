@@ -2,17 +2,22 @@ use moksha_core::{
     amount::Amount,
     blind::{BlindedMessage, BlindedSignature, BlindingFactor, TotalAmount},
     dhke::Dhke,
-    keyset::KeysetId,
+    keyset::{input_fee, KeysetId},
     primitives::{
         CurrencyUnit, MintInfoResponse, PaymentMethod, PostMeltBolt11Response,
         PostMeltBtcOnchainResponse, PostMeltQuoteBolt11Response, PostMeltQuoteBtcOnchainResponse,
-        PostMintQuoteBolt11Response, PostMintQuoteBtcOnchainResponse,
+        PostMintQuoteBolt11Response, PostMintQuoteBtcOnchainResponse, State,
     },
-    proof::{Proof, Proofs},
-    token::TokenV3,
+    proof::{Proof, ProofDleq, Proofs},
+    secret::{
+        HTLCConditions, P2PKConditions, SigFlag, SpendingConditions, WellKnownSecret, Witness,
+    },
+    token::{Token, TokenV3},
 };
 
-use secp256k1::PublicKey;
+use bitcoin_hashes::sha256;
+use rand::Rng;
+use secp256k1::{PublicKey, SecretKey};
 use url::Url;
 
 use crate::{
@@ -25,7 +30,7 @@ use crate::{
 use lightning_invoice::Bolt11Invoice as LNInvoice;
 use moksha_core::primitives::{
     BillKeys, CheckBitcreditQuoteResponse, PostMintQuoteBitcreditResponse,
-    PostRequestToMintBitcreditResponse,
+    PostRequestToMintBitcreditRequest, PostRequestToMintBitcreditResponse,
 };
 use std::{
     collections::{HashMap, HashSet},
@@ -52,6 +57,7 @@ where
 {
     client: Option<C>,
     localstore: Option<L>,
+    seed_words: Option<String>,
 }
 
 impl<L, C> WalletBuilder<L, C>
@@ -63,6 +69,7 @@ where
         Self {
             client: Some(C::default()),
             localstore: None,
+            seed_words: None,
         }
     }
 
@@ -76,16 +83,32 @@ where
         self
     }
 
+    /// Uses the given seed words instead of generating a random seed (e.g. for restoring a wallet)
+    pub fn with_seed_words(mut self, seed_words: String) -> Self {
+        self.seed_words = Some(seed_words);
+        self
+    }
+
     pub async fn build(self) -> Result<Wallet<L, C>, MokshaWalletError> {
         let client = self.client.unwrap_or_default();
         let localstore = self.localstore.expect("localstore is required");
 
         let mut tx = localstore.begin_tx().await?;
         let seed_words = localstore.get_seed(&mut tx).await?;
-        let seed = match seed_words {
-            Some(seed) => seed,
-            None => {
-                let seed = DeterministicSecret::generate_random_seed_words()?;
+        let seed = match (seed_words, self.seed_words) {
+            (Some(seed), None) => seed,
+            (Some(seed), Some(requested)) => {
+                if seed != requested {
+                    return Err(MokshaWalletError::SeedMismatch);
+                }
+                seed
+            }
+            (None, requested) => {
+                let seed = match requested {
+                    Some(seed) => seed,
+                    None => DeterministicSecret::generate_random_seed_words()?,
+                };
+                DeterministicSecret::from_seed_words(&seed)?;
                 localstore.add_seed(&mut tx, &seed).await?;
                 seed
             }
@@ -151,6 +174,17 @@ where
             .await
     }
 
+    /// Accepts the amount the mint offered in the quote
+    pub async fn accept_quote_bitcredit(
+        &self,
+        mint_url: &Url,
+        quote: String,
+    ) -> Result<PostMintQuoteBitcreditResponse, MokshaWalletError> {
+        self.client
+            .post_accept_mint_quote_bitcredit(mint_url, quote)
+            .await
+    }
+
     pub async fn check_bitcredit_quote(
         &self,
         mint_url: &Url,
@@ -172,6 +206,7 @@ where
             .await
     }
 
+    /// Sends the keys of the bill to the mint, signed by the node key of the holder
     pub async fn send_request_to_mint_bitcredit(
         &self,
         mint_url: &Url,
@@ -179,9 +214,17 @@ where
         bill_keys: BillKeys,
         maturity_date: i64,
         bill_amount: u64,
+        node_key: &SecretKey,
     ) -> Result<PostRequestToMintBitcreditResponse, MokshaWalletError> {
+        let request = PostRequestToMintBitcreditRequest::new_signed(
+            bill_id,
+            bill_keys,
+            maturity_date,
+            bill_amount,
+            node_key,
+        );
         self.client
-            .post_request_to_mint_bitcredit(mint_url, bill_id, bill_keys, maturity_date, bill_amount)
+            .post_request_to_mint_bitcredit(mint_url, request)
             .await
     }
 
@@ -289,14 +332,17 @@ where
                 }
             };
 
-            let wallet_keyset = WalletKeyset::new(
-                &keyset_id,
-                mint_url,
-                &keyset.unit,
-                0,
-                public_keys,
-                keyset.active,
-            );
+            let wallet_keyset = WalletKeyset {
+                input_fee_ppk: keyset.input_fee_ppk,
+                ..WalletKeyset::new(
+                    &keyset_id,
+                    mint_url,
+                    &keyset.unit,
+                    0,
+                    public_keys,
+                    keyset.active,
+                )
+            };
 
             result.push(wallet_keyset.clone());
             self.localstore
@@ -355,14 +401,17 @@ where
             };
 
             //this is test data
-            let wallet_keyset = WalletKeyset::new(
-                &keyset_id,
-                mint_url,
-                &keyset.unit,
-                0,
-                public_keys,
-                keyset.active,
-            );
+            let wallet_keyset = WalletKeyset {
+                input_fee_ppk: keyset.input_fee_ppk,
+                ..WalletKeyset::new(
+                    &keyset_id,
+                    mint_url,
+                    &keyset.unit,
+                    0,
+                    public_keys,
+                    keyset.active,
+                )
+            };
 
             result.push(wallet_keyset.clone());
             self.localstore
@@ -380,10 +429,216 @@ where
         Ok(total_amount)
     }
 
+    /// Asks the mint which of the given proofs are already spent (Nut-07) and returns them
+    pub async fn check_proofs_spent(
+        &self,
+        mint_url: &Url,
+        proofs: &Proofs,
+    ) -> Result<Proofs, MokshaWalletError> {
+        let proofs = proofs.proofs();
+        let ys = proofs
+            .iter()
+            .map(|proof| proof.y())
+            .collect::<Result<Vec<PublicKey>, _>>()?;
+
+        let response = self.client.post_checkstate(mint_url, ys.clone()).await?;
+        let spent = response
+            .states
+            .into_iter()
+            .filter(|state| state.state == State::Spent)
+            .map(|state| state.y)
+            .collect::<HashSet<PublicKey>>();
+
+        Ok(proofs
+            .into_iter()
+            .zip(ys)
+            .filter(|(_, y)| spent.contains(y))
+            .map(|(proof, _)| proof)
+            .collect::<Vec<Proof>>()
+            .into())
+    }
+
+    /// Restores the proofs of all known keysets from the wallet seed (Nut-09 / Nut-13)
+    ///
+    /// Derives `batch_size` secrets at a time and asks the mint for the signatures it has issued
+    /// for them. A keyset is considered done after `max_empty_batches` batches in a row without
+    /// any signatures. Spent proofs are dropped, the remaining ones are stored and the
+    /// `last_index` of the keyset is moved past the highest counter that was found.
+    pub async fn restore(
+        &self,
+        batch_size: u32,
+        max_empty_batches: u32,
+    ) -> Result<Proofs, MokshaWalletError> {
+        let mut restored: Vec<Proof> = vec![];
+
+        for keyset in self.get_wallet_keysets().await? {
+            let mut proofs: Vec<Proof> = vec![];
+            let mut last_found: Option<u32> = None;
+            let mut empty_batches = 0;
+            let mut start = 0;
+
+            while empty_batches < max_empty_batches {
+                let secrets = self
+                    .secret
+                    .derive_range(&keyset.keyset_id, start, batch_size)?;
+                let outputs = secrets
+                    .iter()
+                    .map(|(secret, blinding_factor)| {
+                        let b_ = self.dhke.step1_alice(secret, blinding_factor)?;
+                        Ok(BlindedMessage {
+                            amount: 1,
+                            b_,
+                            id: keyset.keyset_id.to_string(),
+                            witness: None,
+                        })
+                    })
+                    .collect::<Result<Vec<BlindedMessage>, MokshaWalletError>>()?;
+
+                let response = self
+                    .client
+                    .post_restore(&keyset.mint_url, outputs.clone())
+                    .await?;
+
+                if response.signatures.is_empty() {
+                    empty_batches += 1;
+                } else {
+                    empty_batches = 0;
+
+                    let mut found_secrets = vec![];
+                    let mut found_outputs = vec![];
+                    for output in response.outputs.iter() {
+                        let Some(index) = outputs.iter().position(|o| o.b_ == output.b_) else {
+                            return Err(MokshaWalletError::UnexpectedResponse(
+                                "mint returned an unknown output".to_string(),
+                            ));
+                        };
+                        let (secret, blinding_factor) = secrets[index].clone();
+                        found_secrets.push(secret);
+                        found_outputs.push((output.clone(), blinding_factor));
+                        last_found = last_found.max(Some(start + index as u32));
+                    }
+
+                    let batch_proofs = self.create_proofs_from_blinded_signatures(
+                        &keyset.keyset_id,
+                        &keyset.public_keys,
+                        response.signatures,
+                        found_secrets,
+                        found_outputs,
+                    )?;
+                    proofs.extend(batch_proofs.proofs());
+                }
+                start += batch_size;
+            }
+
+            let Some(last_found) = last_found else {
+                continue;
+            };
+
+            let spent = self
+                .check_proofs_spent(&keyset.mint_url, &proofs.clone().into())
+                .await?;
+            let spent_secrets = spent
+                .proofs()
+                .into_iter()
+                .map(|p| p.secret)
+                .collect::<HashSet<String>>();
+
+            let mut tx = self.localstore.begin_tx().await?;
+            let known_secrets = self
+                .localstore
+                .get_proofs(&mut tx)
+                .await?
+                .proofs()
+                .into_iter()
+                .map(|p| p.secret)
+                .collect::<HashSet<String>>();
+
+            let live: Proofs = proofs
+                .into_iter()
+                .filter(|p| {
+                    !spent_secrets.contains(&p.secret) && !known_secrets.contains(&p.secret)
+                })
+                .collect::<Vec<Proof>>()
+                .into();
+
+            self.localstore.add_proofs(&mut tx, &live).await?;
+            self.localstore
+                .update_keyset_last_index(
+                    &mut tx,
+                    &WalletKeyset {
+                        last_index: keyset.last_index.max(last_found as u64),
+                        ..keyset.clone()
+                    },
+                )
+                .await?;
+            tx.commit().await?;
+
+            restored.extend(live.proofs());
+        }
+        Ok(restored.into())
+    }
+
     pub async fn send_tokens(
         &self,
         wallet_keyset: &WalletKeyset,
         amount: u64,
+    ) -> Result<TokenV3, MokshaWalletError> {
+        self.send_tokens_with_conditions(wallet_keyset, amount, None)
+            .await
+    }
+
+    /// Sends tokens that can only be redeemed with a signature of `pubkey` (Nut-11).
+    /// After `locktime` the tokens can also be redeemed by one of the `refund_keys` or by anyone if there are none.
+    pub async fn send_locked_tokens(
+        &self,
+        wallet_keyset: &WalletKeyset,
+        amount: u64,
+        pubkey: PublicKey,
+        locktime: Option<u64>,
+        refund_keys: Vec<PublicKey>,
+    ) -> Result<TokenV3, MokshaWalletError> {
+        let conditions = SpendingConditions::P2PK(P2PKConditions {
+            pubkeys: vec![pubkey],
+            n_sigs: 1,
+            sig_flag: SigFlag::SigInputs,
+            locktime,
+            refund_keys,
+        });
+        self.send_tokens_with_conditions(wallet_keyset, amount, Some(&conditions))
+            .await
+    }
+
+    /// Sends tokens that can only be redeemed with the preimage of `hash` and, if `pubkeys` is not empty,
+    /// a signature of one of the `pubkeys` (Nut-14).
+    /// After `locktime` the tokens can also be redeemed by one of the `refund_keys` or by anyone if there are none.
+    pub async fn send_htlc_tokens(
+        &self,
+        wallet_keyset: &WalletKeyset,
+        amount: u64,
+        hash: sha256::Hash,
+        pubkeys: Vec<PublicKey>,
+        locktime: Option<u64>,
+        refund_keys: Vec<PublicKey>,
+    ) -> Result<TokenV3, MokshaWalletError> {
+        let conditions = SpendingConditions::HTLC(HTLCConditions {
+            hash,
+            conditions: P2PKConditions {
+                pubkeys,
+                n_sigs: 1,
+                sig_flag: SigFlag::SigInputs,
+                locktime,
+                refund_keys,
+            },
+        });
+        self.send_tokens_with_conditions(wallet_keyset, amount, Some(&conditions))
+            .await
+    }
+
+    async fn send_tokens_with_conditions(
+        &self,
+        wallet_keyset: &WalletKeyset,
+        amount: u64,
+        conditions: Option<&SpendingConditions>,
     ) -> Result<TokenV3, MokshaWalletError> {
         let balance = self.get_balance().await?;
         if amount > balance {
@@ -398,11 +653,18 @@ where
             .proofs_by_keyset(&wallet_keyset.keyset_id);
         tx.commit().await?;
 
-        let selected_proofs = all_proofs.proofs_for_amount(amount)?;
+        let selected_proofs =
+            all_proofs.proofs_for_amount_with_fee(amount, wallet_keyset.input_fee_ppk)?;
         let selected_tokens = (wallet_keyset.mint_url.to_owned(), selected_proofs.clone()).into();
 
         let (remaining_tokens, result) = self
-            .swap_tokens(wallet_keyset, &selected_tokens, amount.into())
+            .swap_tokens_with_conditions(
+                wallet_keyset,
+                &selected_tokens,
+                amount.into(),
+                conditions,
+                None,
+            )
             .await?;
 
         let mut tx = self.localstore.begin_tx().await?;
@@ -417,14 +679,49 @@ where
         Ok(result)
     }
 
+    /// Redeems the tokens at the mint. Tokens that are locked to the receive pubkey of this wallet are signed automatically.
     pub async fn receive_tokens(
         &self,
         wallet_keyset: &WalletKeyset,
         tokens: &TokenV3,
     ) -> Result<(), MokshaWalletError> {
-        let total_amount = tokens.total_amount();
+        self.receive_tokens_with_preimage(wallet_keyset, tokens, None)
+            .await
+    }
+
+    /// Redeems HTLC tokens (Nut-14) by adding the hex encoded `preimage` to all proofs whose hash lock it unlocks
+    pub async fn receive_htlc_tokens(
+        &self,
+        wallet_keyset: &WalletKeyset,
+        tokens: &TokenV3,
+        preimage: &str,
+    ) -> Result<(), MokshaWalletError> {
+        self.receive_tokens_with_preimage(wallet_keyset, tokens, Some(preimage))
+            .await
+    }
+
+    async fn receive_tokens_with_preimage(
+        &self,
+        wallet_keyset: &WalletKeyset,
+        tokens: &TokenV3,
+        preimage: Option<&str>,
+    ) -> Result<(), MokshaWalletError> {
+        self.verify_dleq_proofs(tokens).await?;
+        let fee = self.input_fee(wallet_keyset, &tokens.proofs()).await?;
+        let total_amount = tokens
+            .total_amount()
+            .checked_sub(fee)
+            .ok_or(MokshaWalletError::NotEnoughTokens)?;
+        let receive_key = self.get_receive_key().await?;
+        let (tokens, sign_outputs) = self.unlock_proofs(tokens, &receive_key, preimage)?;
         let (_, redeemed_tokens) = self
-            .swap_tokens(wallet_keyset, tokens, total_amount.into())
+            .swap_tokens_with_conditions(
+                wallet_keyset,
+                &tokens,
+                total_amount.into(),
+                None,
+                sign_outputs.then_some(&receive_key),
+            )
             .await?;
         let mut tx = self.localstore.begin_tx().await?;
         self.localstore
@@ -434,6 +731,114 @@ where
         Ok(())
     }
 
+    /// Verifies the DLEQ proofs of the tokens offline (Nut-12). Proofs without a DLEQ proof or
+    /// from keysets that are unknown to the wallet can only be checked by the mint.
+    pub async fn verify_dleq_proofs(&self, tokens: &TokenV3) -> Result<(), MokshaWalletError> {
+        let keysets = self.get_wallet_keysets().await?;
+        for proof in tokens.proofs().proofs() {
+            let Some(dleq) = &proof.dleq else {
+                continue;
+            };
+            let Some(keyset) = keysets
+                .iter()
+                .find(|keyset| keyset.keyset_id.to_string() == proof.keyset_id)
+            else {
+                continue;
+            };
+            let key = keyset
+                .public_keys
+                .get(&proof.amount)
+                .ok_or(MokshaWalletError::PubkeyNotFound)?;
+            if !self
+                .dhke
+                .verify_proof_dleq(proof.secret.clone(), proof.c, *key, dleq)?
+            {
+                return Err(MokshaWalletError::InvalidDleq(proof.amount));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the pubkey other wallets can lock tokens to (Nut-11)
+    pub async fn get_receive_pubkey(&self) -> Result<PublicKey, MokshaWalletError> {
+        let key = self.get_receive_key().await?;
+        Ok(key.public_key(&secp256k1::Secp256k1::new()))
+    }
+
+    async fn get_receive_key(&self) -> Result<SecretKey, MokshaWalletError> {
+        let mut tx = self.localstore.begin_tx().await?;
+        let key = match self.localstore.get_receive_key(&mut tx).await? {
+            Some(key) => key,
+            None => {
+                let key = SecretKey::from_slice(&rand::thread_rng().gen::<[u8; 32]>())?;
+                self.localstore.add_receive_key(&mut tx, &key).await?;
+                key
+            }
+        };
+        tx.commit().await?;
+        Ok(key)
+    }
+
+    /// Signs all proofs that are locked to the given key and adds the preimage to all HTLC proofs it unlocks.
+    /// Returns the unlocked tokens and whether the outputs of the swap have to be signed too (`SIG_ALL`).
+    fn unlock_proofs(
+        &self,
+        tokens: &TokenV3,
+        key: &SecretKey,
+        preimage: Option<&str>,
+    ) -> Result<(TokenV3, bool), MokshaWalletError> {
+        let pubkey = key.public_key(&secp256k1::Secp256k1::new());
+        let mut sign_outputs = false;
+
+        let signed = tokens
+            .tokens
+            .iter()
+            .map(|token| {
+                let proofs = token
+                    .proofs
+                    .proofs()
+                    .into_iter()
+                    .map(|mut proof| {
+                        let Ok(secret) = WellKnownSecret::from_str(&proof.secret) else {
+                            return Ok(proof);
+                        };
+                        let spending_conditions = SpendingConditions::try_from(&secret)?;
+                        let mut witness = proof.witness.take().unwrap_or_default();
+                        if let (SpendingConditions::HTLC(htlc), Some(preimage)) =
+                            (&spending_conditions, preimage)
+                        {
+                            if htlc.verify_preimage(preimage) {
+                                witness.preimage = Some(preimage.to_owned());
+                            }
+                        }
+
+                        let conditions = spending_conditions.signature_conditions();
+                        if conditions.pubkeys.contains(&pubkey)
+                            || conditions.refund_keys.contains(&pubkey)
+                        {
+                            sign_outputs |= conditions.sig_flag == SigFlag::SigAll;
+                            witness.sign(proof.secret.as_bytes(), key);
+                        }
+                        proof.witness = (witness != Witness::default()).then_some(witness);
+                        Ok(proof)
+                    })
+                    .collect::<Result<Vec<Proof>, MokshaWalletError>>()?;
+                Ok(Token {
+                    mint: token.mint.clone(),
+                    proofs: proofs.into(),
+                })
+            })
+            .collect::<Result<Vec<Token>, MokshaWalletError>>()?;
+
+        Ok((
+            TokenV3 {
+                tokens: signed,
+                ..tokens.clone()
+            },
+            sign_outputs,
+        ))
+    }
+
     pub async fn get_mint_quote(
         &self,
         mint_url: &Url,
@@ -466,12 +871,16 @@ where
         let all_proofs = self.localstore.get_proofs(&mut tx).await?;
         tx.commit().await?;
 
-        let ln_amount = Self::get_invoice_amount(&invoice)? + melt_quote.fee_reserve;
+        let ln_amount = Self::amount_with_input_fee(
+            Self::get_invoice_amount(&invoice)? + melt_quote.fee_reserve,
+            wallet_keyset.input_fee_ppk,
+        );
 
         if ln_amount > all_proofs.total_amount() {
             return Err(MokshaWalletError::NotEnoughTokens);
         }
-        let selected_proofs = all_proofs.proofs_for_amount(ln_amount)?;
+        let selected_proofs =
+            all_proofs.proofs_for_amount_with_fee(ln_amount, wallet_keyset.input_fee_ppk)?;
 
         let total_proofs = {
             let selected_tokens =
@@ -565,12 +974,16 @@ where
         let all_proofs = self.localstore.get_proofs(&mut tx).await?;
         tx.commit().await?;
 
-        let ln_amount = melt_quote.amount + melt_quote.fee;
+        let ln_amount = Self::amount_with_input_fee(
+            melt_quote.amount + melt_quote.fee,
+            wallet_keyset.input_fee_ppk,
+        );
 
         if ln_amount > all_proofs.total_amount() {
             return Err(MokshaWalletError::NotEnoughTokens);
         }
-        let selected_proofs = all_proofs.proofs_for_amount(ln_amount)?;
+        let selected_proofs =
+            all_proofs.proofs_for_amount_with_fee(ln_amount, wallet_keyset.input_fee_ppk)?;
 
         let mut tx = self.localstore.begin_tx().await?;
         let total_proofs = {
@@ -641,8 +1054,26 @@ where
         tokens: &TokenV3,
         splt_amount: Amount,
     ) -> Result<(TokenV3, TokenV3), MokshaWalletError> {
+        self.swap_tokens_with_conditions(wallet_keyset, tokens, splt_amount, None, None)
+            .await
+    }
+
+    /// Swaps the tokens like `swap_tokens`, but locks the second part (`splt_amount`) to the given
+    /// conditions and signs all outputs with `output_key` (required if the inputs use `SIG_ALL`)
+    async fn swap_tokens_with_conditions(
+        &self,
+        wallet_keyset: &WalletKeyset,
+        tokens: &TokenV3,
+        splt_amount: Amount,
+        conditions: Option<&SpendingConditions>,
+        output_key: Option<&SecretKey>,
+    ) -> Result<(TokenV3, TokenV3), MokshaWalletError> {
+        let fee = self.input_fee(wallet_keyset, &tokens.proofs()).await?;
         let total_token_amount = tokens.total_amount();
-        let first_amount: Amount = (total_token_amount - splt_amount.0).into();
+        let first_amount: Amount = total_token_amount
+            .checked_sub(fee + splt_amount.0)
+            .ok_or(MokshaWalletError::NotEnoughTokens)?
+            .into();
         let first_secrets = self
             .create_secrets(&wallet_keyset.keyset_id, first_amount.split().len() as u32)
             .await?;
@@ -658,6 +1089,16 @@ where
         let second_secrets = self
             .create_secrets(&wallet_keyset.keyset_id, second_amount.split().len() as u32)
             .await?;
+        let second_secrets = match conditions {
+            // the deterministic secret is used as nonce of the locked secret
+            Some(conditions) => second_secrets
+                .into_iter()
+                .map(|(nonce, blinding_factor)| {
+                    Ok((conditions.to_secret(nonce).try_into()?, blinding_factor))
+                })
+                .collect::<Result<Vec<(String, BlindingFactor)>, MokshaWalletError>>()?,
+            None => second_secrets,
+        };
         let second_outputs = self.create_blinded_messages(
             &wallet_keyset.keyset_id,
             second_amount,
@@ -668,7 +1109,15 @@ where
         total_outputs.extend(get_blinded_msg(first_outputs.clone()));
         total_outputs.extend(get_blinded_msg(second_outputs.clone()));
 
-        if tokens.total_amount() != total_outputs.total_amount() {
+        if let Some(key) = output_key {
+            for output in total_outputs.iter_mut() {
+                let mut witness = Witness::default();
+                witness.sign(output.b_.to_string().as_bytes(), key);
+                output.witness = Some(witness);
+            }
+        }
+
+        if tokens.total_amount() - fee != total_outputs.total_amount() {
             return Err(MokshaWalletError::InvalidProofs);
         }
 
@@ -710,10 +1159,12 @@ where
         )
             .into();
 
-        if tokens.total_amount() != first_tokens.total_amount() + second_tokens.total_amount() {
+        if tokens.total_amount() - fee != first_tokens.total_amount() + second_tokens.total_amount()
+        {
             println!(
-                "Error in swap: input {:?} != output {:?} + {:?}",
+                "Error in swap: input {:?} - fee {:?} != output {:?} + {:?}",
                 tokens.total_amount(),
+                fee,
                 first_tokens.total_amount(),
                 second_tokens.total_amount()
             );
@@ -722,6 +1173,47 @@ where
         Ok((first_tokens, second_tokens))
     }
 
+    /// Returns the fee the mint charges for spending the proofs (Nut-02). Proofs of keysets
+    /// that are unknown to the wallet are charged with the fee of `wallet_keyset`.
+    async fn input_fee(
+        &self,
+        wallet_keyset: &WalletKeyset,
+        proofs: &Proofs,
+    ) -> Result<u64, MokshaWalletError> {
+        let mut tx = self.localstore.begin_tx().await?;
+        let keysets = self.localstore.get_keysets(&mut tx).await?;
+        tx.commit().await?;
+
+        let sum_fee_ppk = proofs
+            .proofs()
+            .iter()
+            .map(|proof| {
+                keysets
+                    .iter()
+                    .find(|k| {
+                        k.mint_url == wallet_keyset.mint_url
+                            && k.keyset_id.to_string() == proof.keyset_id
+                    })
+                    .unwrap_or(wallet_keyset)
+                    .input_fee_ppk
+            })
+            .sum();
+        Ok(input_fee(sum_fee_ppk))
+    }
+
+    /// Returns the amount needed to spend `amount` after the proofs created for it have been
+    /// charged with the input fee
+    fn amount_with_input_fee(amount: u64, input_fee_ppk: u64) -> u64 {
+        let mut total = amount;
+        loop {
+            let fee = input_fee(Amount(total).split().len() as u64 * input_fee_ppk);
+            if total >= amount + fee {
+                return total;
+            }
+            total = amount + fee;
+        }
+    }
+
     pub async fn get_mint_info(
         &self,
         mint_url: &Url,
@@ -786,6 +1278,7 @@ where
                         amount,
                         b_,
                         id: wallet_keyset.keyset_id.to_string(), // FIXME use keyset_id
+                        witness: None,
                     },
                     blinding_factor,
                     secret,
@@ -890,6 +1383,7 @@ where
                         amount: 1,
                         b_,
                         id: keyset_id.to_string(),
+                        witness: None,
                     },
                     blinding_factor,
                     secret,
@@ -918,6 +1412,7 @@ where
                         amount,
                         b_,
                         id: keyset_id.to_string(),
+                        witness: None,
                     },
                     blinding_factor,
                 ))
@@ -935,28 +1430,32 @@ where
     ) -> Result<Proofs, MokshaWalletError> {
         let current_keyset_id = keyset_id.to_string(); // FIXME
 
-        let blinding_factors = outputs
-            .into_iter()
-            .map(|(_, secret)| secret)
-            .collect::<Vec<BlindingFactor>>();
-
         Ok(signatures
             .iter()
-            .zip(blinding_factors)
+            .zip(outputs)
             .zip(secrets)
-            .map(|((p, blinding_factor), secret)| {
+            .map(|((p, (output, blinding_factor)), secret)| {
                 let key = pub_keys
                     .get(&p.amount)
                     .ok_or(MokshaWalletError::PubkeyNotFound)?;
-                let pub_alice = self
-                    .dhke
-                    .step3_alice(p.c_, blinding_factor.to_owned(), *key)?;
-                Ok(Proof::new(
-                    p.amount,
-                    secret,
-                    pub_alice,
-                    current_keyset_id.clone(),
-                ))
+                let dleq = match &p.dleq {
+                    Some(dleq) => {
+                        if !self.dhke.verify_dleq(output.b_, p.c_, *key, dleq)? {
+                            return Err(MokshaWalletError::InvalidDleq(p.amount));
+                        }
+                        Some(ProofDleq {
+                            e: dleq.e,
+                            s: dleq.s,
+                            r: blinding_factor.to_secret_key(),
+                        })
+                    }
+                    None => None,
+                };
+                let pub_alice = self.dhke.step3_alice(p.c_, blinding_factor, *key)?;
+                Ok(Proof {
+                    dleq,
+                    ..Proof::new(p.amount, secret, pub_alice, current_keyset_id.clone())
+                })
             })
             .collect::<Result<Vec<_>, MokshaWalletError>>()?
             .into())
@@ -985,17 +1484,29 @@ mod tests {
     use crate::client::MockCashuClient;
     use crate::localstore::sqlite::SqliteLocalStore;
     use crate::localstore::{LocalStore, WalletKeyset};
+    use crate::secret::DeterministicSecret;
     use crate::wallet::WalletBuilder;
 
+    use moksha_core::blind::{BlindedMessage, BlindedSignature, TotalAmount};
+    use moksha_core::dhke::Dhke;
+
     use moksha_core::fixture::{read_fixture, read_fixture_as};
     use moksha_core::keyset::{KeysetId, Keysets, MintKeyset};
     use moksha_core::primitives::{
-        CurrencyUnit, KeyResponse, KeysResponse, PaymentMethod, PostMeltBolt11Response,
-        PostMeltQuoteBolt11Response, PostMintBolt11Response, PostSwapResponse,
+        CurrencyUnit, KeyResponse, KeysResponse, PaymentMethod, PostCheckStateResponse,
+        PostMeltBolt11Response, PostMeltQuoteBolt11Response, PostMintBolt11Response,
+        PostRestoreResponse, PostSwapResponse, ProofState, State,
     };
 
+    use crate::error::MokshaWalletError;
+    use bitcoin_hashes::{sha256, Hash};
+    use moksha_core::proof::Proof;
+    use moksha_core::secret::{
+        HTLCConditions, P2PKConditions, SigFlag, SpendingConditions, WellKnownSecret,
+    };
     use moksha_core::token::TokenV3;
     use secp256k1::PublicKey;
+    use std::str::FromStr;
     use url::Url;
 
     fn create_mock() -> MockCashuClient {
@@ -1173,6 +1684,356 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_send_locked_tokens() -> anyhow::Result<()> {
+        let split_response = read_fixture_as::<PostSwapResponse>("post_swap_response_24_40.json")?;
+        let mut client = create_mock();
+        client
+            .expect_post_swap()
+            .returning(move |_, _, _| Ok(split_response.clone()));
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let keyset = create_test_wallet_keyset()?;
+        let tokens: TokenV3 = read_fixture("token_64.cashu")?.try_into()?;
+        let proofs = tokens
+            .proofs()
+            .proofs()
+            .into_iter()
+            .map(|proof| Proof {
+                keyset_id: keyset.keyset_id.to_string(),
+                ..proof
+            })
+            .collect::<Vec<Proof>>()
+            .into();
+        let mut tx = localstore.begin_tx().await?;
+        localstore.upsert_keyset(&mut tx, &keyset).await?;
+        localstore.add_proofs(&mut tx, &proofs).await?;
+        tx.commit().await?;
+
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore)
+            .build()
+            .await?;
+
+        let pubkey = PublicKey::from_str(
+            "02698c4e2b5f9534cd0687d87513c759790cf829aa5739184a3e3735471fbda904",
+        )?;
+        let keyset = wallet.get_wallet_keysets().await?[0].clone();
+        let result = wallet
+            .send_locked_tokens(&keyset, 44, pubkey, Some(1_700_000_000), vec![])
+            .await?;
+
+        assert_eq!(44, result.total_amount());
+        for proof in result.proofs().proofs() {
+            let conditions = P2PKConditions::try_from(&WellKnownSecret::from_str(&proof.secret)?)?;
+            assert_eq!(vec![pubkey], conditions.pubkeys);
+            assert_eq!(Some(1_700_000_000), conditions.locktime);
+        }
+        assert_eq!(20, wallet.get_balance().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_send_tokens_input_fee() -> anyhow::Result<()> {
+        let split_response = read_fixture_as::<PostSwapResponse>("post_swap_response_24_40.json")?;
+        let mut client = create_mock();
+        // one input of 64 with a fee of 1
+        client
+            .expect_post_swap()
+            .withf(|_, proofs, outputs| proofs.total_amount() == 64 && outputs.total_amount() == 63)
+            .returning(move |_, _, _| Ok(split_response.clone()));
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let keyset = WalletKeyset {
+            input_fee_ppk: 1_000,
+            ..create_test_wallet_keyset()?
+        };
+        let tokens: TokenV3 = read_fixture("token_64.cashu")?.try_into()?;
+        let proofs = tokens
+            .proofs()
+            .proofs()
+            .into_iter()
+            .map(|proof| Proof {
+                keyset_id: keyset.keyset_id.to_string(),
+                ..proof
+            })
+            .collect::<Vec<Proof>>()
+            .into();
+        let mut tx = localstore.begin_tx().await?;
+        localstore.upsert_keyset(&mut tx, &keyset).await?;
+        localstore.add_proofs(&mut tx, &proofs).await?;
+        tx.commit().await?;
+
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore)
+            .build()
+            .await?;
+
+        let keyset = wallet.get_wallet_keysets().await?[0].clone();
+        assert_eq!(1_000, keyset.input_fee_ppk);
+        wallet.send_tokens(&keyset, 43).await?;
+
+        let result = wallet.send_tokens(&keyset, 64).await;
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_receive_locked_tokens() -> anyhow::Result<()> {
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let keyset = create_test_wallet_keyset()?;
+        let receive_key = secp256k1::SecretKey::from_slice(&[7; 32])?;
+        let pubkey = receive_key.public_key(&secp256k1::Secp256k1::new());
+        let mut tx = localstore.begin_tx().await?;
+        localstore.upsert_keyset(&mut tx, &keyset).await?;
+        localstore.add_receive_key(&mut tx, &receive_key).await?;
+        tx.commit().await?;
+
+        let split_response = read_fixture_as::<PostSwapResponse>("post_swap_response_24_40.json")?;
+        let mut client = create_mock();
+        client
+            .expect_post_swap()
+            .withf(move |_, proofs, _| {
+                proofs.proofs().iter().all(|proof| {
+                    proof.witness.as_ref().is_some_and(|witness| {
+                        witness.count_valid_signatures(proof.secret.as_bytes(), &[pubkey]) == 1
+                    })
+                })
+            })
+            .returning(move |_, _, _| Ok(split_response.clone()));
+
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore)
+            .build()
+            .await?;
+        assert_eq!(pubkey, wallet.get_receive_pubkey().await?);
+
+        let conditions = P2PKConditions {
+            pubkeys: vec![pubkey],
+            n_sigs: 1,
+            sig_flag: SigFlag::SigInputs,
+            locktime: None,
+            refund_keys: vec![],
+        };
+        let mut tokens: TokenV3 = read_fixture("token_64.cashu")?.try_into()?;
+        for token in tokens.tokens.iter_mut() {
+            token.proofs = token
+                .proofs
+                .proofs()
+                .into_iter()
+                .map(|proof| {
+                    let secret = conditions.to_secret(proof.secret.clone());
+                    Ok(Proof {
+                        secret: secret.try_into()?,
+                        ..proof
+                    })
+                })
+                .collect::<anyhow::Result<Vec<Proof>>>()?
+                .into();
+        }
+
+        let keyset = wallet.get_wallet_keysets().await?[0].clone();
+        wallet.receive_tokens(&keyset, &tokens).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_receive_htlc_tokens() -> anyhow::Result<()> {
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let keyset = create_test_wallet_keyset()?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore.upsert_keyset(&mut tx, &keyset).await?;
+        tx.commit().await?;
+
+        let preimage = "00".repeat(32);
+        let expected_preimage = preimage.clone();
+        let split_response = read_fixture_as::<PostSwapResponse>("post_swap_response_24_40.json")?;
+        let mut client = create_mock();
+        client
+            .expect_post_swap()
+            .withf(move |_, proofs, _| {
+                proofs.proofs().iter().all(|proof| {
+                    proof.witness.as_ref().is_some_and(|witness| {
+                        witness.preimage.as_ref() == Some(&expected_preimage)
+                            && witness.signatures.is_empty()
+                    })
+                })
+            })
+            .returning(move |_, _, _| Ok(split_response.clone()));
+
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore)
+            .build()
+            .await?;
+
+        let conditions = SpendingConditions::HTLC(HTLCConditions {
+            hash: sha256::Hash::hash(&[0; 32]),
+            conditions: P2PKConditions {
+                pubkeys: vec![],
+                n_sigs: 1,
+                sig_flag: SigFlag::SigInputs,
+                locktime: None,
+                refund_keys: vec![],
+            },
+        });
+        let mut tokens: TokenV3 = read_fixture("token_64.cashu")?.try_into()?;
+        for token in tokens.tokens.iter_mut() {
+            token.proofs = token
+                .proofs
+                .proofs()
+                .into_iter()
+                .map(|proof| {
+                    let secret = conditions.to_secret(proof.secret.clone());
+                    Ok(Proof {
+                        secret: secret.try_into()?,
+                        ..proof
+                    })
+                })
+                .collect::<anyhow::Result<Vec<Proof>>>()?
+                .into();
+        }
+
+        let keyset = wallet.get_wallet_keysets().await?[0].clone();
+        wallet
+            .receive_htlc_tokens(&keyset, &tokens, &preimage)
+            .await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_check_proofs_spent() -> anyhow::Result<()> {
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        let proofs = tokens.proofs();
+        let spent_y = proofs.proofs()[0].y()?;
+
+        let mut client = create_mock();
+        client.expect_post_checkstate().returning(move |_, ys| {
+            Ok(PostCheckStateResponse {
+                states: ys
+                    .into_iter()
+                    .map(|y| ProofState {
+                        y,
+                        state: if y == spent_y {
+                            State::Spent
+                        } else {
+                            State::Unspent
+                        },
+                        witness: None,
+                    })
+                    .collect(),
+            })
+        });
+
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(SqliteLocalStore::with_in_memory().await?)
+            .build()
+            .await?;
+
+        let mint_url = Url::parse("http://localhost:8080/")?;
+        let result = wallet.check_proofs_spent(&mint_url, &proofs).await?;
+        assert_eq!(1, result.len());
+        assert_eq!(proofs.proofs()[0], result.proofs()[0]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_restore() -> anyhow::Result<()> {
+        let seed_words =
+            "half depart obvious quality work element tank gorilla view sugar picture humble";
+        let mint_keyset = MintKeyset::new("mykey", "");
+        let keyset_id = KeysetId::new(&mint_keyset.keyset_id)?;
+        let dhke = Dhke::new();
+
+        // the mint has signed the outputs for the counters 1, 2 and 12
+        let secrets =
+            DeterministicSecret::from_seed_words(seed_words)?.derive_range(&keyset_id, 0, 13)?;
+        let issued = [(1, 1), (2, 2), (12, 4)]
+            .into_iter()
+            .map(|(counter, amount)| {
+                let (secret, blinding_factor) = &secrets[counter];
+                (dhke.step1_alice(secret, blinding_factor).unwrap(), amount)
+            })
+            .collect::<HashMap<PublicKey, u64>>();
+
+        let mut client = create_mock();
+        client.expect_post_restore().returning(move |_, outputs| {
+            let mut response = PostRestoreResponse::default();
+            for output in outputs {
+                if let Some(amount) = issued.get(&output.b_) {
+                    let (c_, dleq) = Dhke::new()
+                        .step2_bob(output.b_, &mint_keyset.private_keys[amount])
+                        .unwrap();
+                    response.signatures.push(BlindedSignature {
+                        amount: *amount,
+                        c_,
+                        id: output.id.clone(),
+                        dleq: Some(dleq),
+                    });
+                    response.outputs.push(BlindedMessage {
+                        amount: *amount,
+                        ..output
+                    });
+                }
+            }
+            Ok(response)
+        });
+        let spent_y = Dhke::hash_to_curve(secrets[2].0.as_bytes())?;
+        client.expect_post_checkstate().returning(move |_, ys| {
+            Ok(PostCheckStateResponse {
+                states: ys
+                    .into_iter()
+                    .map(|y| ProofState {
+                        y,
+                        state: if y == spent_y {
+                            State::Spent
+                        } else {
+                            State::Unspent
+                        },
+                        witness: None,
+                    })
+                    .collect(),
+            })
+        });
+
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(SqliteLocalStore::with_in_memory().await?)
+            .with_seed_words(seed_words.to_string())
+            .build()
+            .await?;
+        let mint_url = Url::parse("http://localhost:8080/")?;
+        wallet
+            .add_mint_keysets(&mint_url, "sat".to_string())
+            .await?;
+
+        let restored = wallet.restore(5, 2).await?;
+        assert_eq!(5, restored.total_amount());
+        assert_eq!(5, wallet.get_balance().await?);
+
+        // the DLEQ proofs of the mint are kept on the proofs and can be verified offline
+        assert!(restored.proofs().iter().all(|proof| proof.dleq.is_some()));
+        let tokens: TokenV3 = (
+            mint_url.clone(),
+            CurrencyUnit::Sat,
+            wallet.get_proofs().await?,
+        )
+            .into();
+        wallet.verify_dleq_proofs(&tokens).await?;
+
+        let mut proofs = restored.proofs();
+        proofs[0].amount = 8;
+        let tokens: TokenV3 = (mint_url, CurrencyUnit::Sat, proofs.into()).into();
+        let result = wallet.verify_dleq_proofs(&tokens).await;
+        assert!(matches!(result, Err(MokshaWalletError::InvalidDleq(8))));
+
+        let keysets = wallet.get_wallet_keysets().await?;
+        assert_eq!(12, keysets[0].last_index);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_get_balance() -> anyhow::Result<()> {
         let fixture = read_fixture("token_60.cashu")?; // 60 tokens (4,8,16,32)